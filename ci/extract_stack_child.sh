@@ -25,7 +25,8 @@ if ! child=$(
     if type == "object"
       and has("child")
       and (.child == null or (.child | type == "string"))
-    then (.child // "")
+      and (.child_branch == null or (.child_branch | type == "string"))
+    then (.child_branch // .child // "")
     else error("metadata child must be a string or null")
     end
   ' <<<"$metadata"
@@ -5,22 +5,23 @@ set -euo pipefail
 action_path=${ACTION_PATH:-$(cd "$(dirname "$0")/.." && pwd)}
 merged_pr_body=${MERGED_PR_BODY-}
 
-child_id=$(
+child_branch=$(
   printf '%s\n' "$merged_pr_body" |
     bash "$action_path/ci/extract_stack_child.sh"
 )
 
-if [[ -z $child_id ]]; then
+if [[ -z $child_branch ]]; then
   echo "Merged PR has no child defined in metadata. Reached top of stack."
   exit 0
 fi
 
-echo "Merged PR indicates next child is ID: $child_id"
+echo "Merged PR indicates next child is on branch: $child_branch"
 
-# GHerrit branches use the stable ID as their exact name.
-child_pr=$(gh pr list --head "$child_id" --json number --jq '.[0].number')
+# The metadata records the child's managed branch, which is its stable ID
+# unless the repository configures a branch naming template.
+child_pr=$(gh pr list --head "$child_branch" --json number --jq '.[0].number')
 if [[ -z $child_pr ]]; then
-  echo "Error: Metadata says child is $child_id, but no open PR exists for branch '$child_id'."
+  echo "Error: Metadata says child is on $child_branch, but no open PR exists for that branch."
   echo "The chain might be broken or the child was deleted."
   exit 1
 fi
@@ -28,6 +28,7 @@ assert_rejected() {
 assert_child Gchild '<!-- gherrit-meta: {"id":"Gid","parent":null,"child":"Gchild"} -->'
 assert_child Gchild '<!-- gherrit-meta: {"id": "Gid", "parent": null, "child": "Gchild"}" -->'
 assert_child '' '<!-- gherrit-meta: {"id":"Gid","parent":null,"child":null} -->'
+assert_child gherrit/octo/Gchild '<!-- gherrit-meta: {"id":"Gid","parent":null,"child":"Gchild","child_branch":"gherrit/octo/Gchild"} -->'
 
 assert_child Gactual 'A commit-body example:
 <!-- gherrit-meta: {"id":"Gfake","parent":null,"child":"Gwrong"} -->
@@ -37,4 +38,5 @@ assert_child Gactual 'A commit-body example:
 assert_rejected 'No metadata here.'
 assert_rejected '<!-- gherrit-meta: {"id":"Gid","parent":null} -->'
 assert_rejected '<!-- gherrit-meta: not-json -->'
+assert_rejected '<!-- gherrit-meta: {"id":"Gid","parent":null,"child":"Gchild","child_branch":7} -->'
 assert_rejected '<!-- gherrit-meta: {"child":"Gone"} {"child":"Gtwo"} -->'
@@ -39,7 +39,7 @@ fn has_gherrit_id(trailers: &str) -> bool {
     trailers.lines().any(|line| line.starts_with("gherrit-pr-id: "))
 }
 
-fn derive_gherrit_id(mut entropy: IdEntropy, object_hash: &[u8]) -> String {
+pub(crate) fn derive_gherrit_id(mut entropy: IdEntropy, object_hash: &[u8]) -> String {
     assert!(!object_hash.is_empty(), "object hash must not be empty");
 
     // IDs are collision identifiers, not secrets. Mixing with XOR keeps the
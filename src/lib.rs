@@ -4,8 +4,10 @@ mod manage;
 mod pre_push;
 mod util;
 
+use std::io::{self, IsTerminal as _};
+
 use clap::{Parser, Subcommand};
-use eyre::{Result, WrapErr};
+use eyre::{Result, WrapErr, bail};
 use manage::State;
 pub(crate) use util::{cmd_macro as cmd, re_macro as re};
 
@@ -62,6 +64,101 @@ enum Commands {
         #[arg(long, short)]
         force: bool,
     },
+    /// Sync a stack to GitHub without running `git push`.
+    Sync {
+        /// The managed branch to sync instead of the current branch.
+        #[arg(long, short)]
+        branch: Option<String>,
+
+        /// Print the pushes, PR creations, and PR updates without performing
+        /// them.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Finish the PR operations of an interrupted sync without pushing
+        /// again. Fails if the stack changed since.
+        #[arg(long)]
+        resume: bool,
+
+        /// What to do with commits whose PR was closed (default: the
+        /// `gherrit.closedPrPolicy` config, or `error`).
+        #[arg(long, value_name = "POLICY")]
+        on_closed_pr: Option<pre_push::ClosedPrPolicy>,
+
+        /// What to do with open PRs whose commits were removed from the
+        /// stack (default: the `gherrit.orphanedPrPolicy` config, or `mark`).
+        #[arg(long, value_name = "POLICY")]
+        on_orphaned_pr: Option<pre_push::OrphanedPrPolicy>,
+
+        /// Push new versions of PRs in the merge queue, or change their base
+        /// branch or draft state, which removes them from the queue.
+        #[arg(long)]
+        allow_requeue: bool,
+    },
+    /// Check out the stack containing a PR as a new managed branch.
+    Checkout {
+        /// The PR's number or gherrit-pr-id.
+        target: String,
+        /// The name of the branch to create (default: `pr-<gherrit-pr-id>`).
+        #[arg(long, short)]
+        branch: Option<String>,
+    },
+    /// Merge the PRs at the bottom of the stack, or add them to the merge
+    /// queue.
+    Land {
+        /// The number of commits to land (default: 1).
+        #[arg(long, group = "limit")]
+        count: Option<usize>,
+        /// The gherrit-pr-id of the last commit to land.
+        #[arg(long, group = "limit", value_name = "GHERRIT_PR_ID")]
+        upto: Option<String>,
+        /// How to merge PRs outside of a merge queue (default: the
+        /// `gherrit.mergeMethod` config, or `merge`).
+        #[arg(long)]
+        method: Option<pre_push::land::MergeMethod>,
+    },
+    /// Close the PR of a commit dropped from its stack and delete its managed
+    /// branch.
+    Abandon {
+        /// The gherrit-pr-id of the dropped commit.
+        gherrit_id: String,
+        /// A comment to post on the PR before closing it.
+        #[arg(long, short)]
+        comment: Option<String>,
+    },
+    /// Delete the managed branches, and optionally the version tags, of merged
+    /// PRs from the remote.
+    Gc {
+        /// Print the refs to delete without deleting them.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// What to do with the version tags of finished PRs (default: the
+        /// `gherrit.gcTags` config, or `keep`).
+        #[arg(long, value_name = "POLICY")]
+        tags: Option<pre_push::gc::TagPolicy>,
+
+        /// Keep version tags of commits younger than this many days (default:
+        /// the `gherrit.gcTagAge` config, or 30).
+        #[arg(long, value_name = "DAYS")]
+        tag_age: Option<u64>,
+
+        /// Also collect the refs of closed PRs, which GitHub can then no
+        /// longer reopen.
+        #[arg(long)]
+        include_closed: bool,
+    },
+    /// Move every version ref, locally and on the remote, to another layout.
+    MigrateRefs {
+        /// The layout to move to.
+        layout: pre_push::RefLayout,
+    },
+    /// Drop landed commits from the current stack and rebase the rest onto the
+    /// updated default branch.
+    Restack,
+    /// Show the PR, version, and publication state of each commit in the
+    /// current stack.
+    Status,
     /// Install GHerrit Git hooks.
     Install {
         /// Overwrite existing hooks not managed by GHerrit
@@ -105,7 +202,30 @@ pub async fn dispatch(cli: Cli, runtime: Runtime) -> Result<()> {
     match cli.command {
         Commands::Hook(cmd) => match cmd {
             HookCommands::PrePush { .. } => {
-                pre_push::run(&repo, &runtime.github_endpoint).await?;
+                // Git writes the refs being pushed to stdin. A terminal means
+                // that the hook was run by hand, outside of a push.
+                let stdin = io::stdin();
+                let pushed_refs = if stdin.is_terminal() {
+                    String::new()
+                } else {
+                    io::read_to_string(stdin).wrap_err("Failed to read the refs being pushed")?
+                };
+
+                let mut options = pre_push::SyncOptions::from_config(
+                    &repo,
+                    pre_push::SyncMode::Apply,
+                    runtime.id_entropy,
+                )?;
+                options.allow_requeue = std::env::var_os("GHERRIT_ALLOW_REQUEUE")
+                    .is_some_and(|value| !value.is_empty());
+                // The enclosing `git push` would still publish a public
+                // branch, so a dry run must also reject it.
+                if std::env::var_os("GHERRIT_DRY_RUN").is_some_and(|value| !value.is_empty()) {
+                    options.mode = pre_push::SyncMode::DryRun;
+                    pre_push::run(&repo, &runtime.github_endpoint, &pushed_refs, options).await?;
+                    bail!("GHERRIT_DRY_RUN is set; rejecting the push after printing the plan.");
+                }
+                pre_push::run(&repo, &runtime.github_endpoint, &pushed_refs, options).await?;
             }
             HookCommands::PostCheckout { prev, new, flag } => {
                 manage::post_checkout(&repo, &prev, &new, &flag)?
@@ -129,6 +249,48 @@ pub async fn dispatch(cli: Cli, runtime: Runtime) -> Result<()> {
             manage::set_state(&repo, target_state, force)?
         }
         Commands::Unmanage { force } => manage::set_state(&repo, State::Unmanaged, force)?,
+        Commands::Sync { branch, dry_run, resume, on_closed_pr, on_orphaned_pr, allow_requeue } => {
+            let mode = if dry_run { pre_push::SyncMode::DryRun } else { pre_push::SyncMode::Apply };
+            let mut options = pre_push::SyncOptions::from_config(&repo, mode, runtime.id_entropy)?;
+            options.closed_prs = on_closed_pr.unwrap_or(options.closed_prs);
+            options.orphaned_prs = on_orphaned_pr.unwrap_or(options.orphaned_prs);
+            options.resume = resume;
+            options.allow_requeue = allow_requeue;
+            pre_push::sync::run(&repo, &runtime.github_endpoint, branch.as_deref(), options).await?
+        }
+        Commands::Checkout { target, branch } => {
+            pre_push::checkout::run(&repo, &runtime.github_endpoint, &target, branch.as_deref())
+                .await?
+        }
+        Commands::Land { count, upto, method } => {
+            let limit = match upto {
+                Some(gherrit_id) => pre_push::land::LandLimit::UpTo(gherrit_id),
+                None => pre_push::land::LandLimit::Count(count.unwrap_or(1)),
+            };
+            let method = match method {
+                Some(method) => method,
+                None => pre_push::land::MergeMethod::from_config(&repo)?,
+            };
+            pre_push::land::run(&repo, &runtime.github_endpoint, limit, method).await?
+        }
+        Commands::Abandon { gherrit_id, comment } => {
+            let mode = pre_push::SyncMode::Apply;
+            let options = pre_push::SyncOptions::from_config(&repo, mode, runtime.id_entropy)?;
+            let endpoint = &runtime.github_endpoint;
+            pre_push::abandon::run(&repo, endpoint, &gherrit_id, comment.as_deref(), options)
+                .await?
+        }
+        Commands::Gc { dry_run, tags, tag_age, include_closed } => {
+            let mode = if dry_run { pre_push::SyncMode::DryRun } else { pre_push::SyncMode::Apply };
+            let mut options = pre_push::gc::GcOptions::from_config(&repo, mode)?;
+            options.tags = tags.unwrap_or(options.tags);
+            options.tag_age_days = tag_age.unwrap_or(options.tag_age_days);
+            options.include_closed = include_closed;
+            pre_push::gc::run(&repo, &runtime.github_endpoint, options).await?
+        }
+        Commands::MigrateRefs { layout } => pre_push::migrate::run(&repo, layout)?,
+        Commands::Restack => pre_push::restack::run(&repo, &runtime.github_endpoint).await?,
+        Commands::Status => pre_push::status::run(&repo, &runtime.github_endpoint).await?,
         Commands::Install { force, allow_global } => install::install(&repo, force, allow_global)?,
     }
 
@@ -0,0 +1,152 @@
+use std::slice;
+
+use color_eyre::eyre::{Context as _, Result, bail, eyre};
+use owo_colors::OwoColorize as _;
+
+use super::{
+    BranchNaming, GithubEndpoint, PrState, SyncOptions, batch_fetch_prs,
+    body::parse_metadata,
+    collect_commits,
+    github::{AddComment, ClosePullRequest},
+    github_client,
+    lock::SyncLock,
+    observe_managed_branches,
+    publication::{RemoteRef, plan_deletion},
+    reconcile::PullRequestState,
+    run_batched_graphql, sync_stack,
+};
+use crate::util::{self, CommandExt as _};
+
+/// Retires the PR of a commit that has been dropped from its stack.
+///
+/// The remaining stack is re-synced first so that no PR is still based on the
+/// abandoned managed branch when it is deleted; GitHub closes such PRs.
+pub async fn run(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    gherrit_id: &str,
+    comment: Option<&str>,
+    options: SyncOptions,
+) -> Result<()> {
+    if gherrit_id.is_empty() || !gherrit_id.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+        bail!("'{gherrit_id}' is not a gherrit-pr-id");
+    }
+    let _lock = SyncLock::acquire(repo)?;
+    let octocrab = github_client(github_endpoint)?;
+    let pr = find_pull_request(repo, &octocrab, gherrit_id).await?;
+    if pr.state == PullRequestState::Merged {
+        bail!("PR #{} is merged, so it cannot be abandoned", pr.number);
+    }
+
+    resync_remaining_stack(repo, github_endpoint, &pr, options).await?;
+    ensure_no_pr_is_based_on(repo, &octocrab, &pr).await?;
+
+    let remote = repo.default_remote()?;
+    if pr.state == PullRequestState::Open {
+        if let Some(comment) = comment {
+            let comment = AddComment::new(pr.node_id.clone(), comment.to_string());
+            run_batched_graphql(&octocrab, [comment]).await?;
+        }
+        run_batched_graphql(&octocrab, [ClosePullRequest::new(pr.node_id.clone())]).await?;
+        log::info!("Closed PR #{}: {}", pr.number.green().bold(), remote.pr_url(pr.number));
+    } else {
+        log::info!("PR #{} is already closed.", pr.number.green().bold());
+    }
+
+    let name = &pr.head_branch;
+    let remote_branches = observe_managed_branches(repo, slice::from_ref(name)).await?;
+    match remote_branches.get(name) {
+        Some(expected_sha) => {
+            let branch =
+                RemoteRef { name: format!("refs/heads/{name}"), object_id: expected_sha.clone() };
+            let arguments = plan_deletion(&repo.default_remote_name(), &[branch]);
+            // Capture the output, which holds only GitHub's "Create a pull
+            // request" hint for the deleted branch unless the push fails.
+            util::cmd("git", arguments)
+                .checked_output()
+                .wrap_err_with(|| format!("Failed to delete managed branch {name}"))?;
+            log::info!("Deleted managed branch {}; its version tags remain.", name.yellow());
+        }
+        None => log::info!("Managed branch {} is already deleted.", name.yellow()),
+    }
+    Ok(())
+}
+
+async fn find_pull_request(
+    repo: &util::Repo,
+    octocrab: &octocrab::Octocrab,
+    gherrit_id: &str,
+) -> Result<PrState> {
+    let missing = || eyre!("No PR exists for gherrit-pr-id '{gherrit_id}'");
+    let naming = BranchNaming::from_config(repo)?;
+    let mut branches = naming.lookup(repo, &[gherrit_id.to_string()]).await?;
+    let branch = branches
+        .remove(gherrit_id)
+        .or_else(|| naming.render_static(gherrit_id))
+        .ok_or_else(missing)?;
+    batch_fetch_prs(repo, octocrab, &[branch]).await?.pop().ok_or_else(missing)
+}
+
+/// Re-syncs the current branch if it holds a neighbour of the abandoned PR,
+/// which re-renders the navigation and bases of the remaining PRs.
+async fn resync_remaining_stack(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    pr: &PrState,
+    options: SyncOptions,
+) -> Result<()> {
+    let Some(branch_name) = repo.current_branch().name() else {
+        return Ok(());
+    };
+    // Abandoning does not require the current branch to be a stack.
+    if !repo.is_managed(branch_name).unwrap_or(false) {
+        return Ok(());
+    }
+    let tip = repo.rev_parse_single("HEAD")?.detach();
+    let commits = collect_commits(repo, branch_name, tip).wrap_err("Failed to collect commits")?;
+    let abandoned_id = BranchNaming::from_config(repo)?.parse(&pr.head_branch);
+    if commits.iter().any(|commit| Some(commit.gherrit_id.as_str()) == abandoned_id) {
+        bail!(
+            "Branch '{branch_name}' still contains the commit of PR #{}.\n\
+             Drop the commit (e.g., with 'git rebase -i') before abandoning its PR.",
+            pr.number
+        );
+    }
+
+    let Some(metadata) = pr.body.as_deref().and_then(parse_metadata) else {
+        return Ok(());
+    };
+    let is_neighbour = |id: &str| commits.iter().any(|commit| commit.gherrit_id == id);
+    if metadata.parent.as_deref().is_some_and(is_neighbour)
+        || metadata.child.as_deref().is_some_and(is_neighbour)
+    {
+        log::info!("Re-syncing the remaining stack of branch {}...", branch_name.yellow());
+        sync_stack(repo, github_endpoint, branch_name, tip, options).await?;
+    }
+    Ok(())
+}
+
+/// Refuses to abandon a PR while an open PR is still stacked on it.
+async fn ensure_no_pr_is_based_on(
+    repo: &util::Repo,
+    octocrab: &octocrab::Octocrab,
+    pr: &PrState,
+) -> Result<()> {
+    let metadata = pr.body.as_deref().and_then(parse_metadata);
+    let Some(child) = metadata.as_ref().and_then(|meta| meta.child_branch()) else {
+        return Ok(());
+    };
+    let child = batch_fetch_prs(repo, octocrab, &[child.to_string()]).await?.pop();
+    if let Some(child) = child
+        && child.state == PullRequestState::Open
+        && child.base_branch == pr.head_branch
+    {
+        bail!(
+            "PR #{} is still stacked on PR #{}.\n\
+             Sync the stack without the abandoned commit before abandoning its PR.",
+            child.number,
+            pr.number
+        );
+    }
+    Ok(())
+}
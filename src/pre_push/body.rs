@@ -1,7 +1,9 @@
 use std::fmt::{self, Write};
 
-use serde::Serialize;
+use gix::ObjectId;
+use serde::{Deserialize, Serialize};
 
+use super::triage::Triage;
 use crate::re;
 
 // Per https://github.com/orgs/community/discussions/27190#discussioncomment-3254953,
@@ -13,13 +15,37 @@ pub(super) struct PrBody<'a> {
     pub commit_body: &'a str,
     pub repo_url: &'a str,
     pub public_branch: Option<&'a str>,
-    pub stack_pr_numbers: &'a [u64],
+    /// The PR number of each commit from the base of the stack, or `None` for a
+    /// PR that a dry run would create.
+    pub stack_pr_numbers: &'a [Option<u64>],
     pub current_pr_number: u64,
     pub latest_version: usize,
+    pub version_revs: VersionRevs<'a>,
     pub base_branch: &'a str,
     pub gherrit_id: &'a str,
-    pub parent_id: Option<&'a str>,
-    pub child_id: Option<&'a str>,
+    /// The managed branch of this PR.
+    pub branch: &'a str,
+    pub parent: Option<Link<'a>>,
+    pub child: Option<Link<'a>>,
+    /// The reviewers, labels, and assignees requested for this PR.
+    pub triage: &'a Triage,
+}
+
+/// A neighbour of a PR in its stack.
+#[derive(Clone, Copy)]
+pub(super) struct Link<'a> {
+    pub id: &'a str,
+    pub branch: &'a str,
+}
+
+/// How compare links name each version of the commit.
+#[derive(Clone, Copy)]
+pub(super) enum VersionRevs<'a> {
+    /// By version tag, e.g. `gherrit/<id>/v2`.
+    Tags,
+    /// By the object ID of each version from v1 up, or `None` for a version
+    /// that is not known locally.
+    ObjectIds(&'a [Option<ObjectId>]),
 }
 
 #[derive(Clone, Copy)]
@@ -65,7 +91,12 @@ impl PrBody<'_> {
             "*Stacked PRs enabled by [GHerrit](https://github.com/joshlf/gherrit).*\n\n",
         )?;
         output.write_str("<!-- WARNING: GHerrit relies on the following metadata to work properly. DO NOT EDIT OR REMOVE. -->")?;
-        output.write_str(&metadata_comment(self.gherrit_id, self.parent_id, self.child_id))
+        output.write_str(&metadata_comment(
+            Link { id: self.gherrit_id, branch: self.branch },
+            self.parent,
+            self.child,
+            self.triage,
+        ))
     }
 
     fn write_navigation(&self, mut output: impl Write) -> fmt::Result {
@@ -75,8 +106,11 @@ impl PrBody<'_> {
 
         self.stack_pr_numbers.iter().rev().try_for_each(|number| {
             let prefix =
-                if *number == self.current_pr_number { "👉" } else { "\u{3000}\u{2009}" };
-            writeln!(output, "- {prefix} #{number}")
+                if *number == Some(self.current_pr_number) { "👉" } else { "\u{3000}\u{2009}" };
+            match number {
+                Some(number) => writeln!(output, "- {prefix} #{number}"),
+                None => writeln!(output, "- {prefix} (new PR)"),
+            }
         })
     }
 
@@ -89,17 +123,16 @@ impl PrBody<'_> {
             return Ok(());
         }
 
-        write!(
-            output,
-            "\n\n**Latest Update:** v{} — [Compare vs v{}]({}/compare/gherrit/{}/v{}..gherrit/{}/v{})\n\n",
-            self.latest_version,
-            self.latest_version - 1,
-            self.repo_url,
-            self.gherrit_id,
-            self.latest_version - 1,
-            self.gherrit_id,
-            self.latest_version
-        )?;
+        let latest = self.latest_version;
+        match (self.version_rev(latest - 1), self.version_rev(latest)) {
+            (Some(previous_rev), Some(latest_rev)) => write!(
+                output,
+                "\n\n**Latest Update:** v{latest} — [Compare vs v{}]({}/compare/{previous_rev}..{latest_rev})\n\n",
+                latest - 1,
+                self.repo_url,
+            )?,
+            _ => write!(output, "\n\n**Latest Update:** v{latest}\n\n")?,
+        }
 
         output.write_str(
             "<details>\n<summary><strong>📚 Full Patch History</strong></summary>\n\n",
@@ -136,27 +169,39 @@ impl PrBody<'_> {
                     HistoryTableFormat::Sparse => row == self.latest_version || row == column + 1,
                 };
 
-                if show_link {
-                    write!(
+                match (show_link, self.version_rev(column), self.version_rev(row)) {
+                    (true, Some(column_rev), Some(row_rev)) => write!(
                         output,
-                        "[{prefix}v{column}]({}/compare/gherrit/{}/v{column}..gherrit/{}/v{row})|",
-                        self.repo_url, self.gherrit_id, self.gherrit_id
-                    )?;
-                } else {
-                    output.write_str("|")?;
+                        "[{prefix}v{column}]({}/compare/{column_rev}..{row_rev})|",
+                        self.repo_url
+                    )?,
+                    _ => output.write_str("|")?,
                 }
             }
 
-            writeln!(
-                output,
-                "[{prefix}Base]({}/compare/{}..gherrit/{}/v{row})|",
-                self.repo_url, self.base_branch, self.gherrit_id
-            )?;
+            match self.version_rev(row) {
+                Some(row_rev) => writeln!(
+                    output,
+                    "[{prefix}Base]({}/compare/{}..{row_rev})|",
+                    self.repo_url, self.base_branch
+                )?,
+                None => writeln!(output, "|")?,
+            }
         }
 
         output.write_str("\n</details>")
     }
 
+    /// Names a version in compare links, if it is known.
+    fn version_rev(&self, version: usize) -> Option<String> {
+        match self.version_revs {
+            VersionRevs::Tags => Some(format!("gherrit/{}/v{version}", self.gherrit_id)),
+            VersionRevs::ObjectIds(object_ids) => {
+                object_ids.get(version.checked_sub(1)?)?.map(|object_id| object_id.to_string())
+            }
+        }
+    }
+
     fn write_download_section(&self, mut output: impl Write) -> fmt::Result {
         output.write_str(
             "\n<details>\n<summary><strong>⬇️ Download this PR</strong></summary>\n\n",
@@ -166,13 +211,13 @@ impl PrBody<'_> {
         // While `git fetch origin {id}` would work most of the time, we use
         // the full `refs/heads/` syntax to avoid ambiguity with tags of the
         // same name.
-        let id = self.gherrit_id;
-        let fetch = format!("git fetch origin refs/heads/{id}");
+        let (id, branch) = (self.gherrit_id, self.branch);
+        let fetch = format!("git fetch origin refs/heads/{branch}");
         let commands = [
             ("Branch", format!("{fetch} && git checkout -b pr-{id} FETCH_HEAD")),
             ("Checkout", format!("{fetch} && git checkout FETCH_HEAD")),
             ("Cherry Pick", format!("{fetch} && git cherry-pick FETCH_HEAD")),
-            ("Pull", format!("git pull origin refs/heads/{id}")),
+            ("Pull", format!("git pull origin refs/heads/{branch}")),
         ];
 
         commands.into_iter().try_for_each(|(title, command)| {
@@ -187,19 +232,128 @@ pub(super) fn gherrit_pr_id_re() -> &'static regex::Regex {
     re!(r"(?m)^gherrit-pr-id[=:][ \t]*([a-zA-Z0-9]+)[ \t]*\r?$")
 }
 
-fn metadata_comment(id: &str, parent: Option<&str>, child: Option<&str>) -> String {
+/// Renders the metadata of a PR and its neighbours.
+///
+/// Branches are recorded only where they differ from the gherrit-pr-id, and
+/// the triage only where it is not empty, so metadata under the default naming
+/// template stays as it has always been.
+fn metadata_comment<'a>(
+    this: Link<'a>,
+    parent: Option<Link<'a>>,
+    child: Option<Link<'a>>,
+    triage: &'a Triage,
+) -> String {
     #[derive(Serialize)]
     struct Metadata<'a> {
         id: &'a str,
         parent: Option<&'a str>,
         child: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        branch: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent_branch: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        child_branch: Option<&'a str>,
+        #[serde(skip_serializing_if = "Triage::is_empty")]
+        triage: &'a Triage,
     }
 
-    let metadata = serde_json::to_string(&Metadata { id, parent, child })
-        .expect("serializing GHerrit metadata cannot fail");
+    let renamed = |link: Link<'a>| (link.branch != link.id).then_some(link.branch);
+    let metadata = Metadata {
+        id: this.id,
+        parent: parent.map(|link| link.id),
+        child: child.map(|link| link.id),
+        branch: renamed(this),
+        parent_branch: parent.and_then(renamed),
+        child_branch: child.and_then(renamed),
+        triage,
+    };
+    let metadata =
+        serde_json::to_string(&metadata).expect("serializing GHerrit metadata cannot fail");
     format!("<!-- gherrit-meta: {metadata} -->")
 }
 
+/// The stack links recorded in a PR body by [`metadata_comment`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub(super) struct PrMetadata {
+    pub id: String,
+    pub parent: Option<String>,
+    pub child: Option<String>,
+    #[serde(default)]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub parent_branch: Option<String>,
+    #[serde(default)]
+    pub child_branch: Option<String>,
+    /// The reviewers, labels, and assignees that GHerrit has requested.
+    #[serde(default)]
+    pub triage: Triage,
+}
+
+impl PrMetadata {
+    /// The managed branch of the PR.
+    pub fn branch(&self) -> &str {
+        self.branch.as_deref().unwrap_or(&self.id)
+    }
+
+    /// The managed branch of the PR's parent, if it has one.
+    pub fn parent_branch(&self) -> Option<&str> {
+        self.parent_branch.as_deref().or(self.parent.as_deref())
+    }
+
+    /// The managed branch of the PR's child, if it has one.
+    pub fn child_branch(&self) -> Option<&str> {
+        self.child_branch.as_deref().or(self.child.as_deref())
+    }
+}
+
+/// Parses the last metadata comment in a PR body.
+///
+/// This accepts exactly what the rebase-stack action accepts in
+/// `ci/extract_stack_child.sh`. In particular, an earlier comment in the
+/// commit body cannot shadow the generated one at the end.
+pub(super) fn parse_metadata(body: &str) -> Option<PrMetadata> {
+    let captures =
+        re!(r"(?m)^.*<!-- gherrit-meta: (.*) -->[ \t\r]*$").captures_iter(body).last()?;
+    let metadata = captures.get(1)?.as_str();
+    // GHerrit versions before the metadata serializer fix appended one stray
+    // quote after the JSON object.
+    let metadata = metadata.strip_suffix('"').unwrap_or(metadata);
+    serde_json::from_str(metadata).ok()
+}
+
+/// The note that marks the PR of a commit that has left its stack.
+const REMOVED_NOTE: &str = "> [!NOTE]\n> This PR's commit was removed from its stack. \
+     The stack links below are out of date.\n\n";
+
+/// Marks a PR body as belonging to a commit that was removed from its stack.
+///
+/// The note goes at the top of the body, and the metadata loses its links so
+/// that tools following the stack no longer reach the PR. Marking a body
+/// twice does not change it.
+pub(super) fn mark_removed(body: &str) -> String {
+    let mut marked = body.to_string();
+    if let Some(metadata) = parse_metadata(body)
+        && let Some(start) = marked.rfind("<!-- gherrit-meta: ")
+        && let Some(len) = marked[start..].find("-->")
+    {
+        let unlinked = metadata_comment(
+            Link { id: &metadata.id, branch: metadata.branch() },
+            None,
+            None,
+            &metadata.triage,
+        );
+        marked.replace_range(start..start + len + "-->".len(), &unlinked);
+    }
+    if !marked.contains(REMOVED_NOTE) {
+        // Keep the generated warning, if any, as the first line.
+        let position = marked.find("-->\n\n").filter(|_| marked.starts_with("<!-- WARNING"));
+        let position = position.map_or(0, |position| position + "-->\n\n".len());
+        marked.insert_str(position, REMOVED_NOTE);
+    }
+    marked
+}
+
 struct ByteCounter(usize);
 
 impl Write for ByteCounter {
@@ -213,7 +367,9 @@ impl Write for ByteCounter {
 mod tests {
     use super::*;
 
-    const STACK: &[u64] = &[11, 22, 33];
+    const STACK: &[Option<u64>] = &[Some(11), Some(22), Some(33)];
+    const NO_TRIAGE: &Triage =
+        &Triage { reviewers: Vec::new(), labels: Vec::new(), assignees: Vec::new() };
 
     #[test]
     fn gherrit_id_trailers_require_a_nonempty_identifier() {
@@ -237,10 +393,13 @@ mod tests {
             stack_pr_numbers: STACK,
             current_pr_number,
             latest_version,
+            version_revs: VersionRevs::Tags,
             base_branch: parent_id.unwrap_or("main"),
             gherrit_id,
-            parent_id,
-            child_id,
+            branch: gherrit_id,
+            parent: parent_id.map(|id| Link { id, branch: id }),
+            child: child_id.map(|id| Link { id, branch: id }),
+            triage: NO_TRIAGE,
         }
     }
 
@@ -287,13 +446,114 @@ mod tests {
         insta::assert_snapshot!(body.render_with_history(HistoryTableFormat::Sparse));
     }
 
+    #[test]
+    fn links_versions_by_object_id() {
+        let v1 = ObjectId::from_bytes_or_panic(&[0x11; 20]);
+        let v3 = ObjectId::from_bytes_or_panic(&[0x33; 20]);
+        let object_ids = [Some(v1), None, Some(v3)];
+        let mut body = body("", None, 22, 3, "Gmiddle", Some("Groot"), Some("Gtip"));
+        body.version_revs = VersionRevs::ObjectIds(&object_ids);
+        let rendered = body.render();
+
+        assert!(rendered.contains(&format!("[vs v1](/octo/widgets/compare/{v1}..{v3})")));
+        assert!(rendered.contains(&format!("[vs Base](/octo/widgets/compare/Groot..{v1})")));
+        assert!(rendered.contains("**Latest Update:** v3\n"), "v2 is unknown");
+        assert!(!rendered.contains("gherrit/Gmiddle/v"));
+    }
+
     #[test]
     fn metadata_is_json_escaped() {
         insta::assert_snapshot!(metadata_comment(
-            "G\"雪",
-            Some("parent\\branch"),
-            Some("child\nline"),
+            Link { id: "G\"雪", branch: "G\"雪" },
+            Some(Link { id: "parent\\branch", branch: "parent\\branch" }),
+            Some(Link { id: "child\nline", branch: "child\nline" }),
+            NO_TRIAGE,
+        ));
+    }
+
+    #[test]
+    fn parses_the_metadata_it_renders() {
+        let rendered = body("Body\n", None, 22, 2, "Gmiddle", Some("Groot"), Some("Gtip")).render();
+        assert_eq!(
+            parse_metadata(&rendered),
+            Some(PrMetadata {
+                id: "Gmiddle".to_string(),
+                parent: Some("Groot".to_string()),
+                child: Some("Gtip".to_string()),
+                ..PrMetadata::default()
+            })
+        );
+
+        let legacy = r#"<!-- gherrit-meta: {"id": "Gid", "parent": null, "child": "Gchild"}" -->"#;
+        assert_eq!(
+            parse_metadata(legacy),
+            Some(PrMetadata {
+                id: "Gid".to_string(),
+                parent: None,
+                child: Some("Gchild".to_string()),
+                ..PrMetadata::default()
+            })
+        );
+
+        let shadowed = "<!-- gherrit-meta: {\"id\":\"Gfake\",\"parent\":null,\"child\":\"Gwrong\"} -->\n\n\
+             <!-- gherrit-meta: {\"id\":\"Gid\",\"parent\":\"Groot\",\"child\":null} -->";
+        assert_eq!(parse_metadata(shadowed).map(|metadata| metadata.id).as_deref(), Some("Gid"));
+
+        assert_eq!(parse_metadata("No metadata here."), None);
+        assert_eq!(parse_metadata("<!-- gherrit-meta: not-json -->"), None);
+    }
+
+    #[test]
+    fn records_branches_that_differ_from_ids() {
+        let mut body = body("Body\n", None, 22, 2, "Gmiddle", Some("Groot"), Some("Gtip"));
+        body.branch = "gherrit/octo/Gmiddle";
+        body.parent = Some(Link { id: "Groot", branch: "gherrit/octo/Groot" });
+        body.base_branch = "gherrit/octo/Groot";
+        let rendered = body.render();
+
+        assert!(rendered.contains("git fetch origin refs/heads/gherrit/octo/Gmiddle && "));
+        assert!(rendered.contains("git checkout -b pr-Gmiddle FETCH_HEAD"));
+        let metadata = parse_metadata(&rendered).unwrap();
+        assert_eq!(metadata.branch(), "gherrit/octo/Gmiddle");
+        assert_eq!(metadata.parent_branch(), Some("gherrit/octo/Groot"));
+        assert_eq!(metadata.child_branch(), Some("Gtip"));
+        assert_eq!(metadata.child_branch, None, "only renamed branches are recorded");
+
+        let marked = parse_metadata(&mark_removed(&rendered)).unwrap();
+        assert_eq!(marked.branch(), "gherrit/octo/Gmiddle");
+        assert_eq!(marked.parent_branch(), None);
+    }
+
+    #[test]
+    fn records_the_requested_triage() {
+        let triage = Triage {
+            reviewers: vec!["alice".to_string(), "org/team".to_string()],
+            labels: vec!["perf".to_string()],
+            assignees: Vec::new(),
+        };
+        let mut body = body("Body\n", None, 22, 2, "Gmiddle", Some("Groot"), Some("Gtip"));
+        body.triage = &triage;
+        let rendered = body.render();
+
+        assert!(rendered.ends_with(
+            r#""child":"Gtip","triage":{"reviewers":["alice","org/team"],"labels":["perf"]}} -->"#
         ));
+        assert_eq!(parse_metadata(&rendered).unwrap().triage, triage);
+        assert_eq!(parse_metadata(&mark_removed(&rendered)).unwrap().triage, triage);
+    }
+
+    #[test]
+    fn marks_removed_prs_once() {
+        let rendered = body("Body\n", None, 22, 2, "Gmiddle", Some("Groot"), Some("Gtip")).render();
+        let marked = mark_removed(&rendered);
+        assert!(marked.starts_with("<!-- WARNING"));
+        assert_eq!(marked.matches("This PR's commit was removed from its stack.").count(), 1);
+        assert_eq!(
+            parse_metadata(&marked),
+            Some(PrMetadata { id: "Gmiddle".to_string(), ..PrMetadata::default() })
+        );
+        assert_eq!(mark_removed(&marked), marked);
+        assert!(mark_removed("Edited by hand").starts_with(REMOVED_NOTE));
     }
 
     #[test]
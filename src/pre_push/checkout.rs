@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+
+use color_eyre::eyre::{Context as _, Result, bail, eyre};
+use gix::ObjectId;
+use octocrab::Octocrab;
+use owo_colors::OwoColorize as _;
+
+use super::{
+    BranchNaming, GithubEndpoint, PrState,
+    body::{PrMetadata, parse_metadata},
+    github::{FindPullRequest, PullRequestByNumber},
+    github_client, observe_managed_branches,
+    reconcile::PullRequestState,
+    run_batched_graphql,
+};
+use crate::{
+    manage::{self, State},
+    util::{self, CommandExt as _},
+};
+
+/// Identifies the PR to start from.
+#[derive(Debug, PartialEq, Eq)]
+enum Target<'a> {
+    Number(u64),
+    GherritId(&'a str),
+}
+
+impl<'a> Target<'a> {
+    fn parse(target: &'a str) -> Result<Self> {
+        let number = target.strip_prefix('#').unwrap_or(target);
+        if let Ok(number) = number.parse() {
+            return Ok(Self::Number(number));
+        }
+        if target.is_empty() || !target.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+            bail!("'{target}' is neither a PR number nor a gherrit-pr-id");
+        }
+        Ok(Self::GherritId(target))
+    }
+}
+
+/// A PR in a stack, along with the links recorded in its body.
+struct StackPr {
+    pr: PrState,
+    metadata: PrMetadata,
+}
+
+impl StackPr {
+    fn new(pr: PrState) -> Result<Self> {
+        let metadata = pr.body.as_deref().and_then(parse_metadata).ok_or_else(|| {
+            eyre!("PR #{} has no GHerrit metadata, so it is not part of a GHerrit stack", pr.number)
+        })?;
+        if metadata.branch() != pr.head_branch {
+            bail!(
+                "The GHerrit metadata of PR #{} names branch '{}', but its head branch is '{}'",
+                pr.number,
+                metadata.branch(),
+                pr.head_branch
+            );
+        }
+        Ok(Self { pr, metadata })
+    }
+}
+
+/// Which neighbour of a PR a walk follows, by the neighbour's branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Parent,
+    Child,
+}
+
+impl Direction {
+    fn next(self, metadata: &PrMetadata) -> Option<&str> {
+        match self {
+            Self::Parent => metadata.parent_branch(),
+            Self::Child => metadata.child_branch(),
+        }
+    }
+
+    fn back(self, metadata: &PrMetadata) -> Option<&str> {
+        match self {
+            Self::Parent => metadata.child_branch(),
+            Self::Child => metadata.parent_branch(),
+        }
+    }
+}
+
+/// Reconstructs the stack containing a PR as a new managed local branch.
+pub async fn run(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    target: &str,
+    branch: Option<&str>,
+) -> Result<()> {
+    let target = Target::parse(target)?;
+    let octocrab = github_client(github_endpoint)?;
+    let remote = repo.default_remote()?;
+
+    let start = match target {
+        Target::Number(number) => {
+            let query = PullRequestByNumber::new(remote.owner.clone(), remote.repo_name, number);
+            fetch_one(&octocrab, query)
+                .await?
+                .ok_or_else(|| eyre!("PR #{number} does not exist"))?
+        }
+        Target::GherritId(id) => {
+            let naming = BranchNaming::from_config(repo)?;
+            let branch = naming.lookup(repo, &[id.to_string()]).await?.remove(id);
+            let branch = branch
+                .or_else(|| naming.render_static(id))
+                .ok_or_else(|| eyre!("No managed branch exists for gherrit-pr-id '{id}'"))?;
+            find_pull_request(repo, &octocrab, &branch).await?
+        }
+    };
+    let start = StackPr::new(start)?;
+    if start.pr.state != PullRequestState::Open {
+        bail!("PR #{} is not open, so there is no stack to check out", start.pr.number);
+    }
+
+    let branch_name = branch.map_or_else(|| format!("pr-{}", start.metadata.id), str::to_owned);
+    if repo.try_find_reference(format!("refs/heads/{branch_name}").as_str())?.is_some() {
+        bail!("Branch '{branch_name}' already exists. Pass --branch to choose another name.");
+    }
+
+    let mut seen = HashSet::from([start.pr.head_branch.clone()]);
+    let mut below = walk(repo, &octocrab, &start, Direction::Parent, &mut seen).await?;
+    let above = walk(repo, &octocrab, &start, Direction::Child, &mut seen).await?;
+    below.reverse();
+    let stack: Vec<_> = below.into_iter().chain([start]).chain(above).collect();
+
+    let base = &stack[0].pr.base_branch;
+    if *base != repo.find_default_branch_on_default_remote() {
+        log::warn!(
+            "The stack is based on {}, not on the default branch. You may need to rebase it.",
+            base.yellow()
+        );
+    }
+
+    let branches: Vec<String> = stack.iter().map(|entry| entry.pr.head_branch.clone()).collect();
+    let tip = fetch_stack(repo, &branches).await?;
+
+    util::cmd("git", ["checkout", "-b", branch_name.as_str(), tip.to_string().as_str()])
+        .success()
+        .wrap_err("Failed to create the local branch")?;
+    // The checkout changed `HEAD`, which `Repo` records when it is opened.
+    let repo = util::Repo::open(".").wrap_err("Failed to reopen repo")?;
+    manage::set_state(&repo, State::Private, false)?;
+
+    log::info!(
+        "Checked out {} PRs as branch {}: {}",
+        stack.len(),
+        branch_name.yellow(),
+        stack.iter().map(|entry| format!("#{}", entry.pr.number)).collect::<Vec<_>>().join(", ")
+    );
+    Ok(())
+}
+
+/// Follows the metadata links of `start` in one direction for as long as they
+/// lead to open PRs, returning the PRs in the order visited.
+async fn walk(
+    repo: &util::Repo,
+    octocrab: &Octocrab,
+    start: &StackPr,
+    direction: Direction,
+    seen: &mut HashSet<String>,
+) -> Result<Vec<StackPr>> {
+    let mut visited: Vec<StackPr> = Vec::new();
+    loop {
+        let current = visited.last().unwrap_or(start);
+        let Some(next_branch) = direction.next(&current.metadata) else {
+            return Ok(visited);
+        };
+        if !seen.insert(next_branch.to_string()) {
+            bail!("The GHerrit metadata of PR #{} forms a cycle", current.pr.number);
+        }
+
+        let next = StackPr::new(find_pull_request(repo, octocrab, next_branch).await?)?;
+        if next.pr.state != PullRequestState::Open {
+            log::info!(
+                "Skipping PR #{} and beyond: it is no longer open.",
+                next.pr.number.to_string().yellow()
+            );
+            return Ok(visited);
+        }
+        if direction.back(&next.metadata) != Some(current.pr.head_branch.as_str()) {
+            bail!(
+                "The GHerrit metadata of PRs #{} and #{} disagree about the stack. \
+                 Ask the author to push the stack again.",
+                current.pr.number,
+                next.pr.number
+            );
+        }
+        visited.push(next);
+    }
+}
+
+async fn find_pull_request(
+    repo: &util::Repo,
+    octocrab: &Octocrab,
+    branch: &str,
+) -> Result<PrState> {
+    let remote = repo.default_remote()?;
+    let query = FindPullRequest::new(remote.owner, remote.repo_name, branch.to_string());
+    fetch_one(octocrab, query).await?.ok_or_else(|| eyre!("No PR exists for branch '{branch}'"))
+}
+
+async fn fetch_one<O: super::BatchedOperation>(octocrab: &Octocrab, query: O) -> Result<O::Output> {
+    let mut outputs = run_batched_graphql(octocrab, [query]).await?;
+    outputs.pop().ok_or_else(|| eyre!("GitHub returned no result for the PR lookup"))
+}
+
+/// Fetches every managed branch of the stack and returns the tip, after
+/// checking that each commit is stacked directly on the one below it.
+async fn fetch_stack(repo: &util::Repo, branches: &[String]) -> Result<ObjectId> {
+    let remote_branches = observe_managed_branches(repo, branches).await?;
+    let object_ids = branches
+        .iter()
+        .map(|branch| {
+            let object_id = remote_branches
+                .get(branch)
+                .ok_or_else(|| eyre!("The managed branch {branch} does not exist on the remote"))?;
+            Ok(ObjectId::from_hex(object_id.as_bytes())?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let refspecs = branches.iter().map(|branch| format!("refs/heads/{branch}"));
+    let arguments = ["fetch", "--quiet", "--no-tags"]
+        .into_iter()
+        .map(ToString::to_string)
+        .chain([repo.default_remote_name()])
+        .chain(refspecs);
+    util::cmd("git", arguments).success().wrap_err("Failed to fetch the stack")?;
+
+    let aboves = object_ids.iter().skip(1).zip(&branches[1..]);
+    for (below, (above, branch)) in object_ids.iter().zip(aboves) {
+        let commit = repo
+            .find_commit(*above)
+            .wrap_err_with(|| format!("{branch} moved while it was being fetched; try again"))?;
+        if !commit.parent_ids().map(|parent| parent.detach()).eq([*below]) {
+            bail!(
+                "The managed branch {branch} is not stacked on the commit below it. \
+                 Ask the author to push the stack again."
+            );
+        }
+    }
+
+    Ok(*object_ids.last().expect("a stack contains at least one PR"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pr_numbers_and_gherrit_ids() {
+        assert_eq!(Target::parse("42").unwrap(), Target::Number(42));
+        assert_eq!(Target::parse("#42").unwrap(), Target::Number(42));
+        assert_eq!(Target::parse("G1a2b").unwrap(), Target::GherritId("G1a2b"));
+        for invalid in ["", "#", "feature/branch", "G 1"] {
+            assert!(Target::parse(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn follows_the_link_that_leads_back() {
+        let metadata = PrMetadata {
+            id: "Gmiddle".to_string(),
+            parent: Some("Groot".to_string()),
+            child: Some("Gtip".to_string()),
+            child_branch: Some("stack/Gtip".to_string()),
+            ..PrMetadata::default()
+        };
+        assert_eq!(Direction::Parent.next(&metadata), Some("Groot"));
+        assert_eq!(Direction::Parent.back(&metadata), Some("stack/Gtip"));
+        assert_eq!(Direction::Child.next(&metadata), Some("stack/Gtip"));
+        assert_eq!(Direction::Child.back(&metadata), Some("Groot"));
+    }
+}
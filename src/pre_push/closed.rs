@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::{Result, eyre};
+use gix::{ObjectId, prelude::ObjectIdExt as _, refs::transaction::PreviousValue};
+use octocrab::Octocrab;
+use owo_colors::OwoColorize as _;
+
+use super::{
+    BranchNaming, Commit, PrState, SyncMode, SyncOptions, github::ReopenPullRequest,
+    reconcile::PullRequestState, run_batched_graphql,
+};
+use crate::{commit_msg, util};
+
+/// What a sync does with a stack commit whose PR has been closed without
+/// being merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub(crate) enum ClosedPrPolicy {
+    /// Reject the sync.
+    #[default]
+    Error,
+    /// Reopen the PR and sync it as usual.
+    Reopen,
+    /// Give the commit a fresh gherrit-pr-id so that it gets a new PR.
+    Recreate,
+}
+
+impl ClosedPrPolicy {
+    const CONFIG_KEY: &str = "gherrit.closedPrPolicy";
+
+    /// Reads the repository's policy, defaulting to `Error`.
+    pub(crate) fn from_config(repo: &util::Repo) -> Result<Self> {
+        repo.config_choice(Self::CONFIG_KEY)
+    }
+}
+
+/// Applies `options.closed_prs` to the closed PRs among `prs`.
+///
+/// Afterwards, no PR in `prs` is closed unless the policy is `Error`. Returns
+/// whether `branch_name` was rewritten, in which case `commits` holds the
+/// rewritten commits.
+pub(super) async fn resolve(
+    repo: &util::Repo,
+    octocrab: &Octocrab,
+    branch_name: &str,
+    tip: ObjectId,
+    commits: &mut [Commit],
+    prs: &mut Vec<PrState>,
+    options: SyncOptions,
+) -> Result<bool> {
+    // A merged PR fails the sync regardless of the policy, so leave the closed
+    // PRs alone rather than resolve them for a sync that cannot proceed.
+    let is_closed = |pr: &PrState| pr.state == PullRequestState::Closed;
+    if !prs.iter().any(is_closed) || prs.iter().any(|pr| pr.state == PullRequestState::Merged) {
+        return Ok(false);
+    }
+
+    match options.closed_prs {
+        ClosedPrPolicy::Error => Ok(false),
+        ClosedPrPolicy::Reopen => {
+            reopen(repo, octocrab, prs.iter_mut().filter(|pr| is_closed(pr)), options.mode).await?;
+            Ok(false)
+        }
+        ClosedPrPolicy::Recreate => {
+            let closed: Vec<PrState> = prs.extract_if(.., |pr| is_closed(pr)).collect();
+            recreate(repo, branch_name, tip, commits, &closed, options)
+        }
+    }
+}
+
+async fn reopen<'a>(
+    repo: &util::Repo,
+    octocrab: &Octocrab,
+    closed: impl Iterator<Item = &'a mut PrState>,
+    mode: SyncMode,
+) -> Result<()> {
+    let remote = repo.default_remote()?;
+    let closed: Vec<_> = closed.collect();
+    for pr in &closed {
+        match mode {
+            SyncMode::Apply => log::info!("Reopening closed PR #{}...", pr.number.green().bold()),
+            SyncMode::DryRun => {
+                println!("Would reopen PR #{} {}", pr.number, remote.pr_url(pr.number))
+            }
+        }
+    }
+    if mode == SyncMode::Apply {
+        let reopens = closed.iter().map(|pr| ReopenPullRequest::new(pr.node_id.clone()));
+        run_batched_graphql(octocrab, reopens).await?;
+    }
+    closed.into_iter().for_each(|pr| pr.state = PullRequestState::Open);
+    Ok(())
+}
+
+/// Gives each commit whose PR is in `closed` a fresh gherrit-pr-id, rewriting
+/// it and every commit above it on `branch_name`.
+fn recreate(
+    repo: &util::Repo,
+    branch_name: &str,
+    tip: ObjectId,
+    commits: &mut [Commit],
+    closed: &[PrState],
+    options: SyncOptions,
+) -> Result<bool> {
+    let naming = BranchNaming::from_config(repo)?;
+    let mut messages = HashMap::new();
+    for commit in commits.iter_mut() {
+        let Some(pr) = closed.iter().find(|pr| pr.head_branch == commit.branch) else {
+            continue;
+        };
+        let fresh_id = commit_msg::derive_gherrit_id((options.id_entropy)(), commit.id.as_bytes());
+        log::info!(
+            "Giving {} the fresh gherrit-pr-id {fresh_id}: PR #{} is closed.",
+            commit.message_title,
+            pr.number.green().bold()
+        );
+        messages.insert(commit.id, replace_gherrit_id(repo, commit, &fresh_id)?);
+        commit.branch = naming.render(&fresh_id, &commit.message_title)?;
+        commit.gherrit_id = fresh_id;
+    }
+    // A dry run writes no objects, so its commits keep their old IDs.
+    if options.mode == SyncMode::DryRun {
+        return Ok(false);
+    }
+
+    // Commits above the synced tip still belong to the branch, so they are
+    // rewritten onto the new tip as well.
+    let branch_ref = format!("refs/heads/{branch_name}");
+    let branch_tip = repo.rev_parse_single(branch_ref.as_str())?.detach();
+    let mut chain: Vec<ObjectId> = commits.iter().map(|commit| commit.id).collect();
+    if branch_tip != tip {
+        let above =
+            repo.commits_between(tip.attach(repo), branch_tip.attach(repo)).map_err(|_| {
+                eyre!("Cannot recreate PRs for commits that are not on branch '{branch_name}'")
+            })?;
+        chain.extend(above.iter().map(|commit| commit.id));
+    }
+
+    let rewritten = rewrite(repo, &chain, &messages)?;
+    commits.iter_mut().for_each(|commit| commit.id = rewritten[&commit.id]);
+    repo.reference(
+        branch_ref,
+        rewritten[&branch_tip],
+        PreviousValue::MustExistAndMatch(branch_tip.into()),
+        "gherrit: recreate PRs of closed commits",
+    )?;
+    log::info!("Rewrote branch {} with the fresh gherrit-pr-ids.", branch_name.yellow());
+    Ok(true)
+}
+
+fn replace_gherrit_id(repo: &util::Repo, commit: &Commit, fresh_id: &str) -> Result<String> {
+    let message = repo.find_commit(commit.id)?.message_raw()?.to_string();
+    let trailer = format!("gherrit-pr-id: {}", commit.gherrit_id);
+    let position = message
+        .rfind(&trailer)
+        .ok_or_else(|| eyre!("Commit {} missing gherrit-pr-id trailer", commit.id))?;
+    let mut message = message;
+    message
+        .replace_range(position..position + trailer.len(), &format!("gherrit-pr-id: {fresh_id}"));
+    Ok(message)
+}
+
+/// Recreates `chain`, a line of commits from the bottom up, replacing the
+/// messages in `messages` and reparenting every commit above a replaced one.
+///
+/// Trees are untouched, so no merge can conflict. Returns the new ID of each
+/// commit in `chain`.
+fn rewrite(
+    repo: &util::Repo,
+    chain: &[ObjectId],
+    messages: &HashMap<ObjectId, String>,
+) -> Result<HashMap<ObjectId, ObjectId>> {
+    let mut rewritten = HashMap::new();
+    let mut parent: Option<(ObjectId, ObjectId)> = None;
+    for &object_id in chain {
+        let mut commit = repo.find_commit(object_id)?.decode()?.to_owned();
+        let message = messages.get(&object_id);
+        let reparent = parent.filter(|(old, new)| old != new);
+        let new_id = if message.is_none() && reparent.is_none() {
+            object_id
+        } else {
+            if let Some(message) = message {
+                commit.message = message.as_str().into();
+            }
+            if let Some((old, new)) = reparent {
+                commit.parents.iter_mut().filter(|id| **id == old).for_each(|id| *id = new);
+            }
+            repo.write_object(&commit)?.detach()
+        };
+        rewritten.insert(object_id, new_id);
+        parent = Some((object_id, new_id));
+    }
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum as _;
+
+    use super::*;
+
+    #[test]
+    fn parses_each_policy() {
+        for (policy, name) in [
+            (ClosedPrPolicy::Error, "error"),
+            (ClosedPrPolicy::Reopen, "reopen"),
+            (ClosedPrPolicy::Recreate, "recreate"),
+        ] {
+            assert_eq!(util::choice_name(&policy), name);
+            assert_eq!(ClosedPrPolicy::from_str(name, false), Ok(policy));
+        }
+        assert!(ClosedPrPolicy::from_str("Reopen", false).is_err());
+        assert_eq!(ClosedPrPolicy::default(), ClosedPrPolicy::Error);
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre::{Context as _, Result};
+use gix::ObjectId;
+use octocrab::Octocrab;
+use owo_colors::OwoColorize as _;
+
+use super::{
+    BranchNaming, GithubEndpoint, PrState, RefLayout, SyncMode, batch_fetch_prs,
+    body::parse_metadata,
+    github_client,
+    lock::SyncLock,
+    publication::{RemoteRef, plan_deletion, push_batches, remote_query_batches},
+    reconcile::PullRequestState,
+    remote::list_gherrit_refs,
+};
+use crate::util::{self, CommandExt as _};
+
+/// What `gherrit gc` does with the version tags of finished PRs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub(crate) enum TagPolicy {
+    /// Keep every version tag.
+    #[default]
+    Keep,
+    /// Delete old version tags except the latest version of each PR.
+    Compact,
+    /// Delete every old version tag.
+    Prune,
+}
+
+/// How to collect the refs of finished PRs.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GcOptions {
+    pub(crate) mode: SyncMode,
+    pub(crate) tags: TagPolicy,
+    /// Version tags of commits younger than this many days are kept.
+    pub(crate) tag_age_days: u64,
+    /// Whether to collect the refs of closed PRs as well as of merged ones.
+    /// GitHub cannot reopen a PR whose head branch is gone.
+    pub(crate) include_closed: bool,
+    pub(crate) layout: RefLayout,
+}
+
+impl GcOptions {
+    const TAGS_KEY: &str = "gherrit.gcTags";
+    const TAG_AGE_KEY: &str = "gherrit.gcTagAge";
+    const DEFAULT_TAG_AGE_DAYS: u64 = 30;
+
+    /// Builds the options configured for `repo`.
+    pub(crate) fn from_config(repo: &util::Repo, mode: SyncMode) -> Result<Self> {
+        let tags = repo.config_choice(Self::TAGS_KEY)?;
+        let tag_age_days = repo
+            .config_string(Self::TAG_AGE_KEY)?
+            .map_or(Ok(Self::DEFAULT_TAG_AGE_DAYS), |value| value.parse())
+            .wrap_err_with(|| {
+                format!("Invalid {}: expected a number of days", Self::TAG_AGE_KEY)
+            })?;
+        let layout = RefLayout::from_config(repo)?;
+        Ok(Self { mode, tags, tag_age_days, include_closed: false, layout })
+    }
+}
+
+/// Deletes the managed branches of merged PRs, and of closed PRs if
+/// `options.include_closed`, from the remote and, depending on
+/// `options.tags`, their old version tags.
+///
+/// The sync lock is held throughout, so that no sync publishes to a branch
+/// while it is being deleted.
+pub async fn run(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    options: GcOptions,
+) -> Result<()> {
+    let _lock = SyncLock::acquire(repo)?;
+    let naming = BranchNaming::from_config(repo)?;
+    let refs = list_gherrit_refs(repo, options.layout, &naming)
+        .wrap_err("Failed to list the remote's GHerrit refs")?;
+    let mut heads: BTreeSet<String> = refs.branches.keys().cloned().collect();
+    if options.tags != TagPolicy::Keep {
+        // GitHub still finds a PR by its head branch once the branch is gone,
+        // but only a static template can name that branch from the tags.
+        for id in refs.tags.keys() {
+            heads.insert(id.clone());
+            heads.extend(naming.render_static(id));
+        }
+    }
+    if heads.is_empty() {
+        log::info!("The remote has no GHerrit refs to collect.");
+        return Ok(());
+    }
+
+    let octocrab = github_client(github_endpoint)?;
+    let heads: Vec<String> = heads.into_iter().collect();
+    // Each finished PR, along with its gherrit-pr-id.
+    let finished: Vec<(PrState, String)> = batch_fetch_prs(repo, &octocrab, &heads)
+        .await?
+        .into_iter()
+        .filter(|pr| match pr.state {
+            PullRequestState::Merged => true,
+            PullRequestState::Closed => options.include_closed,
+            PullRequestState::Open => false,
+        })
+        .filter_map(|pr| {
+            let metadata = pr.body.as_deref().and_then(parse_metadata)?;
+            // Only a PR whose body GHerrit wrote owns its head branch.
+            (metadata.branch() == pr.head_branch).then_some((pr, metadata.id))
+        })
+        .collect();
+    let in_use = branches_in_use(repo, &octocrab, finished.iter().map(|(pr, _)| pr)).await?;
+
+    let mut old_tags = HashSet::new();
+    if options.tags != TagPolicy::Keep {
+        let tags: Vec<&RemoteRef> = finished
+            .iter()
+            .filter_map(|(_, id)| refs.tags.get(id))
+            .flatten()
+            .map(|(_, tag)| tag)
+            .collect();
+        let age = Duration::from_secs(options.tag_age_days.saturating_mul(24 * 60 * 60));
+        let cutoff = SystemTime::now().checked_sub(age).unwrap_or(UNIX_EPOCH);
+        old_tags = find_old_tags(repo, &tags, cutoff)?;
+    }
+
+    let mut doomed = Vec::new();
+    let mut num_tags = 0;
+    for (pr, id) in &finished {
+        let name = &pr.head_branch;
+        let state = if pr.state == PullRequestState::Merged { "merged" } else { "closed" };
+        if let Some(branch) = refs.branches.get(name) {
+            if in_use.contains(name) {
+                log::warn!(
+                    "Keeping managed branch {}: an open PR is still based on it.",
+                    name.yellow()
+                );
+            } else {
+                log::info!(
+                    "Collecting managed branch {}: PR #{} is {state}.",
+                    name.yellow(),
+                    pr.number.green().bold()
+                );
+                doomed.push(branch.clone());
+            }
+        }
+
+        let versions = refs.tags.get(id).map(Vec::as_slice).unwrap_or_default();
+        let tags = select_tags(versions, options.tags, |tag| old_tags.contains(&tag.name));
+        if !tags.is_empty() {
+            log::info!("Collecting {} version tags of {}.", tags.len(), id.yellow());
+            num_tags += tags.len();
+            doomed.extend(tags.into_iter().cloned());
+        }
+    }
+
+    if doomed.is_empty() {
+        log::info!("No refs to collect.");
+        return Ok(());
+    }
+    if options.mode == SyncMode::DryRun {
+        doomed.iter().for_each(|remote_ref| println!("Would delete {}", remote_ref.name));
+        return Ok(());
+    }
+
+    let remote = repo.default_remote_name();
+    for chunk in push_batches(&doomed) {
+        // Capture the output, which holds only GitHub's "Create a pull
+        // request" hints unless the push fails.
+        util::cmd("git", plan_deletion(&remote, chunk)).checked_output().wrap_err(
+            "Failed to delete refs. Another push may have updated them; run 'gherrit gc' again.",
+        )?;
+    }
+    log::info!("Deleted {} managed branches and {num_tags} version tags.", doomed.len() - num_tags);
+    Ok(())
+}
+
+/// Returns the managed branches among `finished` that an open PR is still
+/// based on. Deleting such a branch would make GitHub close the PR.
+async fn branches_in_use<'a>(
+    repo: &util::Repo,
+    octocrab: &Octocrab,
+    finished: impl Iterator<Item = &'a PrState>,
+) -> Result<HashSet<String>> {
+    let children: Vec<String> = finished
+        .filter_map(|pr| Some(parse_metadata(pr.body.as_deref()?)?.child_branch()?.to_string()))
+        .collect();
+    Ok(batch_fetch_prs(repo, octocrab, &children)
+        .await?
+        .into_iter()
+        .filter(|child| child.state == PullRequestState::Open)
+        .map(|child| child.base_branch)
+        .collect())
+}
+
+/// Returns the names of the `tags` whose commits were committed before
+/// `cutoff`, fetching any commits that are missing locally.
+fn find_old_tags(
+    repo: &util::Repo,
+    tags: &[&RemoteRef],
+    cutoff: SystemTime,
+) -> Result<HashSet<String>> {
+    let object_id = |tag: &RemoteRef| ObjectId::from_hex(tag.object_id.as_bytes());
+    let mut missing = Vec::new();
+    for tag in tags {
+        if !repo.has_object(object_id(tag)?) {
+            missing.push(tag.name.clone());
+        }
+    }
+    for chunk in remote_query_batches(&missing) {
+        let arguments = ["fetch", "--quiet", "--no-tags", "--no-write-fetch-head"]
+            .into_iter()
+            .map(ToString::to_string)
+            .chain([repo.default_remote_name()])
+            .chain(chunk.iter().cloned());
+        util::cmd("git", arguments).success().wrap_err("Failed to fetch version tags")?;
+    }
+
+    let cutoff = cutoff.duration_since(UNIX_EPOCH).map_or(0, |cutoff| cutoff.as_secs());
+    let mut old = HashSet::new();
+    for tag in tags {
+        let time = repo.find_commit(object_id(tag)?)?.time()?;
+        if u64::try_from(time.seconds).is_ok_and(|seconds| seconds < cutoff) {
+            old.insert(tag.name.clone());
+        }
+    }
+    Ok(old)
+}
+
+/// Selects the version tags of one finished PR that `policy` deletes.
+///
+/// `versions` is in ascending version order. Only tags for which `is_old`
+/// holds are ever selected.
+fn select_tags(
+    versions: &[(usize, RemoteRef)],
+    policy: TagPolicy,
+    is_old: impl Fn(&RemoteRef) -> bool,
+) -> Vec<&RemoteRef> {
+    let candidates = match policy {
+        TagPolicy::Keep => return Vec::new(),
+        TagPolicy::Compact => versions.split_last().map_or(&[][..], |(_, older)| older),
+        TagPolicy::Prune => versions,
+    };
+    candidates.iter().map(|(_, tag)| tag).filter(|tag| is_old(tag)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum as _;
+
+    use super::*;
+
+    fn versions(count: usize) -> Vec<(usize, RemoteRef)> {
+        (1..=count)
+            .map(|version| {
+                let name = format!("refs/tags/gherrit/Gone/v{version}");
+                (version, RemoteRef { name, object_id: String::new() })
+            })
+            .collect()
+    }
+
+    fn names(tags: Vec<&RemoteRef>) -> Vec<&str> {
+        tags.into_iter().map(|tag| tag.name.as_str()).collect()
+    }
+
+    #[test]
+    fn selects_old_tags_by_policy() {
+        let versions = versions(3);
+        let all_old = |_: &RemoteRef| true;
+        assert!(select_tags(&versions, TagPolicy::Keep, all_old).is_empty());
+        assert_eq!(
+            names(select_tags(&versions, TagPolicy::Compact, all_old)),
+            ["refs/tags/gherrit/Gone/v1", "refs/tags/gherrit/Gone/v2"]
+        );
+        assert_eq!(names(select_tags(&versions, TagPolicy::Prune, all_old)).len(), 3);
+
+        let only_v1_old = |tag: &RemoteRef| tag.name.ends_with("/v1");
+        assert_eq!(
+            names(select_tags(&versions, TagPolicy::Prune, only_v1_old)),
+            ["refs/tags/gherrit/Gone/v1"]
+        );
+        assert!(select_tags(&[], TagPolicy::Compact, all_old).is_empty());
+    }
+
+    #[test]
+    fn parses_each_tag_policy() {
+        for (policy, name) in [
+            (TagPolicy::Keep, "keep"),
+            (TagPolicy::Compact, "compact"),
+            (TagPolicy::Prune, "prune"),
+        ] {
+            assert_eq!(util::choice_name(&policy), name);
+            assert_eq!(TagPolicy::from_str(name, false), Ok(policy));
+        }
+        assert!(TagPolicy::from_str("all", false).is_err());
+    }
+}
@@ -2,7 +2,11 @@ use color_eyre::eyre::{Context as _, Result, bail, eyre};
 use serde::Deserialize;
 use serde_json::{Value, json};
 
-use super::reconcile::PullRequestState;
+use super::{
+    land::MergeMethod,
+    reconcile::{PullRequestState, ReviewDecision},
+};
+use crate::util;
 
 const MAX_PULL_REQUEST_CANDIDATES: usize = 100;
 
@@ -15,6 +19,11 @@ pub(super) struct PullRequest {
     pub(super) base_branch: String,
     pub(super) head_branch: String,
     pub(super) state: PullRequestState,
+    pub(super) is_draft: bool,
+    /// How the PR's reviews decide on it, if its base branch requires
+    /// reviews.
+    pub(super) review_decision: Option<ReviewDecision>,
+    pub(super) is_in_merge_queue: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -157,7 +166,7 @@ impl BatchedOperation for FindPullRequest {
     fn document(&self) -> String {
         let connection = |alias: &str, states: &str| {
             format!(
-                "{alias}: pullRequests(headRefName: {}, first: {MAX_PULL_REQUEST_CANDIDATES}, states: {states}) {{ nodes {{ number, id, title, body, baseRefName, state, isCrossRepository }} pageInfo {{ hasNextPage }} }}",
+                "{alias}: pullRequests(headRefName: {}, first: {MAX_PULL_REQUEST_CANDIDATES}, states: {states}) {{ nodes {{ number, id, title, body, baseRefName, state, isDraft, reviewDecision, isInMergeQueue, isCrossRepository }} pageInfo {{ hasNextPage }} }}",
                 json!(self.head_branch),
             )
         };
@@ -200,6 +209,9 @@ impl BatchedOperation for FindPullRequest {
             body: Option<String>,
             base_ref_name: String,
             state: PullRequestState,
+            is_draft: bool,
+            review_decision: Option<ReviewDecision>,
+            is_in_merge_queue: bool,
             is_cross_repository: bool,
         }
 
@@ -246,10 +258,167 @@ impl BatchedOperation for FindPullRequest {
             base_branch: node.base_ref_name,
             head_branch: self.head_branch.clone(),
             state: node.state,
+            is_draft: node.is_draft,
+            review_decision: node.review_decision,
+            is_in_merge_queue: node.is_in_merge_queue,
         }))
     }
 }
 
+/// Looks up a PR by its number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PullRequestByNumber {
+    owner: String,
+    repository: String,
+    number: u64,
+}
+
+impl PullRequestByNumber {
+    pub(super) fn new(owner: String, repository: String, number: u64) -> Self {
+        Self { owner, repository, number }
+    }
+}
+
+impl BatchedOperation for PullRequestByNumber {
+    type Output = Option<PullRequest>;
+
+    const TYPE: OperationType = OperationType::Query;
+
+    fn document(&self) -> String {
+        format!(
+            "repository(owner: {}, name: {}) {{ pullRequest(number: {}) {{ number, id, title, body, baseRefName, headRefName, state, isDraft, reviewDecision, isInMergeQueue, isCrossRepository }} }}",
+            json!(self.owner),
+            json!(self.repository),
+            self.number,
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Response {
+            pull_request: Option<Node>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Node {
+            number: u64,
+            id: String,
+            title: Option<String>,
+            body: Option<String>,
+            base_ref_name: String,
+            head_ref_name: String,
+            state: PullRequestState,
+            is_draft: bool,
+            review_decision: Option<ReviewDecision>,
+            is_in_merge_queue: bool,
+            is_cross_repository: bool,
+        }
+
+        let response: Response = serde_json::from_value(response)
+            .wrap_err("Failed to decode pull request query response")?;
+        let Some(node) = response.pull_request else {
+            return Ok(None);
+        };
+        if node.is_cross_repository {
+            bail!("PR #{} comes from another repository, so it has no GHerrit stack", node.number);
+        }
+        Ok(Some(PullRequest {
+            number: node.number,
+            node_id: node.id,
+            title: node.title,
+            body: node.body,
+            base_branch: node.base_ref_name,
+            head_branch: node.head_ref_name,
+            state: node.state,
+            is_draft: node.is_draft,
+            review_decision: node.review_decision,
+            is_in_merge_queue: node.is_in_merge_queue,
+        }))
+    }
+}
+
+/// Looks up the users who reviewed a PR, other than its author, as the
+/// logins of the authors of its latest reviews.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PullRequestReviewers {
+    owner: String,
+    repository: String,
+    number: u64,
+}
+
+impl PullRequestReviewers {
+    pub(super) fn new(owner: String, repository: String, number: u64) -> Self {
+        Self { owner, repository, number }
+    }
+}
+
+impl BatchedOperation for PullRequestReviewers {
+    type Output = Vec<String>;
+
+    const TYPE: OperationType = OperationType::Query;
+
+    fn document(&self) -> String {
+        format!(
+            "repository(owner: {}, name: {}) {{ pullRequest(number: {}) {{ author {{ __typename, login }} latestReviews(first: {MAX_PULL_REQUEST_CANDIDATES}) {{ nodes {{ author {{ __typename, login }} }} }} }} }}",
+            json!(self.owner),
+            json!(self.repository),
+            self.number,
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Response {
+            pull_request: Option<Node>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Node {
+            author: Option<Actor>,
+            latest_reviews: Reviews,
+        }
+
+        #[derive(Deserialize)]
+        struct Reviews {
+            nodes: Vec<Review>,
+        }
+
+        #[derive(Deserialize)]
+        struct Review {
+            author: Option<Actor>,
+        }
+
+        #[derive(Deserialize)]
+        struct Actor {
+            #[serde(rename = "__typename")]
+            typename: String,
+            login: String,
+        }
+
+        let response: Response = serde_json::from_value(response)
+            .wrap_err("Failed to decode pull request reviewers response")?;
+        let Some(node) = response.pull_request else {
+            bail!("PR #{} does not exist", self.number);
+        };
+        let author = node.author.map(|author| author.login);
+        // Only users can be requested to review; deleted accounts and apps
+        // cannot.
+        Ok(node
+            .latest_reviews
+            .nodes
+            .into_iter()
+            .filter_map(|review| review.author)
+            .filter(|reviewer| reviewer.typename == "User")
+            .map(|reviewer| reviewer.login)
+            .filter(|login| Some(login) != author.as_ref())
+            .collect())
+    }
+}
+
 /// A request to create a PR for one commit in the stack.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct CreatePullRequest {
@@ -258,6 +427,7 @@ pub(super) struct CreatePullRequest {
     head_branch: String,
     title: String,
     body: String,
+    draft: bool,
 }
 
 impl CreatePullRequest {
@@ -267,8 +437,9 @@ impl CreatePullRequest {
         head_branch: String,
         title: String,
         body: String,
+        draft: bool,
     ) -> Self {
-        Self { repository_id, base_branch, head_branch, title, body }
+        Self { repository_id, base_branch, head_branch, title, body, draft }
     }
 }
 
@@ -287,7 +458,10 @@ impl BatchedOperation for CreatePullRequest {
         ]
         .map(|(name, value)| format!("{name}: {}", json!(value)))
         .join(", ");
-        format!("createPullRequest(input: {{ {fields} }}) {{ pullRequest {{ number, url, id }} }}")
+        let draft = if self.draft { ", draft: true" } else { "" };
+        format!(
+            "createPullRequest(input: {{ {fields}{draft} }}) {{ pullRequest {{ number, url, id }} }}"
+        )
     }
 
     fn decode(&self, response: Value) -> Result<Self::Output> {
@@ -369,6 +543,562 @@ impl BatchedOperation for UpdatePullRequest {
     }
 }
 
+/// Converts an open PR to a draft, or marks a draft PR as ready for review.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct SetPullRequestDraft {
+    node_id: String,
+    draft: bool,
+}
+
+impl SetPullRequestDraft {
+    pub(super) fn new(node_id: String, draft: bool) -> Self {
+        Self { node_id, draft }
+    }
+}
+
+impl BatchedOperation for SetPullRequestDraft {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        let mutation =
+            if self.draft { "convertPullRequestToDraft" } else { "markPullRequestReadyForReview" };
+        format!(
+            "{mutation}(input: {{ pullRequestId: {} }}) {{ clientMutationId }}",
+            json!(self.node_id)
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        if response.is_null() {
+            let action = if self.draft { "convert" } else { "mark" };
+            let state = if self.draft { "to a draft" } else { "as ready for review" };
+            bail!(
+                "The batched GraphQL mutation failed to {action} PR with node ID '{}' {state}. The response for this operation was null.",
+                self.node_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Looks up the global node ID of a user, team, or label, as the mutations
+/// that request reviews, add labels, and add assignees require.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) enum NodeLookup {
+    User(String),
+    /// The authenticated user.
+    Viewer,
+    Team {
+        organization: String,
+        slug: String,
+    },
+    Label {
+        owner: String,
+        repository: String,
+        name: String,
+    },
+}
+
+impl BatchedOperation for NodeLookup {
+    type Output = String;
+
+    const TYPE: OperationType = OperationType::Query;
+
+    fn document(&self) -> String {
+        match self {
+            Self::User(login) => format!("user(login: {}) {{ id }}", json!(login)),
+            Self::Viewer => "viewer { id }".to_string(),
+            Self::Team { organization, slug } => format!(
+                "organization(login: {}) {{ team(slug: {}) {{ id }} }}",
+                json!(organization),
+                json!(slug)
+            ),
+            Self::Label { owner, repository, name } => format!(
+                "repository(owner: {}, name: {}) {{ label(name: {}) {{ id }} }}",
+                json!(owner),
+                json!(repository),
+                json!(name)
+            ),
+        }
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        let node = match self {
+            Self::User(_) | Self::Viewer => Some(&response),
+            Self::Team { .. } => response.get("team"),
+            Self::Label { .. } => response.get("label"),
+        };
+        match node.and_then(|node| node.get("id")).and_then(Value::as_str) {
+            Some(id) => Ok(id.to_string()),
+            None => match self {
+                Self::User(login) => bail!("GitHub has no user '{login}'"),
+                Self::Viewer => bail!("GitHub did not identify the authenticated user"),
+                Self::Team { organization, slug } => {
+                    bail!("GitHub has no team '{organization}/{slug}'")
+                }
+                Self::Label { owner, repository, name } => {
+                    bail!("Repository {owner}/{repository} has no label '{name}'")
+                }
+            },
+        }
+    }
+}
+
+/// Requests reviews of a PR from users and teams, keeping earlier requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RequestReviews {
+    node_id: String,
+    user_ids: Vec<String>,
+    team_ids: Vec<String>,
+}
+
+impl RequestReviews {
+    pub(super) fn new(node_id: String, user_ids: Vec<String>, team_ids: Vec<String>) -> Self {
+        Self { node_id, user_ids, team_ids }
+    }
+}
+
+impl BatchedOperation for RequestReviews {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        let ids = [("userIds", &self.user_ids), ("teamIds", &self.team_ids)]
+            .into_iter()
+            .filter(|(_, ids)| !ids.is_empty())
+            .map(|(name, ids)| format!(", {name}: {}", json!(ids)))
+            .collect::<String>();
+        format!(
+            "requestReviews(input: {{ pullRequestId: {}{ids}, union: true }}) {{ clientMutationId }}",
+            json!(self.node_id)
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        if response.is_null() {
+            bail!(
+                "The batched GraphQL mutation failed to request reviews of PR with node ID '{}'. The response for this operation was null.",
+                self.node_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Adds labels to a PR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct AddLabels {
+    node_id: String,
+    label_ids: Vec<String>,
+}
+
+impl AddLabels {
+    pub(super) fn new(node_id: String, label_ids: Vec<String>) -> Self {
+        Self { node_id, label_ids }
+    }
+}
+
+impl BatchedOperation for AddLabels {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        format!(
+            "addLabelsToLabelable(input: {{ labelableId: {}, labelIds: {} }}) {{ clientMutationId }}",
+            json!(self.node_id),
+            json!(self.label_ids)
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        if response.is_null() {
+            bail!(
+                "The batched GraphQL mutation failed to label PR with node ID '{}'. The response for this operation was null.",
+                self.node_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Adds assignees to a PR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct AddAssignees {
+    node_id: String,
+    assignee_ids: Vec<String>,
+}
+
+impl AddAssignees {
+    pub(super) fn new(node_id: String, assignee_ids: Vec<String>) -> Self {
+        Self { node_id, assignee_ids }
+    }
+}
+
+impl BatchedOperation for AddAssignees {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        format!(
+            "addAssigneesToAssignable(input: {{ assignableId: {}, assigneeIds: {} }}) {{ clientMutationId }}",
+            json!(self.node_id),
+            json!(self.assignee_ids)
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        if response.is_null() {
+            bail!(
+                "The batched GraphQL mutation failed to assign PR with node ID '{}'. The response for this operation was null.",
+                self.node_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// One of the mutations that reconcile an existing PR, so that changes to its
+/// metadata, draft state, reviewers, labels, and assignees share adaptive
+/// batches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum PullRequestChange {
+    Update(UpdatePullRequest),
+    SetDraft(SetPullRequestDraft),
+    RequestReviews(RequestReviews),
+    AddLabels(AddLabels),
+    AddAssignees(AddAssignees),
+}
+
+impl BatchedOperation for PullRequestChange {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        match self {
+            Self::Update(update) => update.document(),
+            Self::SetDraft(set_draft) => set_draft.document(),
+            Self::RequestReviews(request) => request.document(),
+            Self::AddLabels(add) => add.document(),
+            Self::AddAssignees(add) => add.document(),
+        }
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        match self {
+            Self::Update(update) => update.decode(response),
+            Self::SetDraft(set_draft) => set_draft.decode(response),
+            Self::RequestReviews(request) => request.decode(response),
+            Self::AddLabels(add) => add.decode(response),
+            Self::AddAssignees(add) => add.decode(response),
+        }
+    }
+}
+
+/// Reopens a closed PR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ReopenPullRequest {
+    node_id: String,
+}
+
+impl ReopenPullRequest {
+    pub(super) fn new(node_id: String) -> Self {
+        Self { node_id }
+    }
+}
+
+impl BatchedOperation for ReopenPullRequest {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        format!(
+            "reopenPullRequest(input: {{ pullRequestId: {} }}) {{ clientMutationId }}",
+            json!(self.node_id)
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        if response.is_null() {
+            bail!(
+                "The batched GraphQL mutation failed to reopen PR with node ID '{}'. The response for this operation was null.",
+                self.node_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Closes an open PR without merging it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ClosePullRequest {
+    node_id: String,
+}
+
+impl ClosePullRequest {
+    pub(super) fn new(node_id: String) -> Self {
+        Self { node_id }
+    }
+}
+
+impl BatchedOperation for ClosePullRequest {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        format!(
+            "closePullRequest(input: {{ pullRequestId: {} }}) {{ clientMutationId }}",
+            json!(self.node_id)
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        if response.is_null() {
+            bail!(
+                "The batched GraphQL mutation failed to close PR with node ID '{}'. The response for this operation was null.",
+                self.node_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// How the checks of a commit concluded, as GitHub rolls them up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(super) enum CheckState {
+    Error,
+    Expected,
+    Failure,
+    Pending,
+    Success,
+}
+
+/// Whether a PR may land, as [`PullRequestLandability`] finds it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Landability {
+    pub(super) review_decision: Option<ReviewDecision>,
+    /// How the latest review of each reviewer decides on the PR, whether or
+    /// not its base branch requires reviews.
+    pub(super) latest_reviews: ReviewDecision,
+    /// The rolled-up checks of the PR's head commit, if it has any.
+    pub(super) checks: Option<CheckState>,
+    pub(super) is_merge_queue_enabled: bool,
+}
+
+/// Looks up the reviews, checks, and merge queue that decide whether a PR may
+/// land.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PullRequestLandability {
+    owner: String,
+    repository: String,
+    number: u64,
+}
+
+impl PullRequestLandability {
+    pub(super) fn new(owner: String, repository: String, number: u64) -> Self {
+        Self { owner, repository, number }
+    }
+}
+
+impl BatchedOperation for PullRequestLandability {
+    type Output = Landability;
+
+    const TYPE: OperationType = OperationType::Query;
+
+    fn document(&self) -> String {
+        format!(
+            "repository(owner: {}, name: {}) {{ pullRequest(number: {}) {{ reviewDecision, latestReviews(first: {MAX_PULL_REQUEST_CANDIDATES}) {{ nodes {{ state }} }} isMergeQueueEnabled, commits(last: 1) {{ nodes {{ commit {{ statusCheckRollup {{ state }} }} }} }} }} }}",
+            json!(self.owner),
+            json!(self.repository),
+            self.number,
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Response {
+            pull_request: Option<Node>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Node {
+            review_decision: Option<ReviewDecision>,
+            latest_reviews: Reviews,
+            is_merge_queue_enabled: bool,
+            commits: Commits,
+        }
+
+        #[derive(Deserialize)]
+        struct Reviews {
+            nodes: Vec<Review>,
+        }
+
+        #[derive(Deserialize)]
+        struct Review {
+            state: ReviewState,
+        }
+
+        #[derive(Deserialize, PartialEq, Eq)]
+        #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+        enum ReviewState {
+            Approved,
+            ChangesRequested,
+            #[serde(other)]
+            Other,
+        }
+
+        #[derive(Deserialize)]
+        struct Commits {
+            nodes: Vec<CommitNode>,
+        }
+
+        #[derive(Deserialize)]
+        struct CommitNode {
+            commit: Commit,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Commit {
+            status_check_rollup: Option<Rollup>,
+        }
+
+        #[derive(Deserialize)]
+        struct Rollup {
+            state: CheckState,
+        }
+
+        let response: Response = serde_json::from_value(response)
+            .wrap_err("Failed to decode pull request landability response")?;
+        let Some(node) = response.pull_request else {
+            bail!("PR #{} does not exist", self.number);
+        };
+        let checks = node
+            .commits
+            .nodes
+            .into_iter()
+            .next_back()
+            .and_then(|node| node.commit.status_check_rollup)
+            .map(|rollup| rollup.state);
+        let states: Vec<_> =
+            node.latest_reviews.nodes.into_iter().map(|review| review.state).collect();
+        let latest_reviews = if states.contains(&ReviewState::ChangesRequested) {
+            ReviewDecision::ChangesRequested
+        } else if states.contains(&ReviewState::Approved) {
+            ReviewDecision::Approved
+        } else {
+            ReviewDecision::ReviewRequired
+        };
+        Ok(Landability {
+            review_decision: node.review_decision,
+            latest_reviews,
+            checks,
+            is_merge_queue_enabled: node.is_merge_queue_enabled,
+        })
+    }
+}
+
+/// Merges a PR with `method`, or adds it to the merge queue, as long as its
+/// head is still `expected_head_oid`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct MergePullRequest {
+    node_id: String,
+    expected_head_oid: String,
+    enqueue: bool,
+    method: MergeMethod,
+}
+
+impl MergePullRequest {
+    pub(super) fn new(
+        node_id: String,
+        expected_head_oid: String,
+        enqueue: bool,
+        method: MergeMethod,
+    ) -> Self {
+        Self { node_id, expected_head_oid, enqueue, method }
+    }
+}
+
+impl BatchedOperation for MergePullRequest {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        // A merge queue merges with its own method.
+        let (mutation, method) = match self.enqueue {
+            true => ("enqueuePullRequest", String::new()),
+            false => (
+                "mergePullRequest",
+                format!(", mergeMethod: {}", util::choice_name(&self.method).to_uppercase()),
+            ),
+        };
+        format!(
+            "{mutation}(input: {{ pullRequestId: {}, expectedHeadOid: {}{method} }}) {{ clientMutationId }}",
+            json!(self.node_id),
+            json!(self.expected_head_oid)
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        if response.is_null() {
+            let action = if self.enqueue { "enqueue" } else { "merge" };
+            bail!(
+                "The batched GraphQL mutation failed to {action} PR with node ID '{}'. The response for this operation was null.",
+                self.node_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Adds a comment to a PR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct AddComment {
+    subject_id: String,
+    body: String,
+}
+
+impl AddComment {
+    pub(super) fn new(subject_id: String, body: String) -> Self {
+        Self { subject_id, body }
+    }
+}
+
+impl BatchedOperation for AddComment {
+    type Output = ();
+
+    const TYPE: OperationType = OperationType::Mutation;
+
+    fn document(&self) -> String {
+        format!(
+            "addComment(input: {{ subjectId: {}, body: {} }}) {{ clientMutationId }}",
+            json!(self.subject_id),
+            json!(self.body)
+        )
+    }
+
+    fn decode(&self, response: Value) -> Result<Self::Output> {
+        if response.is_null() {
+            bail!(
+                "The batched GraphQL mutation failed to comment on node ID '{}'. The response for this operation was null.",
+                self.subject_id
+            );
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -381,6 +1111,9 @@ mod tests {
             "body": null,
             "baseRefName": "main",
             "state": state,
+            "isDraft": false,
+            "reviewDecision": null,
+            "isInMergeQueue": false,
             "isCrossRepository": is_cross_repository,
         })
     }
@@ -426,7 +1159,7 @@ mod tests {
 
         assert_eq!(
             query.document(),
-            r#"repository(owner: "o\"wner", name: "repo\nname") { open: pullRequests(headRefName: "head\\branch", first: 100, states: [OPEN]) { nodes { number, id, title, body, baseRefName, state, isCrossRepository } pageInfo { hasNextPage } } historical: pullRequests(headRefName: "head\\branch", first: 100, states: [CLOSED, MERGED]) { nodes { number, id, title, body, baseRefName, state, isCrossRepository } pageInfo { hasNextPage } } }"#
+            r#"repository(owner: "o\"wner", name: "repo\nname") { open: pullRequests(headRefName: "head\\branch", first: 100, states: [OPEN]) { nodes { number, id, title, body, baseRefName, state, isDraft, reviewDecision, isInMergeQueue, isCrossRepository } pageInfo { hasNextPage } } historical: pullRequests(headRefName: "head\\branch", first: 100, states: [CLOSED, MERGED]) { nodes { number, id, title, body, baseRefName, state, isDraft, reviewDecision, isInMergeQueue, isCrossRepository } pageInfo { hasNextPage } } }"#
         );
     }
 
@@ -438,6 +1171,7 @@ mod tests {
             "head\\branch".to_string(),
             "A \"title\"".to_string(),
             "line one\nline two".to_string(),
+            false,
         );
 
         assert_eq!(
@@ -446,6 +1180,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn create_document_requests_a_draft_only_when_asked() {
+        let create = |draft| {
+            CreatePullRequest::new(
+                "R_1".to_string(),
+                "main".to_string(),
+                "G123".to_string(),
+                "Title".to_string(),
+                "Body".to_string(),
+                draft,
+            )
+            .document()
+        };
+
+        assert_eq!(
+            create(true),
+            r#"createPullRequest(input: { repositoryId: "R_1", baseRefName: "main", headRefName: "G123", title: "Title", body: "Body", draft: true }) { pullRequest { number, url, id } }"#
+        );
+        assert!(!create(false).contains("draft"));
+    }
+
     #[test]
     fn update_document_omits_unchanged_fields() {
         let update = UpdatePullRequest::new(
@@ -461,6 +1216,184 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reopen_document_names_the_pull_request() {
+        let reopen = ReopenPullRequest::new("PR_node".to_string());
+
+        assert_eq!(
+            reopen.document(),
+            r#"reopenPullRequest(input: { pullRequestId: "PR_node" }) { clientMutationId }"#
+        );
+        assert!(reopen.decode(Value::Null).is_err());
+    }
+
+    #[test]
+    fn close_and_comment_documents_escape_their_inputs() {
+        assert_eq!(
+            ClosePullRequest::new("PR_node".to_string()).document(),
+            r#"closePullRequest(input: { pullRequestId: "PR_node" }) { clientMutationId }"#
+        );
+        assert_eq!(
+            AddComment::new("PR_node".to_string(), "No longer \"needed\"".to_string()).document(),
+            r#"addComment(input: { subjectId: "PR_node", body: "No longer \"needed\"" }) { clientMutationId }"#
+        );
+    }
+
+    #[test]
+    fn draft_documents_name_the_pull_request() {
+        assert_eq!(
+            SetPullRequestDraft::new("PR_node".to_string(), true).document(),
+            r#"convertPullRequestToDraft(input: { pullRequestId: "PR_node" }) { clientMutationId }"#
+        );
+        let ready = SetPullRequestDraft::new("PR_node".to_string(), false);
+        assert_eq!(
+            ready.document(),
+            r#"markPullRequestReadyForReview(input: { pullRequestId: "PR_node" }) { clientMutationId }"#
+        );
+        assert_eq!(
+            ready.decode(Value::Null).unwrap_err().to_string(),
+            "The batched GraphQL mutation failed to mark PR with node ID 'PR_node' as ready for review. The response for this operation was null."
+        );
+    }
+
+    #[test]
+    fn pull_request_changes_share_a_batch() {
+        let operations = [
+            PullRequestChange::Update(UpdatePullRequest::new(
+                "PR_1".to_string(),
+                Some("Title".to_string()),
+                None,
+                None,
+            )),
+            PullRequestChange::SetDraft(SetPullRequestDraft::new("PR_1".to_string(), true)),
+        ];
+
+        assert_eq!(
+            batch_document(&operations),
+            r#"mutation { op0: updatePullRequest(input: { pullRequestId: "PR_1", title: "Title" }) { clientMutationId }op1: convertPullRequestToDraft(input: { pullRequestId: "PR_1" }) { clientMutationId } }"#
+        );
+    }
+
+    #[test]
+    fn reviewers_are_the_users_who_reviewed_other_than_the_author() {
+        let lookup = PullRequestReviewers::new("owner".to_string(), "repo".to_string(), 7);
+        assert_eq!(
+            lookup.document(),
+            "repository(owner: \"owner\", name: \"repo\") { pullRequest(number: 7) { author { __typename, login } latestReviews(first: 100) { nodes { author { __typename, login } } } } }"
+        );
+
+        let actor = |typename: &str, login: &str| json!({ "__typename": typename, "login": login });
+        let response = json!({
+            "pullRequest": {
+                "author": actor("User", "author"),
+                "latestReviews": { "nodes": [
+                    { "author": actor("User", "alice") },
+                    { "author": actor("User", "author") },
+                    { "author": actor("Bot", "ci") },
+                    { "author": null },
+                    { "author": actor("User", "bob") },
+                ] },
+            }
+        });
+        assert_eq!(lookup.decode(response).unwrap(), ["alice", "bob"]);
+        assert!(lookup.decode(json!({ "pullRequest": null })).is_err());
+    }
+
+    #[test]
+    fn node_lookups_name_each_kind_of_node() {
+        let team = NodeLookup::Team { organization: "org".to_string(), slug: "team".to_string() };
+        let label = NodeLookup::Label {
+            owner: "owner".to_string(),
+            repository: "repo".to_string(),
+            name: "good \"first\" issue".to_string(),
+        };
+
+        assert_eq!(
+            batch_document(&[NodeLookup::User("alice".to_string()), NodeLookup::Viewer]),
+            r#"query { op0: user(login: "alice") { id }op1: viewer { id } }"#
+        );
+        assert_eq!(team.document(), r#"organization(login: "org") { team(slug: "team") { id } }"#);
+        assert_eq!(
+            label.document(),
+            r#"repository(owner: "owner", name: "repo") { label(name: "good \"first\" issue") { id } }"#
+        );
+
+        assert_eq!(team.decode(json!({ "team": { "id": "T_1" } })).unwrap(), "T_1");
+        assert_eq!(NodeLookup::Viewer.decode(json!({ "id": "U_1" })).unwrap(), "U_1");
+        assert_eq!(
+            team.decode(json!({ "team": null })).unwrap_err().to_string(),
+            "GitHub has no team 'org/team'"
+        );
+        assert_eq!(
+            NodeLookup::User("ghost".to_string()).decode(Value::Null).unwrap_err().to_string(),
+            "GitHub has no user 'ghost'"
+        );
+    }
+
+    #[test]
+    fn decodes_landability_and_merges_only_the_expected_head() {
+        let query = PullRequestLandability::new("owner".to_string(), "repo".to_string(), 7);
+        let response = |rollup: Value| {
+            json!({
+                "pullRequest": {
+                    "reviewDecision": "APPROVED",
+                    "latestReviews": { "nodes": [{ "state": "COMMENTED" }, { "state": "APPROVED" }] },
+                    "isMergeQueueEnabled": true,
+                    "commits": { "nodes": [{ "commit": { "statusCheckRollup": rollup } }] },
+                }
+            })
+        };
+
+        assert_eq!(
+            query.decode(response(json!({ "state": "PENDING" }))).unwrap(),
+            Landability {
+                review_decision: Some(ReviewDecision::Approved),
+                latest_reviews: ReviewDecision::Approved,
+                checks: Some(CheckState::Pending),
+                is_merge_queue_enabled: true,
+            }
+        );
+        assert_eq!(query.decode(response(Value::Null)).unwrap().checks, None);
+        assert!(query.decode(json!({ "pullRequest": null })).is_err());
+
+        assert_eq!(
+            MergePullRequest::new(
+                "PR_7".to_string(),
+                "abc".to_string(),
+                false,
+                MergeMethod::Squash
+            )
+            .document(),
+            r#"mergePullRequest(input: { pullRequestId: "PR_7", expectedHeadOid: "abc", mergeMethod: SQUASH }) { clientMutationId }"#
+        );
+        assert_eq!(
+            MergePullRequest::new("PR_7".to_string(), "abc".to_string(), true, MergeMethod::Squash)
+                .document(),
+            r#"enqueuePullRequest(input: { pullRequestId: "PR_7", expectedHeadOid: "abc" }) { clientMutationId }"#
+        );
+    }
+
+    #[test]
+    fn triage_documents_list_node_ids() {
+        assert_eq!(
+            RequestReviews::new("PR_1".to_string(), vec!["U_1".to_string()], Vec::new()).document(),
+            r#"requestReviews(input: { pullRequestId: "PR_1", userIds: ["U_1"], union: true }) { clientMutationId }"#
+        );
+        assert_eq!(
+            RequestReviews::new("PR_1".to_string(), Vec::new(), vec!["T_1".to_string()]).document(),
+            r#"requestReviews(input: { pullRequestId: "PR_1", teamIds: ["T_1"], union: true }) { clientMutationId }"#
+        );
+        assert_eq!(
+            AddLabels::new("PR_1".to_string(), vec!["L_1".to_string(), "L_2".to_string()])
+                .document(),
+            r#"addLabelsToLabelable(input: { labelableId: "PR_1", labelIds: ["L_1","L_2"] }) { clientMutationId }"#
+        );
+        assert_eq!(
+            AddAssignees::new("PR_1".to_string(), vec!["U_1".to_string()]).document(),
+            r#"addAssigneesToAssignable(input: { assignableId: "PR_1", assigneeIds: ["U_1"] }) { clientMutationId }"#
+        );
+    }
+
     #[test]
     fn batch_document_aliases_each_operation_exactly() {
         let operations = [
@@ -508,6 +1441,9 @@ mod tests {
                 base_branch: "main".to_string(),
                 head_branch: "G123".to_string(),
                 state: PullRequestState::Open,
+                is_draft: false,
+                review_decision: None,
+                is_in_merge_queue: false,
             })
         );
         assert_eq!(
@@ -516,6 +1452,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn looks_up_pull_requests_by_number() {
+        let query = PullRequestByNumber::new("o\"wner".to_string(), "repo".to_string(), 42);
+        assert_eq!(
+            query.document(),
+            r#"repository(owner: "o\"wner", name: "repo") { pullRequest(number: 42) { number, id, title, body, baseRefName, headRefName, state, isDraft, reviewDecision, isInMergeQueue, isCrossRepository } }"#
+        );
+
+        let mut node = pull_request_node(42, "MERGED", false);
+        node["headRefName"] = json!("G123");
+        node["isDraft"] = json!(true);
+        node["reviewDecision"] = json!("APPROVED");
+        node["isInMergeQueue"] = json!(true);
+        assert_eq!(
+            query.decode(json!({ "pullRequest": node })).unwrap(),
+            Some(PullRequest {
+                number: 42,
+                node_id: "PR_42".to_string(),
+                title: Some("Title".to_string()),
+                body: None,
+                base_branch: "main".to_string(),
+                head_branch: "G123".to_string(),
+                state: PullRequestState::Merged,
+                is_draft: true,
+                review_decision: Some(ReviewDecision::Approved),
+                is_in_merge_queue: true,
+            })
+        );
+        assert_eq!(query.decode(json!({ "pullRequest": null })).unwrap(), None);
+
+        let mut fork = pull_request_node(42, "OPEN", true);
+        fork["headRefName"] = json!("feature");
+        assert!(query.decode(json!({ "pullRequest": fork })).is_err());
+    }
+
     #[test]
     fn pull_request_lifecycle_decoding_is_exhaustive_and_fail_closed() {
         let query =
@@ -692,6 +1663,7 @@ mod tests {
                         "number": 42,
                         "id": "PR_42",
                         "state": "OPEN",
+                        "isDraft": false,
                         "isCrossRepository": false,
                     })],
                     false,
@@ -739,6 +1711,7 @@ mod tests {
             "G123".to_string(),
             "Title".to_string(),
             "Body".to_string(),
+            false,
         );
 
         assert_eq!(
@@ -0,0 +1,403 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Context as _, Result};
+use gix::ObjectId;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use super::{
+    Commit, RefLayout, SyncMode,
+    publication::{PushTarget, RefRestore, plan_restores, push_batches, remote_query_batches},
+    remote::observe_refs,
+};
+use crate::util;
+
+/// The refs of a publication that spans several push batches, recorded before
+/// each batch is pushed.
+///
+/// Each batch is an atomic push, but Git reads refspecs and leases only from
+/// its command line, so a stack larger than one batch cannot be published in
+/// a single ref transaction. If a batch fails, the journal names the refs that
+/// earlier batches published, so that they can be rolled back, if need be by a
+/// later sync.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct PublicationJournal {
+    /// The refs that each batch publishes, in push order.
+    batches: Vec<Vec<JournaledRef>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct JournaledRef {
+    /// The fully qualified name of the remote ref.
+    pub name: String,
+    /// The object ID that the publication pushes.
+    pub object_id: String,
+    /// The object ID before the publication, or `None` if it creates the ref.
+    pub previous: Option<String>,
+}
+
+impl PublicationJournal {
+    fn path(repo: &util::Repo) -> PathBuf {
+        repo.common_dir().join("gherrit").join("publication.json")
+    }
+
+    /// Reads the journal that an unfinished publication left, if any.
+    pub(super) fn load(repo: &util::Repo) -> Result<Option<Self>> {
+        read(&Self::path(repo))
+    }
+
+    pub(super) fn save(&self, repo: &util::Repo) -> Result<()> {
+        write(&Self::path(repo), self)
+    }
+
+    /// Deletes the journal of a finished or rolled back publication.
+    pub(super) fn remove(repo: &util::Repo) -> Result<()> {
+        remove(&Self::path(repo))
+    }
+
+    /// Records the refs that batch number `batch` pushes, replacing those of
+    /// an earlier attempt of it.
+    pub(super) fn record(&mut self, batch: usize, layout: RefLayout, targets: &[PushTarget<'_>]) {
+        self.batches.resize_with(self.batches.len().max(batch + 1), Vec::new);
+        self.batches[batch] = targets
+            .iter()
+            .flat_map(|target| {
+                let object_id = target.object_id.to_string();
+                let previous =
+                    Some(target.expected_remote_sha.to_string()).filter(|s| !s.is_empty());
+                [
+                    JournaledRef {
+                        name: format!("refs/heads/{}", target.branch),
+                        object_id: object_id.clone(),
+                        previous,
+                    },
+                    JournaledRef {
+                        name: layout.version_ref(target.gherrit_id, target.version),
+                        object_id,
+                        previous: None,
+                    },
+                ]
+            })
+            .collect();
+    }
+
+    /// Plans returning each journaled ref that still holds the published
+    /// object ID, according to `current`, to its value before the publication.
+    ///
+    /// Refs that a failed batch never updated, and refs that someone else has
+    /// updated since, are left alone.
+    fn plan_rollback(&self, current: &HashMap<String, String>) -> Vec<RefRestore> {
+        self.batches
+            .iter()
+            .flatten()
+            .filter(|journaled| current.get(&journaled.name) == Some(&journaled.object_id))
+            .map(|journaled| RefRestore {
+                name: journaled.name.clone(),
+                published: journaled.object_id.clone(),
+                previous: journaled.previous.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Returns the remote refs that `journal` records to their values before the
+/// publication, and returns how many refs were restored.
+pub(super) async fn roll_back(repo: &util::Repo, journal: &PublicationJournal) -> Result<usize> {
+    let names: Vec<String> =
+        journal.batches.iter().flatten().map(|journaled| journaled.name.clone()).collect();
+    let current = observe_refs(repo, &names).await?;
+    let restores = journal.plan_rollback(&current);
+    let remote = repo.default_remote_name();
+
+    // Restoring a branch pushes its previous commit, which a fresh clone may
+    // not have.
+    let missing: Vec<&str> = restores
+        .iter()
+        .filter_map(|restore| restore.previous.as_deref())
+        .filter(|previous| {
+            ObjectId::from_hex(previous.as_bytes())
+                .is_ok_and(|object_id| !repo.has_object(object_id))
+        })
+        .collect();
+    for chunk in remote_query_batches(&missing) {
+        let arguments = ["fetch", "--quiet", "--no-tags", "--no-write-fetch-head", remote.as_str()]
+            .into_iter()
+            .chain(chunk.iter().copied());
+        util::spawn_checked(&mut util::async_cmd("git", arguments))?
+            .await
+            .wrap_err("Failed to fetch the commits that the rollback restores")?;
+    }
+
+    for chunk in push_batches(&restores) {
+        util::spawn_checked(&mut util::async_cmd("git", plan_restores(&remote, chunk)))?
+            .await
+            .wrap_err("Failed to roll back the published refs")?;
+    }
+    Ok(restores.len())
+}
+
+/// Finishes rolling back a publication that an earlier sync left unfinished.
+///
+/// In a dry run, the unfinished publication is only reported.
+pub(super) async fn recover(repo: &util::Repo, mode: SyncMode) -> Result<()> {
+    let Some(journal) = PublicationJournal::load(repo)? else {
+        return Ok(());
+    };
+    if mode == SyncMode::DryRun {
+        log::warn!("An earlier sync left a partial publication, which the next sync rolls back.");
+        return Ok(());
+    }
+
+    log::warn!("Rolling back the partial publication of an earlier sync...");
+    let restored = roll_back(repo, &journal)
+        .await
+        .wrap_err("Failed to roll back the partial publication of an earlier sync")?;
+    PublicationJournal::remove(repo)?;
+    log::info!("Rolled back {restored} refs.");
+    Ok(())
+}
+
+/// The progress of a sync whose push succeeded, recorded so that a sync that
+/// fails during its PR operations, for example on a rate limit or a network
+/// error, can be finished without pushing again.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct SyncJournal {
+    /// The object ID that each managed branch of the stack was pushed at.
+    pushed: BTreeMap<String, String>,
+    /// The latest version of each commit, by gherrit-pr-id.
+    versions: BTreeMap<String, usize>,
+    /// The PRs that the sync created, by head branch.
+    created: BTreeMap<String, CreatedPr>,
+    /// The gherrit-pr-ids of the commits whose diff the push changed.
+    #[serde(default)]
+    revised: BTreeSet<String>,
+}
+
+/// A PR that a journaled sync created.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct CreatedPr {
+    pub number: u64,
+    pub node_id: String,
+    pub base_branch: String,
+}
+
+impl SyncJournal {
+    /// Starts the journal of a sync that pushed `commits` as `versions`,
+    /// changing the diffs of the `revised` ones.
+    pub(super) fn new(
+        commits: &[Commit],
+        versions: &HashMap<String, usize>,
+        revised: &HashSet<String>,
+    ) -> Self {
+        Self {
+            pushed: commits.iter().map(|c| (c.branch.clone(), c.id.to_string())).collect(),
+            versions: versions.iter().map(|(id, version)| (id.clone(), *version)).collect(),
+            revised: revised.iter().cloned().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// The journal of each managed branch is named after it, which is a valid
+    /// path because ref names are.
+    fn path(repo: &util::Repo, branch_name: &str) -> PathBuf {
+        repo.common_dir().join("gherrit").join("sync").join(format!("{branch_name}.json"))
+    }
+
+    /// Reads the journal of an interrupted sync of `branch_name`, if any.
+    pub(super) fn load(repo: &util::Repo, branch_name: &str) -> Result<Option<Self>> {
+        read(&Self::path(repo, branch_name))
+    }
+
+    pub(super) fn save(&self, repo: &util::Repo, branch_name: &str) -> Result<()> {
+        write(&Self::path(repo, branch_name), self)
+    }
+
+    /// Deletes the journal of a finished sync of `branch_name`.
+    pub(super) fn remove(repo: &util::Repo, branch_name: &str) -> Result<()> {
+        remove(&Self::path(repo, branch_name))
+    }
+
+    /// Whether `commits` are exactly the stack that the journaled sync pushed,
+    /// and the remote's managed `branches` still point to them.
+    pub(super) fn matches(&self, commits: &[Commit], branches: &HashMap<String, String>) -> bool {
+        commits.len() == self.pushed.len()
+            && commits.iter().all(|c| {
+                let pushed = self.pushed.get(&c.branch);
+                pushed == Some(&c.id.to_string()) && pushed == branches.get(&c.branch)
+            })
+    }
+
+    /// The latest version of each commit, as published by the journaled sync.
+    pub(super) fn versions(&self) -> HashMap<String, usize> {
+        self.versions.iter().map(|(id, version)| (id.clone(), *version)).collect()
+    }
+
+    /// The gherrit-pr-ids of the commits whose diff the journaled sync's push
+    /// changed.
+    pub(super) fn revised(&self) -> HashSet<String> {
+        self.revised.iter().cloned().collect()
+    }
+
+    /// The PR that the journaled sync created for `head_branch`, if any.
+    pub(super) fn created(&self, head_branch: &str) -> Option<&CreatedPr> {
+        self.created.get(head_branch)
+    }
+
+    pub(super) fn created_count(&self) -> usize {
+        self.created.len()
+    }
+
+    pub(super) fn record_created(&mut self, head_branch: String, created: CreatedPr) {
+        self.created.insert(head_branch, created);
+    }
+}
+
+/// Reads the journal at `path`, if it exists.
+fn read<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(error).wrap_err_with(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    serde_json::from_slice(&contents)
+        .map(Some)
+        .wrap_err_with(|| format!("Malformed journal {}", path.display()))
+}
+
+/// Writes the journal at `path`, replacing any earlier one in a single rename
+/// so that an interruption never leaves it half-written.
+fn write<T: Serialize>(path: &Path, journal: &T) -> Result<()> {
+    let partial = path.with_extension("json.partial");
+    let contents = serde_json::to_vec_pretty(journal).expect("serializing a journal cannot fail");
+    fs::create_dir_all(path.parent().unwrap())
+        .and_then(|()| fs::write(&partial, contents))
+        .and_then(|()| fs::rename(&partial, path))
+        .wrap_err_with(|| format!("Failed to write {}", path.display()))
+}
+
+fn remove(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => {
+            Err(error).wrap_err_with(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pre_push::triage::Triage;
+
+    const OBJECT_A: &str = "1111111111111111111111111111111111111111";
+    const OBJECT_B: &str = "2222222222222222222222222222222222222222";
+    const OBJECT_C: &str = "3333333333333333333333333333333333333333";
+
+    fn target<'a>(gherrit_id: &'a str, object_id: &str, expected: &'a str) -> PushTarget<'a> {
+        PushTarget {
+            object_id: ObjectId::from_hex(object_id.as_bytes()).unwrap(),
+            gherrit_id,
+            branch: gherrit_id,
+            version: 2,
+            expected_remote_sha: expected,
+        }
+    }
+
+    #[test]
+    fn records_each_batch_and_replaces_retried_ones() {
+        let mut journal = PublicationJournal::default();
+        journal.record(0, RefLayout::Tags, &[target("Gone", OBJECT_A, OBJECT_C)]);
+        journal.record(1, RefLayout::Tags, &[target("Gtwo", OBJECT_A, "")]);
+        journal.record(1, RefLayout::Refs, &[target("Gtwo", OBJECT_B, "")]);
+
+        let names: Vec<_> = journal.batches.iter().flatten().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "refs/heads/Gone",
+                "refs/tags/gherrit/Gone/v2",
+                "refs/heads/Gtwo",
+                "refs/gherrit/Gtwo/v2"
+            ]
+        );
+        assert_eq!(journal.batches[0][0].previous.as_deref(), Some(OBJECT_C));
+        assert_eq!(journal.batches[1][0].previous, None);
+        assert_eq!(journal.batches[1][0].object_id, OBJECT_B);
+
+        let json = serde_json::to_vec(&journal).unwrap();
+        assert_eq!(serde_json::from_slice::<PublicationJournal>(&json).unwrap(), journal);
+    }
+
+    #[test]
+    fn rolls_back_only_the_refs_that_still_hold_the_publication() {
+        let mut journal = PublicationJournal::default();
+        journal.record(0, RefLayout::Tags, &[target("Gone", OBJECT_A, OBJECT_C)]);
+        journal.record(1, RefLayout::Tags, &[target("Gtwo", OBJECT_A, "")]);
+        let current = HashMap::from([
+            ("refs/heads/Gone".to_string(), OBJECT_A.to_string()),
+            ("refs/tags/gherrit/Gone/v2".to_string(), OBJECT_A.to_string()),
+            // Someone else has since updated the branch.
+            ("refs/heads/Gtwo".to_string(), OBJECT_B.to_string()),
+        ]);
+
+        assert_eq!(
+            journal.plan_rollback(&current),
+            [
+                RefRestore {
+                    name: "refs/heads/Gone".to_string(),
+                    published: OBJECT_A.to_string(),
+                    previous: Some(OBJECT_C.to_string()),
+                },
+                RefRestore {
+                    name: "refs/tags/gherrit/Gone/v2".to_string(),
+                    published: OBJECT_A.to_string(),
+                    previous: None,
+                },
+            ]
+        );
+        assert!(journal.plan_rollback(&HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn resumes_only_an_unchanged_stack_and_tracks_created_prs() {
+        let commit = |gherrit_id: &str, object_id: &str| Commit {
+            id: ObjectId::from_hex(object_id.as_bytes()).unwrap(),
+            gherrit_id: gherrit_id.to_string(),
+            branch: gherrit_id.to_string(),
+            message_title: String::new(),
+            message_body: String::new(),
+            draft: false,
+            triage: Triage::default(),
+        };
+        let commits = [commit("Gone", OBJECT_A), commit("Gtwo", OBJECT_B)];
+        let versions = HashMap::from([("Gone".to_string(), 1), ("Gtwo".to_string(), 3)]);
+        let revised = HashSet::from(["Gtwo".to_string()]);
+        let mut journal = SyncJournal::new(&commits, &versions, &revised);
+        assert_eq!(journal.versions(), versions);
+        assert_eq!(journal.revised(), revised);
+
+        let branches = HashMap::from([
+            ("Gone".to_string(), OBJECT_A.to_string()),
+            ("Gtwo".to_string(), OBJECT_B.to_string()),
+        ]);
+        assert!(journal.matches(&commits, &branches));
+        assert!(!journal.matches(&commits[..1], &branches), "A commit left the stack");
+        assert!(!journal.matches(&[commit("Gone", OBJECT_A), commit("Gtwo", OBJECT_C)], &branches));
+        let mut moved = branches.clone();
+        moved.insert("Gtwo".to_string(), OBJECT_C.to_string());
+        assert!(!journal.matches(&commits, &moved), "Another push moved a branch");
+
+        let created =
+            CreatedPr { number: 7, node_id: "PR_7".to_string(), base_branch: "main".into() };
+        journal.record_created("Gone".to_string(), created.clone());
+        assert_eq!(journal.created("Gone"), Some(&created));
+        assert_eq!(journal.created("Gtwo"), None);
+        let json = serde_json::to_vec(&journal).unwrap();
+        assert_eq!(serde_json::from_slice::<SyncJournal>(&json).unwrap(), journal);
+    }
+}
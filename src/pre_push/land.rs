@@ -0,0 +1,278 @@
+use color_eyre::eyre::{Context as _, Result, bail};
+use owo_colors::OwoColorize as _;
+
+use super::{
+    Commit, GithubEndpoint, PrState, collect_commits, fetch_stack_prs,
+    github::{
+        CheckState, Landability, MergePullRequest, PullRequestLandability, UpdatePullRequest,
+    },
+    github_client,
+    lock::SyncLock,
+    reconcile::{PullRequestState, ReviewDecision},
+    run_batched_graphql,
+};
+use crate::util::{self, HeadState};
+
+/// Which commits at the bottom of the stack to land.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LandLimit {
+    /// The bottom `n` commits.
+    Count(usize),
+    /// The commits up to and including the one with this gherrit-pr-id.
+    UpTo(String),
+}
+
+/// How `gherrit land` merges a PR outside of a merge queue, which decides for
+/// itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub(crate) enum MergeMethod {
+    /// Add a merge commit.
+    #[default]
+    Merge,
+    /// Squash the PR's commit into a new commit on the base branch.
+    Squash,
+    /// Rebase the PR's commit onto the base branch.
+    Rebase,
+}
+
+impl MergeMethod {
+    const CONFIG_KEY: &str = "gherrit.mergeMethod";
+
+    /// Reads the repository's method, defaulting to `Merge`.
+    pub(crate) fn from_config(repo: &util::Repo) -> Result<Self> {
+        repo.config_choice(Self::CONFIG_KEY)
+    }
+}
+
+/// Merges the PRs at the bottom of the current stack, in order, or adds them
+/// to the merge queue of a repository that has one.
+///
+/// Every PR to land must be approved, pass its checks, and match its local
+/// commit before any of them is merged. Before each merge, the next PR in the
+/// stack is retargeted to the default branch, since GitHub closes PRs whose
+/// base branch is deleted unless it deleted the branch itself.
+pub async fn run(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    limit: LandLimit,
+    method: MergeMethod,
+) -> Result<()> {
+    let branch_name = match repo.current_branch() {
+        HeadState::Attached(branch_name) => branch_name,
+        HeadState::Pending(_) => {
+            bail!("A rebase is in progress. Finish it with 'git rebase --continue' first.")
+        }
+        HeadState::Detached => bail!("Cannot land from detached HEAD"),
+    };
+    if !repo.is_managed(branch_name)? {
+        bail!("Branch '{branch_name}' is UNMANAGED, so it has no GHerrit stack to land.");
+    }
+    let _lock = SyncLock::acquire(repo)?;
+
+    let tip = repo.rev_parse_single("HEAD")?.detach();
+    let mut commits =
+        collect_commits(repo, branch_name, tip).wrap_err("Failed to collect commits")?;
+    if commits.is_empty() {
+        bail!("The stack has no commits to land.");
+    }
+    let count = landing_count(&commits, &limit)?;
+
+    let octocrab = github_client(github_endpoint)?;
+    let (prs, remote_branches) = fetch_stack_prs(repo, &octocrab, &mut commits).await?;
+    let pr_of = |c: &Commit| prs.iter().find(|pr| pr.head_branch == c.branch);
+
+    let mut landing = Vec::with_capacity(count);
+    for c in &commits[..count] {
+        let Some(pr) = pr_of(c) else {
+            bail!("Commit {} has no PR. Push the stack first.", c.gherrit_id);
+        };
+        if remote_branches.get(&c.branch) != Some(&c.id.to_string()) {
+            bail!(
+                "PR #{} does not match its local commit {}. Push the stack first.",
+                pr.number,
+                c.id
+            );
+        }
+        landing.push((c, pr));
+    }
+
+    let default_branch = repo.find_default_branch_on_default_remote();
+    let (_, bottom) = landing[0];
+    if bottom.base_branch != default_branch {
+        bail!(
+            "PR #{} is based on {} rather than {default_branch}, so it cannot land first.",
+            bottom.number,
+            bottom.base_branch
+        );
+    }
+
+    let remote = repo.default_remote()?;
+    let lookups = landing.iter().map(|(_, pr)| {
+        PullRequestLandability::new(remote.owner.clone(), remote.repo_name.clone(), pr.number)
+    });
+    let landabilities = run_batched_graphql(&octocrab, lookups.collect::<Vec<_>>())
+        .await
+        .wrap_err("Failed to check whether the PRs can land")?;
+    let problems: Vec<String> = landing
+        .iter()
+        .zip(&landabilities)
+        .filter_map(|((_, pr), landability)| landing_problem(pr, landability, &default_branch))
+        .collect();
+    if !problems.is_empty() {
+        bail!("Cannot land the stack:\n{}", problems.join("\n"));
+    }
+
+    for (index, ((c, pr), landability)) in landing.iter().zip(&landabilities).enumerate() {
+        if let Some(next) = commits.get(index + 1).and_then(pr_of)
+            && next.base_branch != default_branch
+        {
+            let retarget = UpdatePullRequest::new(
+                next.node_id.clone(),
+                None,
+                None,
+                Some(default_branch.clone()),
+            );
+            run_batched_graphql(&octocrab, [retarget]).await.wrap_err_with(|| {
+                format!("Failed to retarget PR #{} to {default_branch}", next.number)
+            })?;
+            log::info!(
+                "Retargeted PR #{} to {}.",
+                next.number.green().bold(),
+                default_branch.yellow()
+            );
+        }
+
+        let enqueue = landability.is_merge_queue_enabled;
+        let merge = MergePullRequest::new(pr.node_id.clone(), c.id.to_string(), enqueue, method);
+        run_batched_graphql(&octocrab, [merge])
+            .await
+            .wrap_err_with(|| format!("Failed to land PR #{}", pr.number))?;
+        let url = remote.pr_url(pr.number).blue().underline().to_string();
+        if enqueue {
+            log::info!("Added PR #{} to the merge queue: {url}", pr.number.green().bold());
+        } else {
+            log::info!("Merged PR #{}: {url}", pr.number.green().bold());
+        }
+    }
+
+    log::info!("Once the PRs have merged, run `gherrit restack` to drop their commits.");
+    Ok(())
+}
+
+/// The number of commits at the bottom of the stack that `limit` selects.
+fn landing_count(commits: &[Commit], limit: &LandLimit) -> Result<usize> {
+    match limit {
+        LandLimit::Count(0) => bail!("--count must be at least 1"),
+        LandLimit::Count(count) if *count > commits.len() => {
+            bail!("--count is {count}, but the stack has only {} commits", commits.len())
+        }
+        LandLimit::Count(count) => Ok(*count),
+        LandLimit::UpTo(gherrit_id) => {
+            match commits.iter().position(|c| &c.gherrit_id == gherrit_id) {
+                Some(position) => Ok(position + 1),
+                None => bail!("The stack has no commit with gherrit-pr-id '{gherrit_id}'"),
+            }
+        }
+    }
+}
+
+/// Why `pr` cannot land, if it cannot.
+///
+/// A PR based on `default_branch` without a review decision needs no reviews,
+/// and a commit without checks has nothing to wait for. GitHub never decides
+/// on the reviews of a PR based on another PR's branch, though, so such a PR
+/// must be approved by its latest reviews.
+fn landing_problem(
+    pr: &PrState,
+    landability: &Landability,
+    default_branch: &str,
+) -> Option<String> {
+    let review_decision = match landability.review_decision {
+        None if pr.base_branch != default_branch => Some(landability.latest_reviews),
+        review_decision => review_decision,
+    };
+    let problem = if pr.state != PullRequestState::Open {
+        "is not open"
+    } else if pr.is_draft {
+        "is a draft"
+    } else if review_decision == Some(ReviewDecision::ChangesRequested) {
+        "has changes requested"
+    } else if review_decision == Some(ReviewDecision::ReviewRequired) {
+        "is not approved"
+    } else {
+        match landability.checks {
+            None | Some(CheckState::Success) => return None,
+            Some(CheckState::Pending | CheckState::Expected) => "is still running its checks",
+            Some(CheckState::Failure | CheckState::Error) => "failed its checks",
+        }
+    };
+    Some(format!("  - PR #{} {problem}", pr.number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pr(is_draft: bool) -> PrState {
+        PrState {
+            number: 7,
+            node_id: "PR_7".to_string(),
+            title: None,
+            body: None,
+            base_branch: "main".to_string(),
+            head_branch: "G7".to_string(),
+            state: PullRequestState::Open,
+            is_draft,
+            review_decision: None,
+            is_in_merge_queue: false,
+        }
+    }
+
+    #[test]
+    fn lands_only_approved_prs_with_passing_checks() {
+        let landability = |review_decision, checks| Landability {
+            review_decision,
+            latest_reviews: ReviewDecision::ReviewRequired,
+            checks,
+            is_merge_queue_enabled: false,
+        };
+        let problem = |pr: &PrState, review_decision, checks| {
+            landing_problem(pr, &landability(review_decision, checks), "main")
+        };
+        let open = pr(false);
+
+        assert_eq!(problem(&open, Some(ReviewDecision::Approved), Some(CheckState::Success)), None);
+        assert_eq!(problem(&open, None, None), None, "Nothing is required");
+        assert_eq!(
+            problem(&pr(true), Some(ReviewDecision::Approved), None).as_deref(),
+            Some("  - PR #7 is a draft")
+        );
+        assert_eq!(
+            problem(&open, Some(ReviewDecision::ReviewRequired), None).as_deref(),
+            Some("  - PR #7 is not approved")
+        );
+        assert_eq!(
+            problem(&open, Some(ReviewDecision::ChangesRequested), None).as_deref(),
+            Some("  - PR #7 has changes requested")
+        );
+        assert_eq!(
+            problem(&open, None, Some(CheckState::Pending)).as_deref(),
+            Some("  - PR #7 is still running its checks")
+        );
+        assert_eq!(
+            problem(&open, None, Some(CheckState::Failure)).as_deref(),
+            Some("  - PR #7 failed its checks")
+        );
+
+        // A PR based on another PR's branch has no review decision.
+        let upper = PrState { base_branch: "G6".to_string(), ..pr(false) };
+        assert_eq!(
+            problem(&upper, None, None).as_deref(),
+            Some("  - PR #7 is not approved"),
+            "The latest reviews decide"
+        );
+        let approved =
+            Landability { latest_reviews: ReviewDecision::Approved, ..landability(None, None) };
+        assert_eq!(landing_problem(&upper, &approved, "main"), None);
+    }
+}
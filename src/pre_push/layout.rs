@@ -0,0 +1,83 @@
+use color_eyre::eyre::Result;
+
+use crate::util;
+
+/// Where the version history of each commit is stored, both locally and on
+/// the remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub(crate) enum RefLayout {
+    /// Lightweight tags named `refs/tags/gherrit/<id>/v<n>`.
+    #[default]
+    Tags,
+    /// Refs named `refs/gherrit/<id>/v<n>`, which neither `git fetch --tags`
+    /// nor `git tag` sees.
+    Refs,
+}
+
+impl RefLayout {
+    pub(crate) const CONFIG_KEY: &str = "gherrit.versionRefLayout";
+
+    /// Reads the repository's layout, defaulting to `Tags`.
+    pub(crate) fn from_config(repo: &util::Repo) -> Result<Self> {
+        repo.config_choice(Self::CONFIG_KEY)
+    }
+
+    /// The prefix shared by every version ref.
+    pub(super) fn root(self) -> &'static str {
+        match self {
+            Self::Tags => "refs/tags/gherrit/",
+            Self::Refs => "refs/gherrit/",
+        }
+    }
+
+    /// The fully qualified name of a version ref.
+    pub(super) fn version_ref(self, gherrit_id: &str, version: usize) -> String {
+        format!("{}{gherrit_id}/v{version}", self.root())
+    }
+
+    /// A `git ls-remote` pattern that matches every version ref.
+    pub(super) fn pattern(self) -> String {
+        format!("{}*", self.root())
+    }
+
+    /// A `git ls-remote` pattern that matches every version ref of one
+    /// gherrit-pr-id.
+    pub(super) fn versions_pattern(self, gherrit_id: &str) -> String {
+        format!("{}{gherrit_id}/v*", self.root())
+    }
+
+    /// Splits a version ref into its gherrit-pr-id and version.
+    pub(super) fn parse_version_ref(self, name: &str) -> Option<(&str, usize)> {
+        let (id, version) = name.strip_prefix(self.root())?.split_once("/v")?;
+        let is_gherrit_id = !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_alphanumeric());
+        Some((id, version.parse().ok()?)).filter(|_| is_gherrit_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum as _;
+
+    use super::*;
+
+    #[test]
+    fn names_and_parses_version_refs() {
+        assert_eq!(RefLayout::Tags.version_ref("Gone", 2), "refs/tags/gherrit/Gone/v2");
+        assert_eq!(RefLayout::Refs.version_ref("Gone", 2), "refs/gherrit/Gone/v2");
+        assert_eq!(RefLayout::Tags.versions_pattern("Gone"), "refs/tags/gherrit/Gone/v*");
+        for layout in [RefLayout::Tags, RefLayout::Refs] {
+            assert_eq!(
+                layout.parse_version_ref(&layout.version_ref("Gone", 12)),
+                Some(("Gone", 12))
+            );
+            assert_eq!(RefLayout::from_str(&util::choice_name(&layout), false), Ok(layout));
+        }
+
+        assert_eq!(RefLayout::Refs.parse_version_ref("refs/tags/gherrit/Gone/v2"), None);
+        for invalid in ["refs/gherrit/Gone/latest", "refs/gherrit/Gone/v2^{}", "refs/gherrit//v2"] {
+            assert_eq!(RefLayout::Refs.parse_version_ref(invalid), None, "{invalid}");
+        }
+        assert_eq!(util::choice_name(&RefLayout::Refs), "refs");
+        assert!(RefLayout::from_str("heads", false).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fs::{self, File, TryLockError},
+    io::{Read as _, Write as _},
+    path::Path,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre::{Context as _, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::util;
+
+/// An exclusive lock on syncing a repository, released when dropped.
+///
+/// Concurrent syncs race on the remote state that they observe and on the
+/// local version refs that they write, so the lock lives in the common Git
+/// directory, which every worktree of the repository shares. A sync that finds
+/// the lock held fails at once, naming the holder, rather than waiting.
+///
+/// The lock file stays on disk; the lock itself is an OS file lock on it,
+/// which the OS releases when its holder exits, even by crashing. The holder
+/// only records itself in the file for the error message of another sync.
+pub(super) struct SyncLock {
+    file: File,
+}
+
+/// The process holding a [`SyncLock`], as recorded in the lock file.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct LockHolder {
+    pid: u32,
+    /// When the process acquired the lock, in seconds since the Unix epoch.
+    acquired_at: u64,
+    /// The working tree in which the process runs.
+    worktree: String,
+    command: String,
+}
+
+impl SyncLock {
+    /// Acquires the lock of `repo`.
+    pub(super) fn acquire(repo: &util::Repo) -> Result<Self> {
+        let path = repo.common_dir().join("gherrit").join("sync.lock");
+        let worktree = repo.workdir().unwrap_or(repo.path());
+        Self::acquire_at(&path, &worktree.display().to_string())
+    }
+
+    fn acquire_at(path: &Path, worktree: &str) -> Result<Self> {
+        fs::create_dir_all(path.parent().unwrap())
+            .wrap_err_with(|| format!("Failed to create {}", path.parent().unwrap().display()))?;
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                // The holder may not have recorded itself yet, and some
+                // platforms do not let other processes read a locked file.
+                let mut existing = Vec::new();
+                let existing = file
+                    .read_to_end(&mut existing)
+                    .ok()
+                    .and_then(|_| serde_json::from_slice::<LockHolder>(&existing).ok());
+                let holder = existing.map_or_else(
+                    || "an unidentified process".to_string(),
+                    |existing| existing.describe(now()),
+                );
+                bail!(
+                    "Another GHerrit sync holds {}: {holder}.\n\
+                     Wait for it to finish, then try again.",
+                    path.display()
+                );
+            }
+            Err(TryLockError::Error(error)) => {
+                return Err(error).wrap_err_with(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+
+        let holder = LockHolder {
+            pid: process::id(),
+            acquired_at: now(),
+            worktree: worktree.to_string(),
+            command: std::env::args().collect::<Vec<_>>().join(" "),
+        };
+        let contents = serde_json::to_vec(&holder).expect("serializing a lock holder cannot fail");
+        file.set_len(0)
+            .and_then(|()| (&file).write_all(&contents))
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for SyncLock {
+    fn drop(&mut self) {
+        if let Err(error) = self.file.unlock() {
+            log::warn!("Failed to release the sync lock: {error}");
+        }
+    }
+}
+
+impl LockHolder {
+    fn describe(&self, now: u64) -> String {
+        format!(
+            "process {}, running `{}` in {} for {}s",
+            self.pid,
+            self.command,
+            self.worktree,
+            now.saturating_sub(self.acquired_at)
+        )
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_the_holder() {
+        let holder = LockHolder {
+            pid: 4242,
+            acquired_at: 1_000,
+            worktree: "/src/project".to_string(),
+            command: "gherrit hook pre-push".to_string(),
+        };
+        assert_eq!(
+            holder.describe(1_042),
+            "process 4242, running `gherrit hook pre-push` in /src/project for 42s"
+        );
+        assert_eq!(holder.describe(0), holder.describe(1_000), "Clocks may disagree");
+    }
+
+    #[test]
+    fn acquires_the_lock_once_at_a_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gherrit").join("sync.lock");
+
+        let held = SyncLock::acquire_at(&path, "/src/project").unwrap();
+        let error = SyncLock::acquire_at(&path, "/src/elsewhere").err().unwrap().to_string();
+        assert!(error.starts_with("Another GHerrit sync holds"), "{error}");
+        // Windows does not let other handles read a locked file.
+        if cfg!(unix) {
+            assert!(error.contains("in /src/project for"), "{error}");
+        }
+        drop(held);
+
+        // A lock file left behind, even a half-written one, is not held.
+        let reacquired = SyncLock::acquire_at(&path, "/src/elsewhere").unwrap();
+        drop(reacquired);
+        fs::write(&path, b"").unwrap();
+        let reclaimed = SyncLock::acquire_at(&path, "/src/elsewhere").unwrap();
+        let holder: LockHolder = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(holder.pid, process::id());
+        assert_eq!(holder.worktree, "/src/elsewhere");
+        drop(reclaimed);
+    }
+}
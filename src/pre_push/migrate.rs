@@ -0,0 +1,99 @@
+use color_eyre::eyre::{Context as _, Result, bail, eyre};
+use gix::{ObjectId, refs::transaction::PreviousValue};
+use owo_colors::OwoColorize as _;
+
+use super::{
+    BranchNaming, RefLayout,
+    lock::SyncLock,
+    publication::{RefMove, plan_moves, push_batches},
+    remote::list_gherrit_refs,
+};
+use crate::{
+    cmd,
+    util::{self, CommandExt as _},
+};
+
+/// Moves every version ref to `target`, on the remote and then locally, and
+/// configures the repository to use `target` from now on.
+///
+/// Running the migration again after an interruption finishes it. The sync
+/// lock is held throughout, since a sync would write refs in the old layout.
+pub fn run(repo: &util::Repo, target: RefLayout) -> Result<()> {
+    let _lock = SyncLock::acquire(repo)?;
+    let source = match target {
+        RefLayout::Tags => RefLayout::Refs,
+        RefLayout::Refs => RefLayout::Tags,
+    };
+
+    let num_remote = migrate_remote(repo, source, target)?;
+    let num_local = migrate_local(repo, source, target)?;
+    cmd!("git config", RefLayout::CONFIG_KEY, util::choice_name(&target))
+        .success()
+        .wrap_err("Failed to configure the new layout")?;
+
+    log::info!(
+        "Moved {num_remote} version refs on the remote and {num_local} locally to the {} layout.",
+        util::choice_name(&target).yellow()
+    );
+    Ok(())
+}
+
+fn migrate_remote(repo: &util::Repo, source: RefLayout, target: RefLayout) -> Result<usize> {
+    let naming = BranchNaming::from_config(repo)?;
+    let existing = list_gherrit_refs(repo, target, &naming)?.tags;
+    let mut moves = Vec::new();
+    for (id, versions) in list_gherrit_refs(repo, source, &naming)?.tags {
+        for (version, from) in versions {
+            let to = target.version_ref(&id, version);
+            let current = existing.get(&id).and_then(|existing| {
+                existing.iter().find(|(existing, _)| *existing == version).map(|(_, to)| to)
+            });
+            let done = match current {
+                None => false,
+                Some(current) if current.object_id == from.object_id => true,
+                Some(_) => bail!(
+                    "The remote has both {} and {to}, and they differ. Delete one of them first.",
+                    from.name
+                ),
+            };
+            moves.push(RefMove { from, to, done });
+        }
+    }
+
+    let remote = repo.default_remote_name();
+    for chunk in push_batches(&moves) {
+        // Capture the output, which holds only GitHub's "Create a pull
+        // request" hints unless the push fails.
+        util::cmd("git", plan_moves(&remote, chunk)).checked_output().wrap_err(
+            "Failed to move the remote's version refs. Run the migration again to finish it.",
+        )?;
+    }
+    Ok(moves.len())
+}
+
+fn migrate_local(repo: &util::Repo, source: RefLayout, target: RefLayout) -> Result<usize> {
+    let mut moves: Vec<(String, String, ObjectId)> = Vec::new();
+    let references = repo.references().map_err(|e| eyre!(e))?;
+    for reference in references.all().map_err(|e| eyre!(e))? {
+        let mut reference = reference.map_err(|e| eyre!(e))?;
+        let name = reference.name().as_bstr().to_string();
+        let Some((id, version)) = source.parse_version_ref(&name) else {
+            continue;
+        };
+        let to = target.version_ref(id, version);
+        let object_id = reference.peel_to_id().map_err(|e| eyre!(e))?.detach();
+        moves.push((name, to, object_id));
+    }
+
+    for (from, to, object_id) in &moves {
+        repo.reference(
+            to.as_str(),
+            *object_id,
+            PreviousValue::ExistingMustMatch((*object_id).into()),
+            "gherrit: migrate version ref layout",
+        )
+        .wrap_err_with(|| format!("Failed to move {from} to {to}"))?;
+        repo.find_reference(from.as_str())?.delete()?;
+    }
+    Ok(moves.len())
+}
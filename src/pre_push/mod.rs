@@ -1,43 +1,77 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     process::Stdio,
     str,
 };
 
 use color_eyre::eyre::{Context, Result, bail, eyre};
-use gix::{ObjectId, reference::Category, refs::transaction::PreviousValue};
+use gix::{ObjectId, prelude::ObjectIdExt as _, refs::transaction::PreviousValue};
 use octocrab::Octocrab;
 use owo_colors::OwoColorize;
 
 use crate::{
+    commit_msg::IdEntropy,
     re,
     util::{self, CommandExt as _, HeadState},
 };
 
+pub(crate) mod abandon;
 mod autosquash;
 mod batching;
 mod body;
+pub(crate) mod checkout;
+mod closed;
+pub(crate) mod gc;
 mod github;
+mod journal;
+pub(crate) mod land;
+mod layout;
+mod lock;
+pub(crate) mod migrate;
+mod naming;
+mod orphaned;
+mod preview;
 mod publication;
+mod push_report;
+mod pushed_refs;
 mod reconcile;
 mod remote;
+mod rereview;
+pub(crate) mod restack;
+pub(crate) mod status;
+pub(crate) mod sync;
+mod triage;
 
 use batching::{
     BatchPlan, INITIAL_GRAPHQL_BATCH_LEN, MAX_GRAPHQL_QUERY_BYTES, ResponseDisposition,
     classify_response, query_exceeds_limit,
 };
-use body::{PrBody, gherrit_pr_id_re};
+use body::{Link, PrBody, VersionRevs, parse_metadata};
+pub(crate) use closed::ClosedPrPolicy;
 use github::{
-    BatchedOperation, CreatePullRequest, CreatedPullRequest, FindPullRequest,
-    PullRequest as PrState, RepositoryIdQuery, UpdatePullRequest, batch_document,
+    AddAssignees, AddLabels, BatchedOperation, CreatePullRequest, CreatedPullRequest,
+    FindPullRequest, NodeLookup, PullRequest as PrState, PullRequestChange, PullRequestReviewers,
+    RepositoryIdQuery, RequestReviews, SetPullRequestDraft, UpdatePullRequest, batch_document,
     decode_batch_response,
 };
-use publication::{PushTarget, plan_push, push_batches};
+use journal::{CreatedPr, PublicationJournal, SyncJournal};
+pub(crate) use layout::RefLayout;
+use lock::SyncLock;
+use naming::BranchNaming;
+pub(crate) use orphaned::OrphanedPrPolicy;
+use publication::{
+    LocalVersion, LocalVersions, PersistedTag, PushTarget, RemoteRef, VersionDecision,
+    decide_version, latest_version, plan_push, push_batches, remote_query_batches,
+};
+use push_report::{PushFailure, RefRejection, diagnose, parse_rejections};
+use pushed_refs::{parse_pushed_refs, stacks_to_sync};
 use reconcile::{
-    CurrentPr, DesiredPr, PrUpdate, PullRequestState, ensure_pull_requests_open, link_stack,
-    plan_update,
+    CurrentPr, DesiredPr, PrUpdate, PullRequestState, ReviewDecision, ensure_pull_requests_open,
+    link_stack, plan_update,
 };
-use remote::observe_managed_branches;
+use remote::{RemoteState, observe_managed_branches, observe_published_versions};
+use rereview::RereviewPolicy;
+use triage::Triage;
 
 #[derive(Eq, PartialEq)]
 pub(crate) enum GithubEndpoint {
@@ -69,7 +103,96 @@ impl GithubEndpoint {
     }
 }
 
-pub async fn run(repo: &util::Repo, github_endpoint: &GithubEndpoint) -> Result<()> {
+/// Whether a sync publishes its plan or only prints it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SyncMode {
+    Apply,
+    /// Prints the pushes, PR creations, and PR updates that `Apply` would
+    /// perform without changing any local, remote, or GitHub state.
+    DryRun,
+}
+
+/// How to sync each stack in one invocation.
+#[derive(Clone, Copy)]
+pub(crate) struct SyncOptions {
+    pub(crate) mode: SyncMode,
+    pub(crate) closed_prs: ClosedPrPolicy,
+    pub(crate) orphaned_prs: OrphanedPrPolicy,
+    pub(crate) rereview: RereviewPolicy,
+    pub(crate) layout: RefLayout,
+    /// Whether the sync must resume an interrupted sync of the stack, which
+    /// never pushes.
+    pub(crate) resume: bool,
+    /// Whether the sync may push new versions of PRs in the merge queue,
+    /// which removes them from the queue.
+    pub(crate) allow_requeue: bool,
+    /// Supplies the entropy of any gherrit-pr-id generated during the sync.
+    pub(crate) id_entropy: fn() -> IdEntropy,
+}
+
+impl SyncOptions {
+    /// Builds the options with the policies configured for `repo`.
+    pub(crate) fn from_config(
+        repo: &util::Repo,
+        mode: SyncMode,
+        id_entropy: fn() -> IdEntropy,
+    ) -> Result<Self> {
+        Ok(Self {
+            mode,
+            closed_prs: ClosedPrPolicy::from_config(repo)?,
+            orphaned_prs: OrphanedPrPolicy::from_config(repo)?,
+            rereview: RereviewPolicy::from_config(repo)?,
+            layout: RefLayout::from_config(repo)?,
+            resume: false,
+            allow_requeue: false,
+            id_entropy,
+        })
+    }
+}
+
+/// Syncs the stack of every managed branch in a push.
+///
+/// `pushed_refs` is the `pre-push` hook's stdin. Unmanaged refs pass through
+/// untouched. The repository's sync lock is held from the first managed stack
+/// on, so a push of unmanaged branches never waits for another sync.
+pub async fn run(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    pushed_refs: &str,
+    options: SyncOptions,
+) -> Result<()> {
+    let pushed_refs = parse_pushed_refs(pushed_refs)?;
+    if pushed_refs.is_empty() {
+        // Git omits refs that are already up to date. The loopback push of a
+        // private stack never changes anything, so it always lands here.
+        return sync_current_branch(repo, github_endpoint, options).await;
+    }
+
+    let is_local_branch = |branch: &str| {
+        repo.try_find_reference(format!("refs/heads/{branch}").as_str()).is_ok_and(|r| r.is_some())
+    };
+    let default_branch = repo.find_default_branch_on_default_remote();
+    let mut lock = None;
+    for (branch_name, tip) in stacks_to_sync(&pushed_refs, is_local_branch)? {
+        if branch_name == default_branch || !repo.is_managed(branch_name)? {
+            log::info!("Branch {} is UNMANAGED. Allowing standard push.", branch_name.yellow());
+            continue;
+        }
+        if lock.is_none() {
+            lock = Some(SyncLock::acquire(repo)?);
+        }
+        log::info!("Branch {} is MANAGED. Syncing stack...", branch_name.yellow());
+        let rewritten = sync_stack(repo, github_endpoint, branch_name, tip, options).await?;
+        ensure_push_not_stale(repo, branch_name, rewritten)?;
+    }
+    Ok(())
+}
+
+async fn sync_current_branch(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    options: SyncOptions,
+) -> Result<()> {
     let branch_name = repo.current_branch();
     let branch_name = match branch_name {
         HeadState::Attached(bn) | HeadState::Pending(bn) => bn,
@@ -86,13 +209,153 @@ pub async fn run(repo: &util::Repo, github_endpoint: &GithubEndpoint) -> Result<
         true => log::info!("Branch {} is MANAGED. Syncing stack...", branch_name.yellow()),
     }
 
-    let commits = collect_commits(repo).wrap_err("Failed to collect commits")?;
+    let _lock = SyncLock::acquire(repo)?;
+    let tip = repo.rev_parse_single("HEAD")?.detach();
+    let rewritten = sync_stack(repo, github_endpoint, branch_name, tip, options).await?;
+    ensure_push_not_stale(repo, branch_name, rewritten)
+}
+
+/// Rejects the push of a public branch whose commits the sync rewrote, since
+/// Git would otherwise publish the commits as they were before the rewrite.
+fn ensure_push_not_stale(repo: &util::Repo, branch_name: &str, rewritten: bool) -> Result<()> {
+    if rewritten && !is_private_stack(repo, branch_name) {
+        bail!(
+            "GHerrit rewrote the commits of branch '{branch_name}', so this push is out of date.\n\
+             Run 'git push' again to publish the rewritten branch."
+        );
+    }
+    Ok(())
+}
+
+/// Syncs the stack of managed branch `branch_name` that ends at `tip`.
+///
+/// Neither the branch nor `tip` needs to be checked out. The PRs of commits
+/// that left the stack since it was last pushed are handled according to
+/// `options.orphaned_prs`. Returns whether the branch was rewritten to resolve
+/// closed PRs.
+async fn sync_stack(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    branch_name: &str,
+    tip: ObjectId,
+    options: SyncOptions,
+) -> Result<bool> {
+    journal::recover(repo, options.mode).await?;
+    let mut commits =
+        collect_commits(repo, branch_name, tip).wrap_err("Failed to collect commits")?;
 
     if commits.is_empty() {
         log::info!("No commits to sync.");
-        return Ok(());
+        return Ok(false);
+    }
+
+    let octocrab = github_client(github_endpoint)?;
+
+    let (mut prs, mut remote_state) =
+        RemoteState::observe_stack(repo, &octocrab, &mut commits, options.layout).await?;
+    let branches: Vec<String> = commits.iter().map(|c| c.branch.clone()).collect();
+    let rewritten =
+        closed::resolve(repo, &octocrab, branch_name, tip, &mut commits, &mut prs, options).await?;
+    ensure_pull_requests_open(prs.iter().map(|pr| (pr.number, pr.state)))?;
+    if commits.iter().map(|c| &c.branch).ne(&branches) {
+        // Recreating closed PRs gave some commits fresh branches.
+        remote_state = RemoteState::observe(repo, &commits, options.layout).await?;
+    }
+    // The PR bodies still describe the stack as it was last pushed.
+    let orphans = orphaned::find(repo, &octocrab, branch_name, &commits, &prs).await?;
+    let mode = options.mode;
+
+    // Only new PRs need the repository ID, which is fetched during the push.
+    // Both are awaited to completion, so a failed lookup cannot interrupt the
+    // push.
+    let needs_repo_id = mode == SyncMode::Apply
+        && commits.iter().any(|c| !prs.iter().any(|pr| pr.head_branch == c.branch));
+    let repo_id = async {
+        match needs_repo_id {
+            true => fetch_repo_id(&octocrab, &repo.default_remote()?).await.map(Some),
+            false => Ok(None),
+        }
+    };
+    // A sync that failed during its PR operations has already pushed the
+    // stack, so it is resumed without pushing if neither the stack nor its
+    // managed branches have changed since.
+    let resumed = SyncJournal::load(repo, branch_name)?
+        .filter(|journal| journal.matches(&commits, &remote_state.branches));
+    if options.resume && resumed.is_none() {
+        bail!(
+            "The stack of branch '{branch_name}' changed since its interrupted sync, so the sync \
+             cannot be resumed. Run `gherrit sync` to sync it anew."
+        );
+    }
+    let default_branch = repo.find_default_branch_on_default_remote();
+    if resumed.is_none() {
+        let branches = &remote_state.branches;
+        guard_reviewed_prs(&commits, &prs, branches, &default_branch, options.allow_requeue)?;
+    }
+    let publish = async {
+        match &resumed {
+            Some(journal) => {
+                log::info!(
+                    "Resuming the interrupted sync of {}: {} PRs created.",
+                    branch_name.yellow(),
+                    journal.created_count()
+                );
+                Ok(journal.versions())
+            }
+            None => push_to_origin(repo, &commits, mode, options.layout, &remote_state).await,
+        }
+    };
+    let (latest_versions, repo_id) = tokio::join!(publish, repo_id);
+    let latest_versions = latest_versions?;
+    // The remote state still names the versions that the push replaced.
+    let revised = match (&resumed, options.rereview) {
+        (Some(journal), _) => journal.revised(),
+        (None, RereviewPolicy::Changed) => {
+            rereview::find_revised(repo, &commits, &remote_state.branches)?
+        }
+        (None, RereviewPolicy::Never) => HashSet::new(),
+    };
+    let journal = match mode {
+        SyncMode::Apply => {
+            let journal =
+                resumed.unwrap_or_else(|| SyncJournal::new(&commits, &latest_versions, &revised));
+            journal.save(repo, branch_name)?;
+            Some(journal)
+        }
+        SyncMode::DryRun => None,
+    };
+
+    let num_commits = commits.len();
+    sync_prs(
+        repo,
+        &octocrab,
+        branch_name,
+        &default_branch,
+        commits,
+        latest_versions,
+        &revised,
+        prs,
+        repo_id?,
+        journal,
+        options,
+    )
+    .await?;
+    if mode == SyncMode::Apply {
+        SyncJournal::remove(repo, branch_name)?;
+    }
+    orphaned::resolve(repo, &octocrab, &orphans, options).await?;
+
+    match mode {
+        SyncMode::Apply => log::info!("Successfully synced {num_commits} commits."),
+        SyncMode::DryRun => {
+            log::info!("Dry run of {num_commits} commits complete; nothing changed.")
+        }
     }
+    Ok(rewritten)
+}
 
+/// Builds the GitHub client for the endpoint selected by the caller.
+fn github_client(github_endpoint: &GithubEndpoint) -> Result<Octocrab> {
     if github_endpoint.is_disabled() {
         bail!("The GHerrit test driver cannot sync PRs without a configured GitHub endpoint");
     }
@@ -108,30 +371,16 @@ pub async fn run(repo: &util::Repo, github_endpoint: &GithubEndpoint) -> Result<
         builder = builder.base_uri(api_url)?;
     }
 
-    let octocrab = builder.build()?;
-
-    let gherrit_ids: Vec<String> = commits.iter().map(|c| c.gherrit_id.clone()).collect();
-    let prs = batch_fetch_prs(repo, &octocrab, &gherrit_ids).await?;
-    ensure_pull_requests_open(prs.iter().map(|pr| (pr.number, pr.state)))?;
-
-    let latest_versions = push_to_origin(repo, &commits)?;
-    let default_branch = repo.find_default_branch_on_default_remote();
-
-    let num_commits = commits.len();
-    sync_prs(repo, &octocrab, branch_name, &default_branch, commits, latest_versions, prs).await?;
-
-    log::info!("Successfully synced {num_commits} commits.");
-    Ok(())
+    Ok(builder.build()?)
 }
 
-fn collect_commits(repo: &util::Repo) -> Result<Vec<Commit>> {
-    let head = repo.rev_parse_single("HEAD")?;
+/// Collects the commits of `branch_name`'s stack, from the base to `tip`.
+fn collect_commits(repo: &util::Repo, branch_name: &str, tip: ObjectId) -> Result<Vec<Commit>> {
     let default_branch = repo.find_default_branch_on_default_remote();
     let default_ref = repo.rev_parse_single(format!("refs/heads/{}", default_branch).as_str())?;
 
-    let commits = repo.commits_between(default_ref, head).map_err(|err| match err {
+    let commits = repo.commits_between(default_ref, tip.attach(repo)).map_err(|err| match err {
         util::CommitsBetweenError::NotAncestor => {
-            let branch_name = repo.current_branch().name().unwrap_or("current branch");
             eyre!(
                 "The branch '{branch_name}' is not based on '{default_branch}'.\n\
                  GHerrit only supports stacked branches that share history with the default branch.\n\
@@ -141,6 +390,16 @@ fn collect_commits(repo: &util::Repo) -> Result<Vec<Commit>> {
         util::CommitsBetweenError::Eyre(e) => e,
     })?;
 
+    parse_stack(repo, &default_branch, commits)
+}
+
+/// Validates the commits of a stack, from the base to the tip, and reads their
+/// GHerrit metadata.
+fn parse_stack(
+    repo: &util::Repo,
+    default_branch: &str,
+    commits: Vec<gix::Commit<'_>>,
+) -> Result<Vec<Commit>> {
     let commits = commits
         .into_iter()
         .map(|commit| -> Result<_> {
@@ -152,14 +411,15 @@ fn collect_commits(repo: &util::Repo) -> Result<Vec<Commit>> {
     autosquash::ensure_publishable(
         commits.iter().map(|(_, title)| title.as_str()),
         &repo.default_remote_name(),
-        &default_branch,
+        default_branch,
     )?;
 
     let trailers = read_commit_trailers(&commits)?;
+    let draft_prefix = repo.config_string("gherrit.draftPrefix")?.filter(|p| !p.is_empty());
     let commits = commits
         .into_iter()
         .zip(trailers)
-        .map(|((commit, _), trailers)| Commit::from_git(commit, &trailers))
+        .map(|((commit, _), trailers)| Commit::from_git(commit, &trailers, draft_prefix.as_deref()))
         .collect::<Result<Vec<_>>>()?;
     ensure_unique_gherrit_ids(commits.iter().map(|commit| commit.gherrit_id.as_str()))?;
     Ok(commits)
@@ -203,6 +463,64 @@ fn read_commit_trailers(commits: &[(gix::Commit<'_>, String)]) -> Result<Vec<Vec
     )
 }
 
+/// Checks the open PRs that the sync publishes new versions of or updates.
+///
+/// A new version may dismiss the approvals of an approved PR, which only
+/// warrants a warning. A new version, a new base branch, or a conversion to
+/// draft removes a PR from the merge queue, though, so those are rejected
+/// unless `allow_requeue`.
+fn guard_reviewed_prs(
+    commits: &[Commit],
+    prs: &[PrState],
+    branches: &HashMap<String, String>,
+    base_branch: &str,
+    allow_requeue: bool,
+) -> Result<()> {
+    let mut queued = Vec::new();
+    let stack = link_stack(base_branch, commits, |c| c.gherrit_id.clone(), |c| c.branch.clone());
+    for entry in stack {
+        let c = entry.item;
+        let Some(pr) = prs.iter().find(|pr| pr.head_branch == c.branch) else {
+            continue;
+        };
+        if pr.state != PullRequestState::Open {
+            continue;
+        }
+        let is_new_version = branches.get(&c.branch) != Some(&c.id.to_string());
+        if pr.is_in_merge_queue {
+            let change = if is_new_version {
+                "new version"
+            } else if pr.base_branch != entry.base_branch {
+                "new base branch"
+            } else if pr.is_draft != c.draft {
+                "conversion to draft"
+            } else {
+                continue;
+            };
+            queued.push(format!("#{} ({change})", pr.number));
+        } else if is_new_version && pr.review_decision == Some(ReviewDecision::Approved) {
+            log::warn!(
+                "PR #{} is approved; pushing a new version of it may dismiss its approvals.",
+                pr.number.yellow()
+            );
+        }
+    }
+
+    if queued.is_empty() {
+        return Ok(());
+    }
+    let queued = queued.join(", ");
+    if allow_requeue {
+        log::warn!("Syncing removes {queued} from the merge queue.");
+        return Ok(());
+    }
+    bail!(
+        "Syncing would remove {queued} from the merge queue.\n\
+         Wait for them to merge, or push anyway with `gherrit sync --allow-requeue` or \
+         `GHERRIT_ALLOW_REQUEUE=1 git push`."
+    )
+}
+
 fn ensure_unique_gherrit_ids<'a>(ids: impl IntoIterator<Item = &'a str>) -> Result<()> {
     ids.into_iter().try_fold(HashSet::new(), |mut seen, id| {
         if !seen.insert(id) {
@@ -213,128 +531,294 @@ fn ensure_unique_gherrit_ids<'a>(ids: impl IntoIterator<Item = &'a str>) -> Resu
     Ok(())
 }
 
-#[allow(clippy::too_many_lines)]
-fn push_to_origin(repo: &util::Repo, commits: &[Commit]) -> Result<HashMap<String, usize>> {
-    let gherrit_ids: Vec<String> = commits.iter().map(|c| c.gherrit_id.clone()).collect();
-
-    // Fetch remote branch states to ensure we don't act on stale information.
-    let remote_branch_states = observe_managed_branches(repo, &gherrit_ids)?;
+/// How many times a push batch is attempted when other pushes keep publishing
+/// its versions first.
+const MAX_PUSH_ATTEMPTS: usize = 3;
 
-    let mut next_versions = HashMap::new();
+/// Publishes each changed commit of the stack as a new version, planning the
+/// push against `remote_state`.
+async fn push_to_origin(
+    repo: &util::Repo,
+    commits: &[Commit],
+    mode: SyncMode,
+    layout: RefLayout,
+    remote_state: &RemoteState,
+) -> Result<HashMap<String, usize>> {
+    let remote_branch_states = &remote_state.branches;
+    // Local version tags may be missing, e.g. in a fresh clone or on another
+    // machine, so the remote's version refs decide which version comes next.
+    let published = &remote_state.versions;
+    if mode == SyncMode::Apply {
+        restore_version_refs(repo, published).await?;
+    }
 
-    for chunk in push_batches(commits) {
-        let mut targets = Vec::with_capacity(chunk.len());
+    let gherrit_ids = commits.iter().map(|c| c.gherrit_id.as_str());
+    let local_versions = read_local_versions(repo, layout, gherrit_ids)?;
+    let mut latest_versions = HashMap::new();
+    let mut targets = Vec::with_capacity(commits.len());
+
+    for c in commits {
+        // Determine the next version based on the version tags (Optimistic
+        // Locking).
+        let remote_sha = remote_branch_states.get(&c.branch).map(String::as_str);
+        let published_latest = match published.get(&c.gherrit_id).and_then(|v| v.last()) {
+            Some((version, tag)) => Some(LocalVersion {
+                version: *version,
+                object_id: ObjectId::from_hex(tag.object_id.as_bytes())?,
+            }),
+            None => None,
+        };
+        let latest = latest_version(local_versions.latest(&c.gherrit_id), published_latest);
+        let next_ver = match decide_version(c.id, latest, remote_sha) {
+            VersionDecision::Unchanged { version } => {
+                log::debug!("{} is unchanged since v{version}", c.gherrit_id);
+                latest_versions.insert(c.gherrit_id.clone(), version);
+                continue;
+            }
+            VersionDecision::Publish { version } => version,
+        };
+        latest_versions.insert(c.gherrit_id.clone(), next_ver);
+
+        // Lease the branch to ensure it hasn't changed since our fetch. If we
+        // know the remote SHA, we expect it. If we don't (None), we expect ""
+        // (creation).
+        targets.push(PushTarget {
+            object_id: c.id,
+            gherrit_id: &c.gherrit_id,
+            branch: &c.branch,
+            version: next_ver,
+            expected_remote_sha: remote_sha.unwrap_or(""),
+        });
+    }
 
-        for c in chunk {
-            // Determine the next version based on local tags (Optimistic
-            // Locking).
-            let local_max = get_local_version(repo, &c.gherrit_id).unwrap_or(0);
-            let next_ver = local_max + 1;
-            next_versions.insert(c.gherrit_id.clone(), next_ver);
+    if targets.is_empty() {
+        log::info!("Every commit is already published.");
+        return Ok(latest_versions);
+    }
 
-            // Lease the branch to ensure it hasn't changed since our fetch.
-            // If we know the remote SHA, we expect it. If we don't (None), we
-            // expect "" (creation).
-            let expected_sha =
-                remote_branch_states.get(&c.gherrit_id).map(String::as_str).unwrap_or("");
+    let remote = repo.default_remote_name();
+    let mut batches: Vec<Vec<PushTarget<'_>>> = push_batches(&targets).map(<[_]>::to_vec).collect();
+    if mode == SyncMode::DryRun {
+        for batch in &batches {
+            print!("{}", preview::render_push(&plan_push(&remote, layout, batch)));
+        }
+        return Ok(latest_versions);
+    }
 
-            targets.push(PushTarget {
-                object_id: c.id,
-                gherrit_id: &c.gherrit_id,
-                version: next_ver,
-                expected_remote_sha: expected_sha,
-            });
+    // A stack larger than one batch is published by several atomic pushes, so
+    // it is journaled in order to roll back the published batches if a later
+    // one fails.
+    let mut journal = (batches.len() > 1).then(PublicationJournal::default);
+    let mut persisted_tags = Vec::new();
+    for (index, batch) in batches.iter_mut().enumerate() {
+        let journal_batch = journal.as_mut().map(|journal| (journal, index));
+        match publish_batch(repo, layout, &remote, batch, &mut latest_versions, journal_batch).await
+        {
+            Ok(tags) => persisted_tags.extend(tags),
+            Err(error) => {
+                let Some(journal) = &journal else {
+                    return Err(error);
+                };
+                let rolled_back = journal::roll_back(repo, journal)
+                    .await
+                    .and_then(|restored| PublicationJournal::remove(repo).map(|()| restored));
+                return Err(match rolled_back {
+                    Ok(restored) => error.wrap_err(format!(
+                        "Failed to publish the stack; rolled back the {restored} refs that its \
+                         earlier push batches published"
+                    )),
+                    Err(rollback_error) => error.wrap_err(format!(
+                        "Failed to publish the stack or to roll back its earlier push batches \
+                         ({rollback_error:#}). The next sync retries the rollback."
+                    )),
+                });
+            }
         }
+    }
 
-        let plan = plan_push(&repo.default_remote_name(), &targets);
+    // Persist the local version refs only now that the whole stack is
+    // published.
+    for tag in persisted_tags {
+        let _ = repo.reference(
+            layout.version_ref(&tag.gherrit_id, tag.version),
+            tag.object_id,
+            PreviousValue::Any,
+            "gherrit: persist local version state",
+        );
+    }
+    if journal.is_some() {
+        PublicationJournal::remove(repo)?;
+    }
+    Ok(latest_versions)
+}
+
+/// Pushes one batch of targets and returns the version tags to persist
+/// locally, retrying with the next versions if other pushes take them first.
+///
+/// If the stack spans several batches, the refs of each attempt are recorded
+/// in `journal` under the batch's index before they are pushed.
+async fn publish_batch(
+    repo: &util::Repo,
+    layout: RefLayout,
+    remote: &str,
+    batch: &mut [PushTarget<'_>],
+    latest_versions: &mut HashMap<String, usize>,
+    mut journal: Option<(&mut PublicationJournal, usize)>,
+) -> Result<Vec<PersistedTag>> {
+    for attempt in 1..=MAX_PUSH_ATTEMPTS {
+        let plan = plan_push(remote, layout, batch);
+        if let Some((journal, index)) = &mut journal {
+            journal.record(*index, layout, batch);
+            journal.save(repo)?;
+        }
 
         log::info!("Pushing chunk to remote...");
-        let mut child = util::cmd("git", plan.arguments)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::piped())
-            .spawn()
-            .wrap_err("Failed to run `git push`")?;
+        let Some(rejections) = run_push(plan.arguments).await? else {
+            return Ok(plan.persisted_tags);
+        };
 
-        // Filter output logic (elided for brevity, same as before)
-        {
-            use std::io::{BufRead, BufReader};
-            let stderr = child.stderr.take().unwrap();
-            let reader = BufReader::new(stderr);
-            let mut remote_buffer: Vec<String> = Vec::new();
-            let flush_buffer = |buf: &mut Vec<String>| {
-                if buf.is_empty() {
-                    return;
-                }
-                let block = buf.join("\n");
-                let re = re!(
-                    r"(?m)\n?^remote:\s*\nremote: Create a pull request for '.*' on GitHub by visiting:\s*\nremote:\s*https://github\.com/.*\nremote:\s*$"
-                );
-                let cleaned = re.replace(&block, "");
-                if !cleaned.is_empty() {
-                    eprintln!("{}", cleaned);
-                }
-                buf.clear();
-            };
-            for line in reader.lines() {
-                let line = line.unwrap();
-                if line.trim_start().starts_with("remote:") {
-                    remote_buffer.push(line);
-                } else {
-                    flush_buffer(&mut remote_buffer);
-                    eprintln!("{}", line);
-                }
+        let failures = diagnose(layout, batch, &rejections);
+        if failures.is_empty() {
+            bail!(
+                "`git push` to {remote} failed without rejecting a ref, which usually means an \
+                 authentication or network error. See Git's output above."
+            );
+        }
+        // A taken version tag means only that another push published the same
+        // version first; every branch lease held, so publishing the next
+        // version is safe.
+        if attempt < MAX_PUSH_ATTEMPTS && failures.iter().all(PushFailure::is_version_taken) {
+            let taken: Vec<String> =
+                failures.iter().map(|failure| failure.gherrit_id().to_string()).collect();
+            log::warn!(
+                "Another push published versions of {} first; retrying with the next versions.",
+                taken.join(", ").yellow()
+            );
+            let published = observe_published_versions(repo, layout, &taken).await?;
+            restore_version_refs(repo, &published).await?;
+            for target in batch.iter_mut().filter(|t| taken.iter().any(|id| id == t.gherrit_id)) {
+                let remote_latest = published
+                    .get(target.gherrit_id)
+                    .and_then(|versions| versions.last())
+                    .map_or(0, |(version, _)| *version);
+                target.version = remote_latest.max(target.version) + 1;
+                latest_versions.insert(target.gherrit_id.to_string(), target.version);
             }
-            flush_buffer(&mut remote_buffer);
+            continue;
         }
+        let failures: Vec<String> =
+            failures.iter().map(|failure| format!("  - {failure}")).collect();
+        bail!(
+            "`git push` to {remote} rejected the stack:\n{}\nRun `git fetch {remote}` to sync, then push again.",
+            failures.join("\n")
+        );
+    }
+    unreachable!("the last attempt either succeeds or fails the push")
+}
 
-        let status = child.wait().unwrap();
-        if !status.success() {
-            // If the push failed, it's likely due to a lease failure
-            // (concurrent modification). If failed, it might be due to the tag
-            // lock or branch lease.
-            let r = repo.default_remote_name();
-            bail!(
-                "`git push` failed. The remote might be ahead or changed. Run `git fetch {r}` to sync."
+/// Runs one `git push --porcelain`, relaying Git's standard error without
+/// GitHub's "Create a pull request" hints.
+///
+/// Returns `None` if the push succeeded, or else the refs that Git reported as
+/// rejected, which are none if the push failed before updating refs.
+async fn run_push(arguments: Vec<String>) -> Result<Option<Vec<RefRejection>>> {
+    use tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _, BufReader};
+
+    let mut child = util::async_cmd("git", arguments)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .wrap_err("Failed to run `git push`")?;
+
+    let mut stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let relay_stderr = async {
+        let mut lines = BufReader::new(stderr).lines();
+        let mut remote_buffer: Vec<String> = Vec::new();
+        let flush_buffer = |buf: &mut Vec<String>| {
+            if buf.is_empty() {
+                return;
+            }
+            let block = buf.join("\n");
+            let re = re!(
+                r"(?m)\n?^remote:\s*\nremote: Create a pull request for '.*' on GitHub by visiting:\s*\nremote:\s*https://github\.com/.*\nremote:\s*$"
             );
+            let cleaned = re.replace(&block, "");
+            if !cleaned.is_empty() {
+                eprintln!("{}", cleaned);
+            }
+            buf.clear();
+        };
+        while let Some(line) = lines.next_line().await? {
+            if line.trim_start().starts_with("remote:") {
+                remote_buffer.push(line);
+            } else {
+                flush_buffer(&mut remote_buffer);
+                eprintln!("{}", line);
+            }
         }
+        flush_buffer(&mut remote_buffer);
+        Ok::<_, std::io::Error>(())
+    };
+    let mut porcelain = String::new();
+    let (relayed, read) = tokio::join!(relay_stderr, stdout.read_to_string(&mut porcelain));
+    relayed?;
+    read?;
 
-        // Persist the local tags now that the push succeeded.
-        for tag in plan.persisted_tags {
-            let _ = repo.reference(
-                format!("refs/tags/gherrit/{}/v{}", tag.gherrit_id, tag.version),
-                tag.object_id,
-                PreviousValue::Any,
-                "gherrit: persist local version state",
-            );
+    let status = child.wait().await?;
+    Ok((!status.success()).then(|| parse_rejections(&porcelain)))
+}
+
+/// Fetches each published version ref that is missing locally or points
+/// elsewhere, so that local versioning continues from the remote's.
+async fn restore_version_refs(
+    repo: &util::Repo,
+    published: &BTreeMap<String, Vec<(usize, RemoteRef)>>,
+) -> Result<()> {
+    let mut refspecs = Vec::new();
+    for (_, tag) in published.values().flatten() {
+        let local = match repo.try_find_reference(tag.name.as_str())? {
+            Some(mut reference) => Some(reference.peel_to_id().map_err(|e| eyre!(e))?.to_string()),
+            None => None,
+        };
+        if local.as_deref() != Some(tag.object_id.as_str()) {
+            refspecs.push(format!("+{0}:{0}", tag.name));
         }
     }
+    if refspecs.is_empty() {
+        return Ok(());
+    }
 
-    Ok(next_versions)
+    for chunk in remote_query_batches(&refspecs) {
+        let arguments = ["fetch", "--quiet", "--no-tags", "--no-write-fetch-head"]
+            .into_iter()
+            .map(ToString::to_string)
+            .chain([repo.default_remote_name()])
+            .chain(chunk.iter().cloned());
+        util::spawn_checked(&mut util::async_cmd("git", arguments))?
+            .await
+            .wrap_err("Failed to restore version refs")?;
+    }
+    log::info!("Restored {} version refs from the remote.", refspecs.len());
+    Ok(())
 }
 
-fn get_local_version(repo: &util::Repo, gherrit_id: &str) -> Result<usize> {
-    let prefix = format!("refs/tags/gherrit/{}/v", gherrit_id);
-    let mut max_ver = 0;
-
-    // Use .all() and manual filtering to avoid `prefixed` API type issues.
+/// Indexes the locally recorded versions of `gherrit_ids` in one pass over
+/// the layout's refs.
+fn read_local_versions<'a>(
+    repo: &util::Repo,
+    layout: RefLayout,
+    gherrit_ids: impl IntoIterator<Item = &'a str>,
+) -> Result<LocalVersions> {
+    let mut index = LocalVersions::new(gherrit_ids);
     let references = repo.references().map_err(|e| eyre!(e))?;
-
-    for reference in references.all().map_err(|e| eyre!(e))? {
-        let reference = reference.map_err(|e| eyre!(e))?;
+    for reference in references.prefixed(layout.root()).map_err(|e| eyre!(e))? {
+        let mut reference = reference.map_err(|e| eyre!(e))?;
         let name = reference.name().as_bstr().to_string();
-
-        if name.starts_with(&prefix) {
-            // Parse "refs/tags/gherrit/<id>/v<ver>"
-            if let Some(ver_str) = name.rsplit('v').next()
-                && let Ok(ver) = ver_str.parse::<usize>()
-                && ver > max_ver
-            {
-                max_ver = ver;
-            }
-        }
+        index
+            .record(layout, &name, || Ok(reference.peel_to_id().map_err(|e| eyre!(e))?.detach()))?;
     }
-
-    Ok(max_ver)
+    Ok(index)
 }
 
 /// Syncs the local stack of commits with GitHub Pull Requests.
@@ -343,6 +827,20 @@ fn get_local_version(repo: &util::Repo, gherrit_id: &str) -> Result<usize> {
 /// 1. Finds existing PRs or creates new ones for new commits.
 /// 2. Updates PR metadata (title, body, base branch) to match the local stack.
 /// 3. Updates are queued and executed in batches to optimize performance.
+///
+/// The PRs of the `revised` commits, whose diff the push changed, also ask
+/// their previous reviewers to review them again.
+///
+/// `repo_id`, if already fetched, is the repository ID needed to create PRs.
+/// `journal`, when applying, records the created PRs as they are created, so
+/// that an interrupted sync can be resumed; the PRs that it records are reused
+/// rather than created again. The updates are planned anew on resume, from
+/// the PRs as they are then.
+///
+/// In a dry run, the creations and updates are printed instead. PRs that
+/// would be created have no number yet, so they are rendered as placeholders
+/// in the navigation of their neighbours.
+#[allow(clippy::too_many_arguments)]
 async fn sync_prs(
     repo: &util::Repo,
     octocrab: &Octocrab,
@@ -350,11 +848,21 @@ async fn sync_prs(
     base_branch: &str,
     commits: Vec<Commit>,
     latest_versions: HashMap<String, usize>,
+    revised: &HashSet<String>,
     prs: Vec<PrState>,
+    repo_id: Option<String>,
+    mut journal: Option<SyncJournal>,
+    options: SyncOptions,
 ) -> Result<()> {
     let remote = repo.default_remote()?;
+    let mode = options.mode;
 
-    let commits = link_stack(base_branch, commits, |commit| commit.gherrit_id.clone());
+    let commits = link_stack(
+        base_branch,
+        commits,
+        |commit| commit.gherrit_id.clone(),
+        |commit| commit.branch.clone(),
+    );
 
     enum PrResolution {
         Existing(PrState),
@@ -367,16 +875,35 @@ async fn sync_prs(
         .map(|entry| {
             let c = &entry.item;
 
-            if let Some(pr) = prs.iter().find(|pr| pr.head_branch == c.gherrit_id) {
-                log::debug!("Found existing PR #{} for {}", pr.number.green().bold(), c.gherrit_id);
+            if let Some(pr) = prs.iter().find(|pr| pr.head_branch == c.branch) {
+                log::debug!("Found existing PR #{} for {}", pr.number.green().bold(), c.branch);
                 PrResolution::Existing(pr.clone())
+            } else if let Some(created) = journal.as_ref().and_then(|j| j.created(&c.branch)) {
+                // GitHub may not list a PR by its head branch right after
+                // creating it.
+                log::debug!("The interrupted sync created PR #{} for {}", created.number, c.branch);
+                PrResolution::Existing(PrState {
+                    number: created.number,
+                    node_id: created.node_id.clone(),
+                    title: None,
+                    body: None,
+                    base_branch: created.base_branch.clone(),
+                    head_branch: c.branch.clone(),
+                    state: PullRequestState::Open,
+                    // The journal only resumes an unchanged stack, so the PR
+                    // was created with the draft state of this commit.
+                    is_draft: c.draft,
+                    review_decision: None,
+                    is_in_merge_queue: false,
+                })
             } else {
-                log::debug!("No GitHub PR exists for {}; queuing creation...", c.gherrit_id);
+                log::debug!("No GitHub PR exists for {}; queuing creation...", c.branch);
                 PrResolution::ToCreate(BatchCreate {
                     title: c.message_title.clone(),
                     body: c.message_body.clone(),
                     base_branch: entry.base_branch.clone(),
-                    head_branch: c.gherrit_id.clone(),
+                    head_branch: c.branch.clone(),
+                    draft: c.draft,
                 })
             }
         })
@@ -392,10 +919,42 @@ async fn sync_prs(
         .cloned()
         .collect::<Vec<_>>();
     let num_creations = creations.len();
-    let new_prs = if !creations.is_empty() {
+    let new_prs = if mode == SyncMode::DryRun {
+        creations.iter().for_each(|create| {
+            print!(
+                "{}",
+                preview::render_creation(
+                    &create.head_branch,
+                    &create.base_branch,
+                    &create.title,
+                    create.draft
+                )
+            );
+        });
+        HashMap::new()
+    } else if !creations.is_empty() {
         log::info!("Creating {num_creations} PRs...");
-        let repo_id = fetch_repo_id(octocrab, &remote).await?;
-        let created = batch_create_prs(octocrab, &repo_id, creations).await?;
+        let repo_id = match repo_id {
+            Some(repo_id) => repo_id,
+            None => fetch_repo_id(octocrab, &remote).await?,
+        };
+        let base_branches: HashMap<String, String> = creations
+            .iter()
+            .map(|create| (create.head_branch.clone(), create.base_branch.clone()))
+            .collect();
+        let created = batch_create_prs(octocrab, &repo_id, creations, |created| {
+            let Some(journal) = journal.as_mut() else {
+                return Ok(());
+            };
+            for pr in created {
+                let base_branch = base_branches[&pr.head_branch].clone();
+                let created =
+                    CreatedPr { number: pr.number, node_id: pr.node_id.clone(), base_branch };
+                journal.record_created(pr.head_branch.clone(), created);
+            }
+            journal.save(repo, branch_name)
+        })
+        .await?;
         assert_eq!(created.len(), num_creations);
         log::info!("Created {num_creations} PRs.");
         created
@@ -406,13 +965,14 @@ async fn sync_prs(
     // 3. Resolve final PR states
     //
     // We zip commits with resolutions. Since resolutions were built in order,
-    // they match perfectly.
+    // they match perfectly. Only a dry run leaves a PR unresolved.
     let commit_pr_states = commits
         .iter()
         .zip(resolutions)
         .map(|(entry, resolution)| {
             let pr_state = match resolution {
-                PrResolution::Existing(state) => state,
+                PrResolution::Existing(state) => Some(state),
+                PrResolution::ToCreate(_) if mode == SyncMode::DryRun => None,
                 PrResolution::ToCreate(create) => {
                     let created = new_prs.get(&create.head_branch).ok_or_else(|| {
                         eyre::eyre!("Failed to resolve created PR for {}", create.head_branch)
@@ -422,7 +982,7 @@ async fn sync_prs(
                         created.number.green().bold(),
                         created.url.blue().underline()
                     );
-                    PrState {
+                    Some(PrState {
                         number: created.number,
                         node_id: created.node_id.clone(),
                         title: Some(create.title),
@@ -432,71 +992,148 @@ async fn sync_prs(
                         // NOTE: We assume that newly-created PRs are in the
                         // OPEN state.
                         state: PullRequestState::Open,
-                    }
+                        is_draft: create.draft,
+                        review_decision: None,
+                        is_in_merge_queue: false,
+                    })
                 }
             };
             Ok((entry, pr_state))
         })
         .collect::<Result<Vec<_>>>()?;
 
-    let public_branch = (!is_private_stack(repo, branch_name))
-        .then(|| {
-            let head_ref = repo.head().ok()?.try_into_referent()?;
-            let (cat, short_name) = head_ref.inner.name.category_and_short_name()?;
-            (cat == Category::LocalBranch).then(|| short_name.to_string())
-        })
-        .flatten();
+    let lookups: Vec<_> = commit_pr_states
+        .iter()
+        .filter(|(entry, _)| revised.contains(&entry.item.gherrit_id))
+        .filter_map(|(entry, pr_state)| Some((entry.item.gherrit_id.as_str(), pr_state.as_ref()?)))
+        .collect();
+    let previous_reviewers: HashMap<&str, Vec<String>> = if lookups.is_empty() {
+        HashMap::new()
+    } else {
+        let operations: Vec<_> = lookups
+            .iter()
+            .map(|(_, pr)| {
+                PullRequestReviewers::new(remote.owner.clone(), remote.repo_name.clone(), pr.number)
+            })
+            .collect();
+        let reviewers = run_batched_graphql(octocrab, operations)
+            .await
+            .wrap_err("Failed to look up the previous reviewers of the revised PRs")?;
+        lookups.iter().map(|(id, _)| *id).zip(reviewers).collect()
+    };
+
+    let public_branch = (!is_private_stack(repo, branch_name)).then_some(branch_name);
 
     let repo_url = remote.repo_url_relative();
-    let stack_pr_numbers =
-        commit_pr_states.iter().map(|(_, state)| state.number).collect::<Vec<_>>();
+    let stack_pr_numbers = commit_pr_states
+        .iter()
+        .map(|(_, state)| state.as_ref().map(|s| s.number))
+        .collect::<Vec<_>>();
+    let local_versions = match options.layout {
+        RefLayout::Tags => LocalVersions::default(),
+        RefLayout::Refs => read_local_versions(
+            repo,
+            options.layout,
+            commit_pr_states.iter().map(|(entry, _)| entry.item.gherrit_id.as_str()),
+        )?,
+    };
     let updates: Vec<PrUpdate> = commit_pr_states
         .iter()
-        .filter_map(|(entry, pr_state)| {
+        .map(|(entry, pr_state)| {
             let c = &entry.item;
+            let Some(pr_state) = pr_state.as_ref() else {
+                return Ok(None);
+            };
             let latest_version = latest_versions.get(&c.gherrit_id).copied().unwrap_or(1);
+            // GitHub resolves only branches and tags by name in compare links,
+            // so other layouts link to each version by its object ID.
+            let version_object_ids = match options.layout {
+                RefLayout::Tags => Vec::new(),
+                RefLayout::Refs => {
+                    let mut versions = local_versions.versions(&c.gherrit_id);
+                    // In a dry run, the latest version is not recorded yet.
+                    versions.insert(latest_version, c.id);
+                    (1..=latest_version).map(|version| versions.get(&version).copied()).collect()
+                }
+            };
+            let version_revs = match options.layout {
+                RefLayout::Tags => VersionRevs::Tags,
+                RefLayout::Refs => VersionRevs::ObjectIds(&version_object_ids),
+            };
 
             let body = PrBody {
                 commit_body: &c.message_body,
                 repo_url: &repo_url,
-                public_branch: public_branch.as_deref(),
+                public_branch,
                 stack_pr_numbers: &stack_pr_numbers,
                 current_pr_number: pr_state.number,
                 latest_version,
+                version_revs,
                 base_branch: &entry.base_branch,
                 gherrit_id: &c.gherrit_id,
-                parent_id: entry.parent_id.as_deref(),
-                child_id: entry.child_id.as_deref(),
+                branch: &c.branch,
+                parent: entry
+                    .parent_id
+                    .as_deref()
+                    .map(|id| Link { id, branch: &entry.base_branch }),
+                child: entry
+                    .child_id
+                    .as_deref()
+                    .zip(entry.child_branch.as_deref())
+                    .map(|(id, branch)| Link { id, branch }),
+                triage: &c.triage,
             }
             .render();
 
             let pr_num = pr_state.number.green().bold().to_string();
             let pr_url = remote.pr_url(pr_state.number).blue().underline().to_string();
 
+            let recorded_triage = pr_state
+                .body
+                .as_deref()
+                .and_then(parse_metadata)
+                .map(|metadata| metadata.triage)
+                .unwrap_or_default();
             let update = plan_update(
                 CurrentPr {
                     node_id: &pr_state.node_id,
                     title: pr_state.title.as_deref(),
                     body: pr_state.body.as_deref(),
                     base_branch: &pr_state.base_branch,
+                    is_draft: pr_state.is_draft,
+                    triage: &recorded_triage,
+                },
+                DesiredPr {
+                    title: &c.message_title,
+                    body: &body,
+                    base_branch: &entry.base_branch,
+                    draft: c.draft,
+                    triage: &c.triage,
+                    rereview: previous_reviewers
+                        .get(c.gherrit_id.as_str())
+                        .map_or(&[], Vec::as_slice),
                 },
-                DesiredPr { title: &c.message_title, body: &body, base_branch: &entry.base_branch },
             );
 
-            if update.is_some() {
+            if let Some(update) = &update {
                 log::debug!("Queuing update for PR #{}", pr_num);
                 log::info!("Queued update for PR #{}: {}", pr_num, pr_url);
+                if mode == SyncMode::DryRun {
+                    let url = remote.pr_url(pr_state.number);
+                    print!("{}", preview::render_update(&url, pr_state, update));
+                }
             } else {
                 log::info!("PR #{} is up to date: {}", pr_num, pr_url);
             }
 
-            update
+            Ok(update)
         })
-        .collect();
+        .filter_map(Result::transpose)
+        .collect::<Result<_>>()?;
 
-    if !updates.is_empty() {
+    if mode == SyncMode::Apply && !updates.is_empty() {
         log::info!("Updating batch of {} PRs...", updates.len());
-        batch_update_prs(octocrab, updates).await?;
+        batch_update_prs(octocrab, &remote, updates).await?;
         log::info!("Batch update complete.");
     }
 
@@ -514,12 +1151,21 @@ fn is_private_stack(repo: &util::Repo, branch: &str) -> bool {
 struct Commit {
     id: ObjectId,
     gherrit_id: String,
+    /// The managed branch of the commit's PR, as resolved by
+    /// [`fetch_stack_prs`]. Until then, it is the gherrit-pr-id.
+    branch: String,
     message_title: String,
     message_body: String,
+    /// Whether the commit's PR is a draft, as set by a `gherrit-draft`
+    /// trailer or, failing that, by a title starting with `draft_prefix`.
+    draft: bool,
+    /// The reviewers, labels, and assignees requested by the commit's
+    /// trailers.
+    triage: Triage,
 }
 
 impl Commit {
-    fn from_git(c: gix::Commit<'_>, trailers: &[u8]) -> Result<Self> {
+    fn from_git(c: gix::Commit<'_>, trailers: &[u8], draft_prefix: Option<&str>) -> Result<Self> {
         let message = c.message()?;
         let message_title = core::str::from_utf8(message.title)?.to_string();
         let message_body =
@@ -540,13 +1186,40 @@ impl Commit {
             bail!("Commit {} has invalid gherrit-pr-id trailer", c.id);
         }
         let gherrit_id = str::from_utf8(gherrit_id)?.to_string();
-        let message_body = strip_gherrit_id(&message_body, &gherrit_id);
+        let message_body = strip_control_trailers(&message_body);
+
+        // The last `gherrit-draft` trailer wins, as with other Git trailers.
+        let draft = trailers
+            .split(|byte| *byte == b'\n')
+            .filter_map(|line| line.strip_prefix(b"gherrit-draft: "))
+            .try_fold(None, |_, value| match value {
+                b"true" => Ok(Some(true)),
+                b"false" => Ok(Some(false)),
+                _ => Err(eyre!(
+                    "Commit {} has invalid gherrit-draft trailer '{}'; expected 'true' or 'false'",
+                    c.id,
+                    String::from_utf8_lossy(value)
+                )),
+            })?
+            .unwrap_or_else(|| {
+                draft_prefix.is_some_and(|prefix| message_title.starts_with(prefix))
+            });
 
-        Ok(Commit { id: c.id, gherrit_id, message_title, message_body })
+        let triage = Triage::from_trailers(trailers)
+            .wrap_err_with(|| format!("Commit {} has invalid trailers", c.id))?;
+
+        let branch = gherrit_id.clone();
+        Ok(Commit { id: c.id, gherrit_id, branch, message_title, message_body, draft, triage })
     }
 }
 
-fn strip_gherrit_id(body: &str, id: &str) -> String {
+/// The trailers that control GHerrit rather than describe the change, which
+/// are left out of PR descriptions, along with the triage trailers.
+const CONTROL_TRAILERS: &[&str] = &["gherrit-pr-id", "gherrit-draft"];
+
+/// Removes the [`CONTROL_TRAILERS`] and the triage trailers from the last
+/// paragraph of a commit message body. The PR metadata records the triage.
+fn strip_control_trailers(body: &str) -> String {
     let trailer_start = body
         .rfind("\n\n")
         .map(|position| position + 2)
@@ -554,19 +1227,22 @@ fn strip_gherrit_id(body: &str, id: &str) -> String {
         .chain(body.rfind("\r\n\r\n").map(|position| position + 4))
         .max()
         .unwrap_or(0);
-    let matching_trailer = gherrit_pr_id_re()
-        .captures_iter(&body[trailer_start..])
-        .filter(|captures| captures.get(1).is_some_and(|value| value.as_str() == id))
-        .filter_map(|captures| captures.get(0))
-        .last();
-    let Some(trailer) = matching_trailer else {
-        return body.to_string();
+    let (message, trailers) = body.split_at(trailer_start);
+    let is_control = |line: &str| {
+        line.find([':', '=']).is_some_and(|end| {
+            CONTROL_TRAILERS.contains(&&line[..end]) || Triage::is_trailer(&line[..end])
+        })
     };
-
-    let mut body = body.to_string();
-    let range = trailer.range();
-    body.replace_range(trailer_start + range.start..trailer_start + range.end, "");
-    body
+    // Each control trailer leaves its line break behind, which keeps the
+    // bodies of existing PRs unchanged.
+    let trailers: String = trailers
+        .split_inclusive('\n')
+        .map(|line| match is_control(line) {
+            true => &line[line.trim_end_matches('\n').len()..],
+            false => line,
+        })
+        .collect();
+    format!("{message}{trailers}")
 }
 
 /// A request to create a new PR in a batch.
@@ -576,6 +1252,7 @@ struct BatchCreate {
     body: String,
     base_branch: String,
     head_branch: String,
+    draft: bool,
 }
 
 /// Fetches the global Repository Node ID for the given owner and repo.
@@ -595,24 +1272,132 @@ async fn fetch_repo_id(octocrab: &Octocrab, remote: &util::Remote) -> Result<Str
 ///
 /// This avoids rate limits and network latency by grouping updates into
 /// adaptive batches and sending each batch as one GraphQL operation.
-async fn batch_update_prs(octocrab: &Octocrab, updates: Vec<PrUpdate>) -> Result<()> {
-    let updates = updates.into_iter().map(|update| {
-        UpdatePullRequest::new(update.node_id, update.title, update.body, update.base_branch)
-    });
-    run_batched_graphql(octocrab, updates).await?;
+///
+/// An update may take several mutations, which may land in different batches:
+/// one for each kind of triage it adds, one for its metadata, and one for its
+/// draft state. The triage goes first, since the metadata records it as
+/// requested.
+async fn batch_update_prs(
+    octocrab: &Octocrab,
+    remote: &util::Remote,
+    updates: Vec<PrUpdate>,
+) -> Result<()> {
+    let node_ids = resolve_triage(octocrab, remote, &updates).await?;
+    let node_ids = |lookups: Vec<NodeLookup>| -> Vec<String> {
+        lookups.iter().map(|lookup| node_ids[lookup].clone()).collect()
+    };
+
+    let mut changes = Vec::with_capacity(updates.len());
+    for update in updates {
+        let Triage { reviewers, labels, assignees } = &update.triage;
+        // The previous reviewers are asked again alongside the added ones.
+        let mut reviewers = reviewers.clone();
+        for login in &update.rereview {
+            if !reviewers.contains(login) {
+                reviewers.push(login.clone());
+            }
+        }
+        if !reviewers.is_empty() {
+            let (teams, users): (Vec<_>, Vec<_>) = reviewers
+                .iter()
+                .map(|reviewer| person_lookup(reviewer))
+                .partition(|lookup| matches!(lookup, NodeLookup::Team { .. }));
+            changes.push(PullRequestChange::RequestReviews(RequestReviews::new(
+                update.node_id.clone(),
+                node_ids(users),
+                node_ids(teams),
+            )));
+        }
+        if !labels.is_empty() {
+            let labels = labels.iter().map(|label| label_lookup(remote, label)).collect();
+            changes.push(PullRequestChange::AddLabels(AddLabels::new(
+                update.node_id.clone(),
+                node_ids(labels),
+            )));
+        }
+        if !assignees.is_empty() {
+            let assignees = assignees.iter().map(|assignee| person_lookup(assignee)).collect();
+            changes.push(PullRequestChange::AddAssignees(AddAssignees::new(
+                update.node_id.clone(),
+                node_ids(assignees),
+            )));
+        }
+
+        let draft = update.draft;
+        if update.changes_metadata() || (draft.is_none() && update.triage.is_empty()) {
+            changes.push(PullRequestChange::Update(UpdatePullRequest::new(
+                update.node_id.clone(),
+                update.title,
+                update.body,
+                update.base_branch,
+            )));
+        }
+        if let Some(draft) = draft {
+            changes
+                .push(PullRequestChange::SetDraft(SetPullRequestDraft::new(update.node_id, draft)));
+        }
+    }
+
+    run_batched_graphql(octocrab, changes).await?;
     Ok(())
 }
 
+/// Looks up the node IDs of the reviewers, labels, and assignees that
+/// `updates` add, in adaptive batches.
+async fn resolve_triage(
+    octocrab: &Octocrab,
+    remote: &util::Remote,
+    updates: &[PrUpdate],
+) -> Result<HashMap<NodeLookup, String>> {
+    let mut lookups = Vec::new();
+    for update in updates {
+        let Triage { reviewers, labels, assignees } = &update.triage;
+        let people = reviewers.iter().chain(assignees).chain(&update.rereview);
+        let people = people.map(|login| person_lookup(login));
+        let labels = labels.iter().map(|label| label_lookup(remote, label));
+        for lookup in people.chain(labels) {
+            if !lookups.contains(&lookup) {
+                lookups.push(lookup);
+            }
+        }
+    }
+    let node_ids = run_batched_graphql(octocrab, lookups.clone())
+        .await
+        .wrap_err("Failed to look up the requested reviewers, labels, and assignees")?;
+    Ok(lookups.into_iter().zip(node_ids).collect())
+}
+
+/// The lookup of a user or `org/team`, as named in a triage trailer.
+fn person_lookup(login: &str) -> NodeLookup {
+    match login.split_once('/') {
+        Some((organization, slug)) => {
+            NodeLookup::Team { organization: organization.to_string(), slug: slug.to_string() }
+        }
+        None if login == triage::VIEWER => NodeLookup::Viewer,
+        None => NodeLookup::User(login.to_string()),
+    }
+}
+
+fn label_lookup(remote: &util::Remote, name: &str) -> NodeLookup {
+    NodeLookup::Label {
+        owner: remote.owner.clone(),
+        repository: remote.repo_name.clone(),
+        name: name.to_string(),
+    }
+}
+
 /// Performs batched creation of PRs using GitHub's GraphQL API.
 ///
 /// This avoids rate limits and network latency by grouping creations into
 /// adaptive batches and sending each batch as one GraphQL operation.
 ///
-/// Returns the newly-created PRs keyed by their head branches.
+/// Calls `on_batch` with the PRs of each batch once they are created, and
+/// returns the newly-created PRs keyed by their head branches.
 async fn batch_create_prs(
     octocrab: &Octocrab,
     repo_id: &str,
     creations: impl IntoIterator<Item = BatchCreate>,
+    on_batch: impl FnMut(&[CreatedPullRequest]) -> Result<()>,
 ) -> Result<HashMap<String, CreatedPullRequest>> {
     let creations = creations.into_iter().map(|create| {
         CreatePullRequest::new(
@@ -621,9 +1406,10 @@ async fn batch_create_prs(
             create.head_branch,
             create.title,
             create.body,
+            create.draft,
         )
     });
-    Ok(run_batched_graphql(octocrab, creations)
+    Ok(run_batched_graphql_with_progress(octocrab, creations, on_batch)
         .await?
         .into_iter()
         .map(|created| (created.head_branch.clone(), created))
@@ -646,6 +1432,44 @@ async fn batch_fetch_prs(
     Ok(run_batched_graphql(octocrab, queries).await?.into_iter().flatten().collect())
 }
 
+/// Resolves the managed branch of each of `commits`, then looks up their PRs
+/// and observes which of the branches exist on the remote, by name.
+///
+/// With the default template, each branch is named by its gherrit-pr-id, so
+/// the PR lookup runs at once with the observation. Otherwise the observation
+/// also finds the existing branches that the PR lookup needs.
+async fn fetch_stack_prs(
+    repo: &util::Repo,
+    octocrab: &Octocrab,
+    commits: &mut [Commit],
+) -> Result<(Vec<PrState>, HashMap<String, String>)> {
+    let naming = BranchNaming::from_config(repo)?;
+    let gherrit_ids: Vec<String> = commits.iter().map(|c| c.gherrit_id.clone()).collect();
+    if naming.is_default() {
+        return tokio::try_join!(
+            batch_fetch_prs(repo, octocrab, &gherrit_ids),
+            observe_managed_branches(repo, &gherrit_ids),
+        );
+    }
+
+    let mut found =
+        naming.find(repo, &gherrit_ids).await.wrap_err("Failed to resolve managed branches")?;
+    let mut branches = HashMap::new();
+    for commit in commits.iter_mut() {
+        commit.branch = match found.remove(&commit.gherrit_id) {
+            Some((branch, object_id)) => {
+                branches.insert(branch.clone(), object_id);
+                branch
+            }
+            None => naming
+                .render(&commit.gherrit_id, &commit.message_title)
+                .wrap_err("Failed to resolve managed branches")?,
+        };
+    }
+    let names: Vec<String> = commits.iter().map(|c| c.branch.clone()).collect();
+    Ok((batch_fetch_prs(repo, octocrab, &names).await?, branches))
+}
+
 /// Executes batched GraphQL operations (queries or mutations).
 ///
 /// Builds a combined query for each adaptive batch and decodes each operation
@@ -654,6 +1478,19 @@ async fn run_batched_graphql<O>(
     octocrab: &Octocrab,
     operations: impl IntoIterator<Item = O>,
 ) -> Result<Vec<O::Output>>
+where
+    O: BatchedOperation,
+{
+    run_batched_graphql_with_progress(octocrab, operations, |_| Ok(())).await
+}
+
+/// Executes batched GraphQL operations like [`run_batched_graphql`], calling
+/// `on_batch` with the outputs of each batch, in order, once it succeeds.
+async fn run_batched_graphql_with_progress<O>(
+    octocrab: &Octocrab,
+    operations: impl IntoIterator<Item = O>,
+    mut on_batch: impl FnMut(&[O::Output]) -> Result<()>,
+) -> Result<Vec<O::Output>>
 where
     O: BatchedOperation,
 {
@@ -741,7 +1578,9 @@ where
             continue;
         };
 
-        outputs.extend(decode_batch_response(chunk, response)?);
+        let decoded = decode_batch_response(chunk, response)?;
+        on_batch(&decoded)?;
+        outputs.extend(decoded);
 
         batches.accept();
     }
@@ -0,0 +1,305 @@
+use std::collections::{HashMap, hash_map::Entry};
+
+use color_eyre::eyre::{Context as _, Result, bail, eyre};
+use gix::ObjectId;
+
+use super::remote::ls_remote_batches;
+use crate::util;
+
+const DEFAULT_TEMPLATE: &str = "{id}";
+const ID: &str = "{id}";
+const USER: &str = "{user}";
+const SLUG: &str = "{slug}";
+const MAX_SLUG_LEN: usize = 40;
+
+/// How managed branches are named, e.g. `gherrit/{user}/{id}`.
+///
+/// A template contains `{id}` exactly once. It may also contain `{user}` and
+/// `{slug}`, a slug of the commit's title when its PR was created. The head
+/// branch of a PR cannot change, so once a commit has been pushed, its branch
+/// is found on the remote rather than rendered again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BranchNaming {
+    template: String,
+    user: Option<String>,
+}
+
+impl Default for BranchNaming {
+    fn default() -> Self {
+        Self { template: DEFAULT_TEMPLATE.to_string(), user: None }
+    }
+}
+
+impl BranchNaming {
+    pub(crate) const CONFIG_KEY: &str = "gherrit.branchTemplate";
+    const USER_KEY: &str = "gherrit.user";
+
+    /// Reads the repository's template, defaulting to `{id}`.
+    ///
+    /// `{user}` is `gherrit.user`, or else the local part of `user.email`.
+    pub(crate) fn from_config(repo: &util::Repo) -> Result<Self> {
+        let Some(template) = repo.config_string(Self::CONFIG_KEY)? else {
+            return Ok(Self::default());
+        };
+        let user = match repo.config_string(Self::USER_KEY)? {
+            Some(user) => Some(user),
+            None => repo
+                .config_string("user.email")?
+                .and_then(|email| Some(email.split_once('@')?.0.to_string())),
+        };
+        Self::new(template, user).wrap_err_with(|| format!("Invalid {}", Self::CONFIG_KEY))
+    }
+
+    pub(super) fn new(template: String, user: Option<String>) -> Result<Self> {
+        if template.matches(ID).count() != 1 {
+            bail!("'{template}' must contain {ID} exactly once");
+        }
+        let literal = [ID, USER, SLUG].iter().fold(template.clone(), |rest, p| rest.replace(p, ""));
+        if literal.contains(['{', '}']) {
+            bail!(
+                "'{template}' contains an unknown placeholder. Expected {ID}, {USER}, or {SLUG}."
+            );
+        }
+        let sample = template.replace(ID, "Gid").replace(SLUG, "slug").replace(USER, "user");
+        if !is_branch_name(&sample) {
+            bail!("'{template}' does not form valid branch names");
+        }
+        Ok(Self { template, user })
+    }
+
+    pub(super) fn is_default(&self) -> bool {
+        self.template == DEFAULT_TEMPLATE
+    }
+
+    /// Whether a branch name depends on nothing but the gherrit-pr-id.
+    pub(super) fn is_static(&self) -> bool {
+        !self.template.contains(USER) && !self.template.contains(SLUG)
+    }
+
+    /// Names the branch of a commit that has not been pushed yet.
+    pub(super) fn render(&self, gherrit_id: &str, title: &str) -> Result<String> {
+        let mut name = self.template.replace(ID, gherrit_id).replace(SLUG, &slug(title));
+        if name.contains(USER) {
+            let user = self.user.as_deref().ok_or_else(|| {
+                eyre!(
+                    "{} uses {USER}, but neither {} nor user.email is set",
+                    Self::CONFIG_KEY,
+                    Self::USER_KEY
+                )
+            })?;
+            name = name.replace(USER, user);
+        }
+        if !is_branch_name(&name) {
+            bail!("The {} template names an invalid branch '{name}'", Self::CONFIG_KEY);
+        }
+        Ok(name)
+    }
+
+    /// Names the branch of `gherrit_id` if that takes no lookup.
+    pub(super) fn render_static(&self, gherrit_id: &str) -> Option<String> {
+        self.is_static().then(|| self.template.replace(ID, gherrit_id))
+    }
+
+    /// A `git ls-remote` pattern that matches every branch the template names,
+    /// with `id` in place of `{id}`.
+    pub(super) fn pattern(&self, id: &str) -> String {
+        let pattern = self.template.replace(ID, id).replace(SLUG, "*").replace(USER, "*");
+        format!("refs/heads/{pattern}")
+    }
+
+    /// Returns the gherrit-pr-id of a branch the template names.
+    ///
+    /// A branch named just by its gherrit-pr-id also counts, since it was
+    /// pushed before the template was configured.
+    pub(super) fn parse<'a>(&self, branch: &'a str) -> Option<&'a str> {
+        if !branch.is_empty() && branch.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+            return Some(branch);
+        }
+        let mut pattern = String::from("^");
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            pattern.push_str(&regex::escape(&rest[..start]));
+            let end = start + rest[start..].find('}')? + 1;
+            pattern.push_str(match &rest[start..end] {
+                ID => "([a-zA-Z0-9]+)",
+                USER => "[^/]+",
+                _ => "[a-z0-9]+(?:-[a-z0-9]+)*",
+            });
+            rest = &rest[end..];
+        }
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+        let captures = regex::Regex::new(&pattern).ok()?.captures(branch)?;
+        Some(captures.get(1)?.as_str())
+    }
+
+    /// Maps each of `ids` to the branch on the remote that the template, or
+    /// the bare gherrit-pr-id, names for it. An ID with no such branch is
+    /// missing, except with the default template, which maps every ID to
+    /// itself without a lookup.
+    pub(super) async fn lookup(
+        &self,
+        repo: &util::Repo,
+        ids: &[String],
+    ) -> Result<HashMap<String, String>> {
+        if self.is_default() {
+            return Ok(ids.iter().map(|id| (id.clone(), id.clone())).collect());
+        }
+        let found = self.find(repo, ids).await?;
+        Ok(found.into_iter().map(|(id, (branch, _))| (id, branch)).collect())
+    }
+
+    /// Finds the branch on the remote that the template, or the bare
+    /// gherrit-pr-id, names for each of `ids`, along with its object ID.
+    ///
+    /// The pattern of every branch the template could name for an ID is
+    /// listed, so an ID with no match has no managed branch on the remote.
+    pub(super) async fn find(
+        &self,
+        repo: &util::Repo,
+        ids: &[String],
+    ) -> Result<HashMap<String, (String, String)>> {
+        let mut branches = HashMap::new();
+        for (chunk, output) in
+            ls_remote_batches(repo, ids, |id| [self.pattern(id), format!("refs/heads/{id}")])?
+        {
+            for (id, branch) in self.parse_lookup(&output.await?.stdout, chunk)? {
+                match branches.entry(id) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(branch);
+                    }
+                    Entry::Occupied(entry) => bail!(
+                        "gherrit-pr-id {} has several managed branches: {} and {}",
+                        entry.key(),
+                        entry.get().0,
+                        branch.0
+                    ),
+                }
+            }
+        }
+        Ok(branches)
+    }
+
+    /// Parses `git ls-remote` standard output into the branches of the
+    /// `requested` IDs, with their object IDs.
+    fn parse_lookup(
+        &self,
+        output: &[u8],
+        requested: &[String],
+    ) -> Result<Vec<(String, (String, String))>> {
+        let output = output.strip_suffix(b"\n").unwrap_or(output);
+        let mut branches = Vec::new();
+        for line in output.split(|byte| *byte == b'\n').filter(|line| !line.is_empty()) {
+            let mut fields = line.split(|byte| *byte == b'\t');
+            let (Some(object_id), Some(ref_name), None) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                bail!("malformed `git ls-remote` line: {line:?}");
+            };
+            let object_id = ObjectId::from_hex(object_id)
+                .wrap_err_with(|| format!("invalid object ID in `git ls-remote` line: {line:?}"))?;
+            let Some(branch) =
+                str::from_utf8(ref_name).ok().and_then(|name| name.strip_prefix("refs/heads/"))
+            else {
+                continue;
+            };
+            if let Some(id) = self.parse(branch)
+                && requested.iter().any(|requested| requested == id)
+            {
+                branches.push((id.to_string(), (branch.to_string(), object_id.to_string())));
+            }
+        }
+        Ok(branches)
+    }
+}
+
+fn is_branch_name(name: &str) -> bool {
+    gix::refs::FullName::try_from(format!("refs/heads/{name}")).is_ok()
+}
+
+/// Turns a commit title into lowercase words joined by dashes.
+fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for word in title.split(|c: char| !c.is_ascii_alphanumeric()).filter(|word| !word.is_empty()) {
+        if !slug.is_empty() && slug.len() + 1 + word.len() > MAX_SLUG_LEN {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    slug.truncate(MAX_SLUG_LEN);
+    if slug.is_empty() { "untitled".to_string() } else { slug }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naming(template: &str) -> BranchNaming {
+        BranchNaming::new(template.to_string(), Some("octo".to_string())).unwrap()
+    }
+
+    #[test]
+    fn renders_and_parses_branch_names() {
+        let user = naming("gherrit/{user}/{id}");
+        assert_eq!(user.render("Gone", "Add widgets").unwrap(), "gherrit/octo/Gone");
+        assert_eq!(user.parse("gherrit/octo/Gone"), Some("Gone"));
+        assert_eq!(user.parse("gherrit/someone-else/Gone"), Some("Gone"));
+        assert_eq!(user.parse("Gone"), Some("Gone"), "branches from before the template");
+        assert_eq!(user.parse("gherrit/octo/nested/Gone"), None);
+        assert_eq!(user.pattern("Gone"), "refs/heads/gherrit/*/Gone");
+        assert!(!user.is_static());
+
+        let slug = naming("{id}-{slug}");
+        assert_eq!(
+            slug.render("Gtwo", "Fix the [widget] parser's crash").unwrap(),
+            "Gtwo-fix-the-widget-parser-s-crash"
+        );
+        assert_eq!(slug.parse("Gtwo-fix-the-widget-parser-s-crash"), Some("Gtwo"));
+        assert_eq!(slug.parse("Gtwo-"), None);
+        assert_eq!(slug.render("Gtwo", "☃").unwrap(), "Gtwo-untitled");
+
+        let prefixed = naming("stack/{id}");
+        assert_eq!(prefixed.render_static("Gone").as_deref(), Some("stack/Gone"));
+        assert_eq!(prefixed.parse("stack/Gone"), Some("Gone"));
+        assert_eq!(BranchNaming::default().render("Gone", "Title").unwrap(), "Gone");
+    }
+
+    #[test]
+    fn limits_slugs_to_whole_words() {
+        let title = "Replace the widget registry with a lock-free concurrent map";
+        assert_eq!(slug(title), "replace-the-widget-registry-with-a-lock");
+        assert_eq!(slug(&"x".repeat(50)).len(), MAX_SLUG_LEN);
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in ["gherrit/{user}", "{id}/{id}", "{id}-{title}", "{id}..{slug}", "{id} x"] {
+            assert!(BranchNaming::new(template.to_string(), None).is_err(), "{template}");
+        }
+        let anonymous = BranchNaming::new("{user}/{id}".to_string(), None).unwrap();
+        assert!(anonymous.render("Gone", "Title").is_err());
+    }
+
+    #[test]
+    fn parses_lookups_of_requested_ids() {
+        let naming = naming("gherrit/{user}/{id}");
+        let (one, two) = ("1".repeat(40), "2".repeat(40));
+        let output = format!(
+            "{one}\trefs/heads/gherrit/octo/Gone\n\
+             {one}\trefs/heads/gherrit/octo/Gother\n\
+             {two}\trefs/heads/Gtwo\n"
+        );
+        let requested = ["Gone".to_string(), "Gtwo".to_string()];
+        assert_eq!(
+            naming.parse_lookup(output.as_bytes(), &requested).unwrap(),
+            [
+                ("Gone".to_string(), ("gherrit/octo/Gone".to_string(), one)),
+                ("Gtwo".to_string(), ("Gtwo".to_string(), two))
+            ]
+        );
+        assert!(naming.parse_lookup(b"xyz\trefs/heads/Gone\n", &requested).is_err());
+    }
+}
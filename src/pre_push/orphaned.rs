@@ -0,0 +1,232 @@
+use std::collections::HashSet;
+
+use color_eyre::eyre::Result;
+use octocrab::Octocrab;
+use owo_colors::OwoColorize as _;
+
+use super::{
+    Commit, PrState, SyncMode, SyncOptions, batch_fetch_prs, batch_update_prs,
+    body::{gherrit_pr_id_re, mark_removed, parse_metadata},
+    github::ClosePullRequest,
+    naming::BranchNaming,
+    reconcile::{CurrentPr, DesiredPr, PullRequestState, plan_update},
+    run_batched_graphql,
+    triage::Triage,
+};
+use crate::util;
+
+/// What a sync does with the open PR of a commit that was squashed or dropped
+/// from the middle of its stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub(crate) enum OrphanedPrPolicy {
+    /// Only warn about the PR.
+    Ignore,
+    /// Note in the PR body that its commit was removed from the stack.
+    #[default]
+    Mark,
+    /// Mark the PR, then close it.
+    Close,
+}
+
+impl OrphanedPrPolicy {
+    const CONFIG_KEY: &str = "gherrit.orphanedPrPolicy";
+
+    /// Reads the repository's policy, defaulting to `Mark`.
+    pub(crate) fn from_config(repo: &util::Repo) -> Result<Self> {
+        repo.config_choice(Self::CONFIG_KEY)
+    }
+}
+
+/// Finds the open PRs that were in the stack when it was last pushed, but
+/// whose commits are no longer in `commits`.
+///
+/// The previous stack is recovered from the metadata links in the PR bodies,
+/// starting from the PRs in `prs`, which must not have been updated yet. A PR
+/// only counts as a former member of the stack if its own metadata links back
+/// to the PR that led to it; a commit that moved to another stack has been
+/// relinked there. Nor does the PR of a commit that is still on `branch_name`
+/// above `commits`, as after pushing only part of the branch.
+pub(super) async fn find(
+    repo: &util::Repo,
+    octocrab: &Octocrab,
+    branch_name: &str,
+    commits: &[Commit],
+    prs: &[PrState],
+) -> Result<Vec<PrState>> {
+    let mut seen: HashSet<String> = commits.iter().map(|commit| commit.branch.clone()).collect();
+    let mut orphans = Vec::new();
+
+    // Each link is the branch of a neighbour outside the stack, along with the
+    // branch of the PR it was reached from and whether the neighbour is its
+    // parent.
+    let links_of = |pr: &PrState| {
+        let metadata = pr.body.as_deref().and_then(parse_metadata);
+        let from = pr.head_branch.clone();
+        metadata
+            .into_iter()
+            .flat_map(|metadata| {
+                let parent = metadata.parent_branch().map(str::to_owned);
+                let child = metadata.child_branch().map(str::to_owned);
+                [(parent, true), (child, false)]
+            })
+            .filter_map(move |(branch, is_parent)| Some((branch?, from.clone(), is_parent)))
+    };
+    let mut links: Vec<_> = prs.iter().flat_map(links_of).collect();
+    while !links.is_empty() {
+        links.retain(|(branch, _, _)| seen.insert(branch.clone()));
+        let branches: Vec<String> = links.iter().map(|(branch, _, _)| branch.clone()).collect();
+        let candidates = batch_fetch_prs(repo, octocrab, &branches).await?;
+
+        let mut next = Vec::new();
+        for (branch, from, is_parent) in links {
+            let Some(candidate) = candidates.iter().find(|pr| pr.head_branch == branch) else {
+                continue;
+            };
+            let Some(metadata) = candidate.body.as_deref().and_then(parse_metadata) else {
+                continue;
+            };
+            let back = if is_parent { metadata.child_branch() } else { metadata.parent_branch() };
+            if candidate.state != PullRequestState::Open || back != Some(from.as_str()) {
+                continue;
+            }
+            next.extend(links_of(candidate));
+            orphans.push(candidate.clone());
+        }
+        links = next;
+    }
+
+    if !orphans.is_empty() {
+        let on_branch = gherrit_ids_on_branch(repo, branch_name)?;
+        let naming = BranchNaming::from_config(repo)?;
+        orphans.retain(|pr| {
+            naming.parse(&pr.head_branch).is_none_or(|gherrit_id| !on_branch.contains(gherrit_id))
+        });
+    }
+
+    orphans.sort_by_key(|pr| pr.number);
+    Ok(orphans)
+}
+
+/// The gherrit-pr-ids of the commits on `branch_name` that are not on the
+/// default branch.
+fn gherrit_ids_on_branch(repo: &util::Repo, branch_name: &str) -> Result<HashSet<String>> {
+    let default_branch = repo.find_default_branch_on_default_remote();
+    let default_ref = repo.rev_parse_single(format!("refs/heads/{default_branch}").as_str())?;
+    let branch = repo.rev_parse_single(format!("refs/heads/{branch_name}").as_str())?;
+    let commits = match repo.commits_between(default_ref, branch) {
+        Ok(commits) => commits,
+        Err(util::CommitsBetweenError::NotAncestor) => return Ok(HashSet::new()),
+        Err(util::CommitsBetweenError::Eyre(err)) => return Err(err),
+    };
+    commits
+        .iter()
+        .filter_map(|commit| {
+            let message = match commit.message_raw() {
+                Ok(message) => message.to_string(),
+                Err(err) => return Some(Err(err.into())),
+            };
+            let captures = gherrit_pr_id_re().captures_iter(&message).last()?;
+            Some(Ok(captures[1].to_string()))
+        })
+        .collect()
+}
+
+/// Applies `options.orphaned_prs` to `orphans`, as found by [`find`].
+pub(super) async fn resolve(
+    repo: &util::Repo,
+    octocrab: &Octocrab,
+    orphans: &[PrState],
+    options: SyncOptions,
+) -> Result<()> {
+    let remote = repo.default_remote()?;
+    let close = match options.orphaned_prs {
+        OrphanedPrPolicy::Ignore => {
+            for pr in orphans {
+                log::warn!(
+                    "PR #{} is no longer in the stack: {}",
+                    pr.number.green().bold(),
+                    remote.pr_url(pr.number)
+                );
+            }
+            return Ok(());
+        }
+        OrphanedPrPolicy::Mark => false,
+        OrphanedPrPolicy::Close => true,
+    };
+
+    let updates: Vec<_> = orphans
+        .iter()
+        .filter_map(|pr| {
+            let body = mark_removed(pr.body.as_deref().unwrap_or(""));
+            plan_update(
+                CurrentPr {
+                    node_id: &pr.node_id,
+                    title: pr.title.as_deref(),
+                    body: pr.body.as_deref(),
+                    base_branch: &pr.base_branch,
+                    is_draft: pr.is_draft,
+                    triage: &Triage::default(),
+                },
+                DesiredPr {
+                    title: pr.title.as_deref().unwrap_or(""),
+                    body: &body,
+                    base_branch: &pr.base_branch,
+                    draft: pr.is_draft,
+                    triage: &Triage::default(),
+                    rereview: &[],
+                },
+            )
+        })
+        .collect();
+    for pr in orphans {
+        let (number, url) = (pr.number, remote.pr_url(pr.number));
+        match (options.mode, close) {
+            (SyncMode::Apply, false) => {
+                log::info!("Marking PR #{} as removed from the stack: {url}", number.green().bold())
+            }
+            (SyncMode::Apply, true) => log::info!(
+                "Closing PR #{}, which was removed from the stack: {url}",
+                number.green().bold()
+            ),
+            (SyncMode::DryRun, false) => {
+                println!("Would mark PR #{number} {url} as removed from the stack")
+            }
+            (SyncMode::DryRun, true) => {
+                println!("Would close PR #{number} {url}, which was removed from the stack")
+            }
+        }
+    }
+    if options.mode == SyncMode::DryRun {
+        return Ok(());
+    }
+
+    if !updates.is_empty() {
+        batch_update_prs(octocrab, &remote, updates).await?;
+    }
+    if close {
+        let closes = orphans.iter().map(|pr| ClosePullRequest::new(pr.node_id.clone()));
+        run_batched_graphql(octocrab, closes).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum as _;
+
+    use super::*;
+
+    #[test]
+    fn parses_each_policy() {
+        for (policy, name) in [
+            (OrphanedPrPolicy::Ignore, "ignore"),
+            (OrphanedPrPolicy::Mark, "mark"),
+            (OrphanedPrPolicy::Close, "close"),
+        ] {
+            assert_eq!(util::choice_name(&policy), name);
+            assert_eq!(OrphanedPrPolicy::from_str(name, false), Ok(policy));
+        }
+        assert!(OrphanedPrPolicy::from_str("closed", false).is_err());
+        assert_eq!(OrphanedPrPolicy::default(), OrphanedPrPolicy::Mark);
+    }
+}
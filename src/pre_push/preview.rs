@@ -0,0 +1,273 @@
+use std::fmt::{self, Write};
+
+use super::{github::PullRequest as PrState, publication::PushPlan, reconcile::PrUpdate};
+
+/// The number of unchanged lines shown around each change in a body diff.
+const DIFF_CONTEXT_LINES: usize = 2;
+
+/// Renders the `git push` invocation for one push batch, one refspec or lease
+/// per line.
+pub(super) fn render_push(plan: &PushPlan) -> String {
+    let (command, refspecs) = plan.arguments.split_at(plan.arguments.len() - plan.refspecs().len());
+    let mut output = format!("Would run: git {} \\\n", command.join(" "));
+    refspecs.iter().enumerate().for_each(|(index, refspec)| {
+        let continuation = if index + 1 < refspecs.len() { " \\" } else { "" };
+        output.push_str(&format!("    {refspec}{continuation}\n"));
+    });
+    output
+}
+
+pub(super) fn render_creation(
+    head_branch: &str,
+    base_branch: &str,
+    title: &str,
+    draft: bool,
+) -> String {
+    let kind = if draft { "draft PR" } else { "PR" };
+    format!("Would create {kind} for {head_branch} (base: {base_branch}): {title}\n")
+}
+
+/// Renders the fields an update would change on `current`.
+pub(super) fn render_update(url: &str, current: &PrState, update: &PrUpdate) -> String {
+    let mut output = String::new();
+    write_update(&mut output, url, current, update)
+        .expect("writing a PR update to a string cannot fail");
+    output
+}
+
+fn write_update(
+    mut output: impl Write,
+    url: &str,
+    current: &PrState,
+    update: &PrUpdate,
+) -> fmt::Result {
+    writeln!(output, "Would update PR #{} {url}", current.number)?;
+    if let Some(title) = &update.title {
+        writeln!(output, "    title: {:?} -> {title:?}", current.title.as_deref().unwrap_or(""))?;
+    }
+    if let Some(base_branch) = &update.base_branch {
+        writeln!(output, "    base:  {} -> {base_branch}", current.base_branch)?;
+    }
+    if let Some(draft) = update.draft {
+        writeln!(output, "    draft: {} -> {draft}", current.is_draft)?;
+    }
+    let people = |logins: &[String]| logins.iter().map(|login| format!("@{login}")).collect();
+    for (field, added) in [
+        ("reviewers", people(&update.triage.reviewers)),
+        ("labels", update.triage.labels.clone()),
+        ("assignees", people(&update.triage.assignees)),
+        ("re-request review", people(&update.rereview)),
+    ] {
+        if !added.is_empty() {
+            writeln!(output, "    {field}: + {}", added.join(", "))?;
+        }
+    }
+    if let Some(body) = &update.body {
+        output.write_str("    body:\n")?;
+        write_diff(&mut output, &diff_lines(current.body.as_deref().unwrap_or(""), body))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Computes a minimal line diff from `old` to `new`.
+///
+/// Regenerated bodies usually differ only in a few lines, so the common prefix
+/// and suffix are trimmed before the quadratic longest-common-subsequence
+/// table is built.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(old, new)| old == new).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+
+    // `lengths[i * width + j]` is the length of the longest common subsequence
+    // of `old_changed[i..]` and `new_changed[j..]`.
+    let width = new_changed.len() + 1;
+    let mut lengths = vec![0; (old_changed.len() + 1) * width];
+    for i in (0..old_changed.len()).rev() {
+        for j in (0..new_changed.len()).rev() {
+            lengths[i * width + j] = if old_changed[i] == new_changed[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<_> = old[..prefix].iter().map(|line| DiffLine::Same(line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_changed.len() && j < new_changed.len() {
+        if old_changed[i] == new_changed[j] {
+            lines.push(DiffLine::Same(old_changed[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            lines.push(DiffLine::Removed(old_changed[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new_changed[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old_changed[i..].iter().map(|line| DiffLine::Removed(line)));
+    lines.extend(new_changed[j..].iter().map(|line| DiffLine::Added(line)));
+    lines.extend(old[old.len() - suffix..].iter().map(|line| DiffLine::Same(line)));
+    lines
+}
+
+/// Writes the changed lines of a diff with a little surrounding context,
+/// eliding longer runs of unchanged lines.
+fn write_diff(mut output: impl Write, lines: &[DiffLine<'_>]) -> fmt::Result {
+    let is_near_change = |index: usize| {
+        let start = index.saturating_sub(DIFF_CONTEXT_LINES);
+        let end = (index + DIFF_CONTEXT_LINES + 1).min(lines.len());
+        lines[start..end].iter().any(|line| !matches!(line, DiffLine::Same(_)))
+    };
+
+    let mut elided = false;
+    for (index, line) in lines.iter().enumerate() {
+        if !is_near_change(index) {
+            if !elided {
+                output.write_str("        ...\n")?;
+                elided = true;
+            }
+            continue;
+        }
+        elided = false;
+        match line {
+            DiffLine::Same(line) => writeln!(output, "          {line}")?,
+            DiffLine::Removed(line) => writeln!(output, "        - {line}")?,
+            DiffLine::Added(line) => writeln!(output, "        + {line}")?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pre_push::{publication::PushTarget, reconcile::PullRequestState, triage::Triage};
+
+    #[test]
+    fn diffs_only_the_changed_lines() {
+        use DiffLine::*;
+
+        assert_eq!(diff_lines("a\nb\nc", "a\nb\nc"), [Same("a"), Same("b"), Same("c")]);
+        assert_eq!(diff_lines("", "a"), [Added("a")]);
+        assert_eq!(diff_lines("a", ""), [Removed("a")]);
+        assert_eq!(
+            diff_lines("a\nb\nc\nd", "a\nB\nc\nd\ne"),
+            [Same("a"), Removed("b"), Added("B"), Same("c"), Same("d"), Added("e")]
+        );
+        assert_eq!(
+            diff_lines("x\na\nb", "a\nb\ny"),
+            [Removed("x"), Same("a"), Same("b"), Added("y")]
+        );
+        assert_eq!(diff_lines("a\r\nb\r\n", "a\nb\n"), [Same("a"), Same("b")]);
+    }
+
+    #[test]
+    fn renders_a_push_batch() {
+        let object_id = gix::ObjectId::from_bytes_or_panic(&[0x11; 20]);
+        let plan = crate::pre_push::publication::plan_push(
+            "origin",
+            crate::pre_push::RefLayout::Tags,
+            &[PushTarget {
+                object_id,
+                gherrit_id: "Gone",
+                branch: "Gone",
+                version: 2,
+                expected_remote_sha: "abc",
+            }],
+        );
+
+        insta::assert_snapshot!(render_push(&plan));
+    }
+
+    #[test]
+    fn renders_every_changed_field() {
+        let current = PrState {
+            number: 22,
+            node_id: "PR_22".to_string(),
+            title: Some("Old title".to_string()),
+            body: Some((1..=12).map(|line| format!("line {line}\n")).collect()),
+            base_branch: "Groot".to_string(),
+            head_branch: "Gmiddle".to_string(),
+            state: PullRequestState::Open,
+            is_draft: false,
+            review_decision: None,
+            is_in_merge_queue: false,
+        };
+        let update = PrUpdate {
+            node_id: "PR_22".to_string(),
+            title: Some("New \"title\"".to_string()),
+            body: Some(
+                (1..=12)
+                    .map(|line| match line {
+                        2 => "line two\n".to_string(),
+                        10 => String::new(),
+                        line => format!("line {line}\n"),
+                    })
+                    .collect(),
+            ),
+            base_branch: Some("main".to_string()),
+            draft: Some(true),
+            triage: Triage {
+                reviewers: vec!["alice".to_string(), "org/team".to_string()],
+                labels: vec!["perf".to_string()],
+                assignees: Vec::new(),
+            },
+            rereview: vec!["bob".to_string()],
+        };
+
+        insta::assert_snapshot!(render_update(
+            "https://github.com/octo/widgets/pull/22",
+            &current,
+            &update
+        ));
+    }
+
+    #[test]
+    fn omits_unchanged_fields() {
+        let current = PrState {
+            number: 7,
+            node_id: "PR_7".to_string(),
+            title: None,
+            body: None,
+            base_branch: "main".to_string(),
+            head_branch: "Gtip".to_string(),
+            state: PullRequestState::Open,
+            is_draft: true,
+            review_decision: None,
+            is_in_merge_queue: false,
+        };
+        let update = PrUpdate {
+            node_id: "PR_7".to_string(),
+            title: Some("Finish the stack".to_string()),
+            body: None,
+            base_branch: None,
+            draft: None,
+            triage: Triage::default(),
+            rereview: Vec::new(),
+        };
+
+        assert_eq!(
+            render_update("/pull/7", &current, &update),
+            "Would update PR #7 /pull/7\n    title: \"\" -> \"Finish the stack\"\n"
+        );
+    }
+}
@@ -1,7 +1,13 @@
-use std::slice;
+use std::{
+    collections::{BTreeMap, HashMap},
+    slice,
+};
 
+use color_eyre::eyre::Result;
 use gix::ObjectId;
 
+use super::RefLayout;
+
 // Windows command lines are limited to roughly 32 KiB. Each target contributes
 // about 200 characters of branch and tag refspecs, so 80 targets leave ample
 // headroom.
@@ -10,13 +16,124 @@ const PUSH_BATCH_LEN: usize = 80;
 // 15.5 KiB.
 const REMOTE_QUERY_BATCH_LEN: usize = 250;
 
+// `--porcelain` reports each rejected ref on standard output, so that a failed
+// push can be explained ref by ref.
+const PUSH_OPTIONS: [&str; 5] = ["push", "--quiet", "--porcelain", "--no-verify", "--atomic"];
+
+#[derive(Debug, Clone, Copy)]
 pub(super) struct PushTarget<'a> {
     pub object_id: ObjectId,
     pub gherrit_id: &'a str,
+    /// The managed branch, without `refs/heads/`.
+    pub branch: &'a str,
     pub version: usize,
     pub expected_remote_sha: &'a str,
 }
 
+/// The most recent version of a commit recorded by a version tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct LocalVersion {
+    pub version: usize,
+    pub object_id: ObjectId,
+}
+
+/// Every locally recorded version of the commits in a stack, indexed by
+/// gherrit-pr-id.
+///
+/// Repositories accumulate version refs for every commit ever pushed, so the
+/// index is built in a single pass over the layout's refs rather than one
+/// pass per commit.
+#[derive(Debug, Default)]
+pub(super) struct LocalVersions {
+    versions: HashMap<String, BTreeMap<usize, ObjectId>>,
+}
+
+impl LocalVersions {
+    /// Creates an empty index of the versions of `gherrit_ids`.
+    pub(super) fn new<'a>(gherrit_ids: impl IntoIterator<Item = &'a str>) -> Self {
+        let versions =
+            gherrit_ids.into_iter().map(|id| (id.to_string(), BTreeMap::new())).collect();
+        Self { versions }
+    }
+
+    /// Records the ref `name` if it is a version ref of an indexed commit.
+    ///
+    /// `peel` resolves the ref to the commit it records and is only called
+    /// for recorded refs.
+    pub(super) fn record(
+        &mut self,
+        layout: RefLayout,
+        name: &str,
+        peel: impl FnOnce() -> Result<ObjectId>,
+    ) -> Result<()> {
+        let Some((id, version)) = layout.parse_version_ref(name) else {
+            return Ok(());
+        };
+        if let Some(versions) = self.versions.get_mut(id) {
+            versions.insert(version, peel()?);
+        }
+        Ok(())
+    }
+
+    /// Every recorded version of a commit, in ascending version order.
+    pub(super) fn versions(&self, gherrit_id: &str) -> BTreeMap<usize, ObjectId> {
+        self.versions.get(gherrit_id).cloned().unwrap_or_default()
+    }
+
+    /// The latest recorded version of a commit, if any.
+    pub(super) fn latest(&self, gherrit_id: &str) -> Option<LocalVersion> {
+        let (&version, &object_id) = self.versions.get(gherrit_id)?.last_key_value()?;
+        Some(LocalVersion { version, object_id })
+    }
+}
+
+/// Picks the latest version of a commit from its local version tags and the
+/// ones published on the remote.
+///
+/// The remote is authoritative: local tags may be missing, for example in a
+/// fresh clone, and a version that both record is taken from the remote.
+pub(super) fn latest_version(
+    local: Option<LocalVersion>,
+    remote: Option<LocalVersion>,
+) -> Option<LocalVersion> {
+    match (local, remote) {
+        (Some(local), Some(remote)) if local.version > remote.version => Some(local),
+        (local, None) => local,
+        (_, remote) => remote,
+    }
+}
+
+/// Whether a commit needs a new version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum VersionDecision {
+    /// The latest version already records this exact commit, and the managed
+    /// branch still points to it.
+    Unchanged { version: usize },
+    /// The commit must be published as a new version.
+    Publish { version: usize },
+}
+
+/// Decides whether a commit must be published as a new version.
+///
+/// A commit's object ID covers its tree, parents, and message, so an equal
+/// object ID means that neither the commit nor anything below it in the stack
+/// has changed. The managed branch must also still point to the commit;
+/// otherwise, republishing repairs it.
+pub(super) fn decide_version(
+    object_id: ObjectId,
+    latest: Option<LocalVersion>,
+    remote_branch: Option<&str>,
+) -> VersionDecision {
+    let version = latest.map_or(0, |latest| latest.version);
+    let is_published = remote_branch == Some(object_id.to_string().as_str());
+    match latest {
+        Some(latest) if latest.object_id == object_id && is_published => {
+            VersionDecision::Unchanged { version }
+        }
+        _ => VersionDecision::Publish { version: version + 1 },
+    }
+}
+
 pub(super) struct PersistedTag {
     pub object_id: ObjectId,
     pub gherrit_id: String,
@@ -28,6 +145,13 @@ pub(super) struct PushPlan {
     pub persisted_tags: Vec<PersistedTag>,
 }
 
+impl PushPlan {
+    /// The refspecs and leases that follow the remote name.
+    pub fn refspecs(&self) -> &[String] {
+        &self.arguments[PUSH_OPTIONS.len() + 1..]
+    }
+}
+
 pub(super) fn push_batches<T>(items: &[T]) -> slice::Chunks<'_, T> {
     items.chunks(PUSH_BATCH_LEN)
 }
@@ -36,13 +160,13 @@ pub(super) fn remote_query_batches<T>(items: &[T]) -> slice::Chunks<'_, T> {
     items.chunks(REMOTE_QUERY_BATCH_LEN)
 }
 
-pub(super) fn plan_push(remote: &str, targets: &[PushTarget<'_>]) -> PushPlan {
+pub(super) fn plan_push(remote: &str, layout: RefLayout, targets: &[PushTarget<'_>]) -> PushPlan {
     assert!(!targets.is_empty(), "cannot plan an empty push");
     let refspecs = targets.iter().flat_map(|target| {
-        let branch = format!("refs/heads/{}", target.gherrit_id);
-        let tag = format!("refs/tags/gherrit/{}/v{}", target.gherrit_id, target.version);
+        let branch = format!("refs/heads/{}", target.branch);
+        let tag = layout.version_ref(target.gherrit_id, target.version);
         // Branch updates are leased against the observed remote value. A tag
-        // lease with an empty expected value requires that the version tag not
+        // lease with an empty expected value requires that the version ref not
         // exist, making it a lock rather than an overwrite.
         [
             format!("{}:{branch}", target.object_id),
@@ -51,11 +175,8 @@ pub(super) fn plan_push(remote: &str, targets: &[PushTarget<'_>]) -> PushPlan {
             format!("--force-with-lease={tag}:"),
         ]
     });
-    let arguments = ["push", "--quiet", "--no-verify", "--atomic", remote]
-        .into_iter()
-        .map(ToString::to_string)
-        .chain(refspecs)
-        .collect();
+    let arguments =
+        PUSH_OPTIONS.into_iter().chain([remote]).map(ToString::to_string).chain(refspecs).collect();
     let persisted_tags = targets
         .iter()
         .map(|target| PersistedTag {
@@ -68,6 +189,82 @@ pub(super) fn plan_push(remote: &str, targets: &[PushTarget<'_>]) -> PushPlan {
     PushPlan { arguments, persisted_tags }
 }
 
+/// A ref on the remote, as observed by `git ls-remote`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RemoteRef {
+    pub name: String,
+    pub object_id: String,
+}
+
+/// Plans the atomic deletion of `refs` from the remote.
+///
+/// Each deletion is leased against the observed object ID, so a ref that
+/// someone else has updated since is left alone and fails the whole push.
+pub(super) fn plan_deletion(remote: &str, refs: &[RemoteRef]) -> Vec<String> {
+    assert!(!refs.is_empty(), "cannot plan an empty deletion");
+    let refspecs = refs.iter().flat_map(|remote_ref| {
+        [
+            format!(":{}", remote_ref.name),
+            format!("--force-with-lease={}:{}", remote_ref.name, remote_ref.object_id),
+        ]
+    });
+    PUSH_OPTIONS.into_iter().chain([remote]).map(ToString::to_string).chain(refspecs).collect()
+}
+
+/// A remote ref to rename, keeping the object it points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RefMove {
+    pub from: RemoteRef,
+    pub to: String,
+    /// Whether `to` already points to the object, as after an interrupted
+    /// move.
+    pub done: bool,
+}
+
+/// Plans the atomic renaming of remote refs.
+///
+/// Creating a ref is leased on its absence and deleting one on its observed
+/// value, so a concurrent push makes the whole push fail.
+pub(super) fn plan_moves(remote: &str, moves: &[RefMove]) -> Vec<String> {
+    assert!(!moves.is_empty(), "cannot plan an empty move");
+    let refspecs = moves.iter().flat_map(|ref_move| {
+        let RefMove { from, to, done } = ref_move;
+        let create = (!done)
+            .then(|| [format!("{}:{to}", from.object_id), format!("--force-with-lease={to}:")]);
+        create.into_iter().flatten().chain([
+            format!(":{}", from.name),
+            format!("--force-with-lease={}:{}", from.name, from.object_id),
+        ])
+    });
+    PUSH_OPTIONS.into_iter().chain([remote]).map(ToString::to_string).chain(refspecs).collect()
+}
+
+/// A remote ref to return to its value before a publication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RefRestore {
+    pub name: String,
+    /// The object ID that the publication pushed.
+    pub published: String,
+    /// The object ID to restore, or `None` to delete the ref.
+    pub previous: Option<String>,
+}
+
+/// Plans the atomic restoration of remote refs.
+///
+/// Each restoration is leased against the published object ID, so a ref that
+/// someone else has updated since is left alone and fails the whole push.
+pub(super) fn plan_restores(remote: &str, restores: &[RefRestore]) -> Vec<String> {
+    assert!(!restores.is_empty(), "cannot plan an empty restoration");
+    let refspecs = restores.iter().flat_map(|restore| {
+        let RefRestore { name, published, previous } = restore;
+        [
+            format!("{}:{name}", previous.as_deref().unwrap_or_default()),
+            format!("--force-with-lease={name}:{published}"),
+        ]
+    });
+    PUSH_OPTIONS.into_iter().chain([remote]).map(ToString::to_string).chain(refspecs).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,30 +303,120 @@ mod tests {
         }
     }
 
+    #[test]
+    fn keeps_the_version_of_an_unchanged_published_commit() {
+        let latest = LocalVersion { version: 3, object_id: object_id(0x11) };
+        let published = object_id(0x11).to_string();
+
+        assert_eq!(
+            decide_version(object_id(0x11), Some(latest), Some(&published)),
+            VersionDecision::Unchanged { version: 3 }
+        );
+    }
+
+    #[test]
+    fn prefers_the_remote_version_unless_the_local_one_is_newer() {
+        let version = |version, byte| Some(LocalVersion { version, object_id: object_id(byte) });
+
+        assert_eq!(latest_version(None, None), None);
+        assert_eq!(latest_version(version(2, 0x11), None), version(2, 0x11));
+        assert_eq!(latest_version(None, version(4, 0x22)), version(4, 0x22));
+        assert_eq!(latest_version(version(2, 0x11), version(4, 0x22)), version(4, 0x22));
+        assert_eq!(latest_version(version(4, 0x11), version(4, 0x22)), version(4, 0x22));
+        assert_eq!(latest_version(version(5, 0x11), version(4, 0x22)), version(5, 0x11));
+    }
+
+    #[test]
+    fn indexes_the_stack_from_a_large_ref_set_in_one_pass() {
+        const STACK_LEN: usize = 150;
+        const OTHER_IDS: usize = 20_000;
+        const VERSIONS: usize = 5;
+
+        let layout = RefLayout::Tags;
+        let stack: Vec<String> = (0..STACK_LEN).map(|i| format!("Stack{i:05}")).collect();
+        let mut refs: Vec<String> = (0..OTHER_IDS)
+            .flat_map(|i| (1..=VERSIONS).map(move |v| layout.version_ref(&format!("Old{i:05}"), v)))
+            .collect();
+        // The stack's commits have as many versions as their position.
+        for (position, id) in stack.iter().enumerate() {
+            refs.extend((1..=position).map(|version| layout.version_ref(id, version)));
+        }
+        refs.push(RefLayout::Refs.version_ref(&stack[0], 9));
+        refs.sort();
+
+        let mut index = LocalVersions::new(stack.iter().map(String::as_str));
+        let mut peeled = 0;
+        for name in &refs {
+            index
+                .record(layout, name, || {
+                    peeled += 1;
+                    Ok(object_id(0x11))
+                })
+                .unwrap();
+        }
+
+        assert_eq!(peeled, STACK_LEN * (STACK_LEN - 1) / 2, "Only the stack's refs are peeled");
+        assert_eq!(index.latest(&stack[0]), None);
+        for (position, id) in stack.iter().enumerate().skip(1) {
+            let latest = LocalVersion { version: position, object_id: object_id(0x11) };
+            assert_eq!(index.latest(id), Some(latest));
+            assert_eq!(index.versions(id).len(), position);
+        }
+        assert_eq!(index.latest("Old00000"), None, "Only the stack is indexed");
+    }
+
+    #[test]
+    fn publishes_every_other_combination_as_the_next_version() {
+        let same = object_id(0x11);
+        let other = object_id(0x22);
+        let latest = |object_id| Some(LocalVersion { version: 3, object_id });
+
+        for (latest, remote_branch, expected) in [
+            (None, None, 1),
+            (None, Some(same.to_string()), 1),
+            (latest(same), None, 4),
+            (latest(same), Some(other.to_string()), 4),
+            (latest(other), Some(same.to_string()), 4),
+            (latest(other), Some(other.to_string()), 4),
+        ] {
+            assert_eq!(
+                decide_version(same, latest, remote_branch.as_deref()),
+                VersionDecision::Publish { version: expected },
+                "latest={latest:?}, remote_branch={remote_branch:?}"
+            );
+        }
+    }
+
     #[test]
     fn plans_atomic_branch_and_tag_leases() {
         let targets = [
             PushTarget {
                 object_id: object_id(0x11),
                 gherrit_id: "Gone",
+                branch: "Gone",
                 version: 2,
                 expected_remote_sha: "abc123",
             },
             PushTarget {
                 object_id: object_id(0x22),
                 gherrit_id: "Gtwo",
+                branch: "stack/Gtwo",
                 version: 1,
                 expected_remote_sha: "",
             },
         ];
 
-        let plan = plan_push("origin", &targets);
+        let plan = plan_push("origin", RefLayout::Tags, &targets);
+        let refs_plan = plan_push("origin", RefLayout::Refs, &targets);
+        assert_eq!(refs_plan.arguments[8], format!("{}:refs/gherrit/Gone/v2", object_id(0x11)));
+        assert_eq!(refs_plan.arguments[9], "--force-with-lease=refs/gherrit/Gone/v2:");
 
         assert_eq!(
             plan.arguments,
             [
                 "push".to_string(),
                 "--quiet".to_string(),
+                "--porcelain".to_string(),
                 "--no-verify".to_string(),
                 "--atomic".to_string(),
                 "origin".to_string(),
@@ -137,12 +424,13 @@ mod tests {
                 "--force-with-lease=refs/heads/Gone:abc123".to_string(),
                 format!("{}:refs/tags/gherrit/Gone/v2", object_id(0x11)),
                 "--force-with-lease=refs/tags/gherrit/Gone/v2:".to_string(),
-                format!("{}:refs/heads/Gtwo", object_id(0x22)),
-                "--force-with-lease=refs/heads/Gtwo:".to_string(),
+                format!("{}:refs/heads/stack/Gtwo", object_id(0x22)),
+                "--force-with-lease=refs/heads/stack/Gtwo:".to_string(),
                 format!("{}:refs/tags/gherrit/Gtwo/v1", object_id(0x22)),
                 "--force-with-lease=refs/tags/gherrit/Gtwo/v1:".to_string(),
             ]
         );
+        assert_eq!(plan.refspecs(), &plan.arguments[6..]);
         assert_eq!(plan.persisted_tags.len(), 2);
         assert_eq!(plan.persisted_tags[0].object_id, object_id(0x11));
         assert_eq!(plan.persisted_tags[0].gherrit_id, "Gone");
@@ -151,4 +439,73 @@ mod tests {
         assert_eq!(plan.persisted_tags[1].gherrit_id, "Gtwo");
         assert_eq!(plan.persisted_tags[1].version, 1);
     }
+
+    #[test]
+    fn plans_leased_deletions() {
+        let refs = [
+            RemoteRef { name: "refs/heads/Gone".to_string(), object_id: "abc123".to_string() },
+            RemoteRef {
+                name: "refs/tags/gherrit/Gone/v1".to_string(),
+                object_id: "def456".to_string(),
+            },
+        ];
+        assert_eq!(
+            plan_deletion("origin", &refs),
+            [
+                "push",
+                "--quiet",
+                "--porcelain",
+                "--no-verify",
+                "--atomic",
+                "origin",
+                ":refs/heads/Gone",
+                "--force-with-lease=refs/heads/Gone:abc123",
+                ":refs/tags/gherrit/Gone/v1",
+                "--force-with-lease=refs/tags/gherrit/Gone/v1:def456",
+            ]
+        );
+    }
+
+    #[test]
+    fn plans_leased_moves() {
+        let tag = |version: usize| RemoteRef {
+            name: format!("refs/tags/gherrit/Gone/v{version}"),
+            object_id: format!("abc{version}"),
+        };
+        let moves = [
+            RefMove { from: tag(1), to: "refs/gherrit/Gone/v1".to_string(), done: false },
+            RefMove { from: tag(2), to: "refs/gherrit/Gone/v2".to_string(), done: true },
+        ];
+        assert_eq!(
+            plan_moves("origin", &moves)[6..],
+            [
+                "abc1:refs/gherrit/Gone/v1",
+                "--force-with-lease=refs/gherrit/Gone/v1:",
+                ":refs/tags/gherrit/Gone/v1",
+                "--force-with-lease=refs/tags/gherrit/Gone/v1:abc1",
+                ":refs/tags/gherrit/Gone/v2",
+                "--force-with-lease=refs/tags/gherrit/Gone/v2:abc2",
+            ]
+        );
+    }
+
+    #[test]
+    fn plans_leased_restores() {
+        let restore = |name: &str, previous: Option<&str>| RefRestore {
+            name: name.to_string(),
+            published: "abc2".to_string(),
+            previous: previous.map(ToString::to_string),
+        };
+        let restores =
+            [restore("refs/heads/Gone", Some("abc1")), restore("refs/tags/gherrit/Gone/v2", None)];
+        assert_eq!(
+            plan_restores("origin", &restores)[6..],
+            [
+                "abc1:refs/heads/Gone",
+                "--force-with-lease=refs/heads/Gone:abc2",
+                ":refs/tags/gherrit/Gone/v2",
+                "--force-with-lease=refs/tags/gherrit/Gone/v2:abc2",
+            ]
+        );
+    }
 }
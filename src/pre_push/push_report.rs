@@ -0,0 +1,244 @@
+use std::fmt;
+
+use super::{RefLayout, publication::PushTarget};
+
+/// What `git push --porcelain` reported for one ref that it did not update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RefRejection {
+    /// The fully qualified name of the remote ref.
+    pub name: String,
+    pub reason: RejectionReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum RejectionReason {
+    /// The ref's `--force-with-lease` expectation did not hold.
+    StaleLease,
+    /// Another ref of the atomic push failed, so this one was not attempted.
+    AtomicPushFailed,
+    /// The remote refused the ref, for example by a protected-branch rule.
+    RemoteRejected(String),
+    /// Git refused the ref for any other reason.
+    Rejected(String),
+}
+
+/// Parses the rejected refs from `git push --porcelain` standard output.
+///
+/// Each ref is reported as `<flag>\t<from>:<to>\t<summary>`, where a flag of
+/// `!` marks a rejection and the summary reads `[rejected] (<reason>)` or
+/// `[remote rejected] (<reason>)`. Every other line is ignored.
+pub(super) fn parse_rejections(stdout: &str) -> Vec<RefRejection> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let (Some("!"), Some(refspec), Some(summary), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return None;
+            };
+            let (_, name) = refspec.split_once(':')?;
+            let (is_remote, reason) = match summary.strip_prefix("[remote rejected] ") {
+                Some(reason) => (true, reason),
+                None => (false, summary.strip_prefix("[rejected] ").unwrap_or(summary)),
+            };
+            let reason =
+                reason.strip_prefix('(').and_then(|r| r.strip_suffix(')')).unwrap_or(reason);
+            let reason = match (is_remote, reason) {
+                (false, "stale info") => RejectionReason::StaleLease,
+                (false, "atomic push failed") | (true, "atomic transaction failed") => {
+                    RejectionReason::AtomicPushFailed
+                }
+                (true, reason) => RejectionReason::RemoteRejected(reason.to_string()),
+                (false, reason) => RejectionReason::Rejected(reason.to_string()),
+            };
+            Some(RefRejection { name: name.to_string(), reason })
+        })
+        .collect()
+}
+
+/// Why one commit of a failed push was not published.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum PushFailure<'a> {
+    /// The version tag already exists: another push published it first.
+    VersionTaken { gherrit_id: &'a str, version: usize },
+    /// The managed branch changed on the remote since it was observed.
+    StaleBranch { gherrit_id: &'a str, branch: &'a str },
+    /// The remote refused a ref, for example by a protected-branch rule.
+    Refused { gherrit_id: &'a str, name: String, reason: String },
+    /// Git rejected a ref for another reason.
+    Rejected { gherrit_id: &'a str, name: String, reason: String },
+}
+
+impl<'a> PushFailure<'a> {
+    /// The gherrit-pr-id of the commit that was not published.
+    pub(super) fn gherrit_id(&self) -> &'a str {
+        match self {
+            Self::VersionTaken { gherrit_id, .. }
+            | Self::StaleBranch { gherrit_id, .. }
+            | Self::Refused { gherrit_id, .. }
+            | Self::Rejected { gherrit_id, .. } => gherrit_id,
+        }
+    }
+
+    /// Whether the failure is resolved by publishing the next version.
+    pub(super) fn is_version_taken(&self) -> bool {
+        matches!(self, Self::VersionTaken { .. })
+    }
+}
+
+impl fmt::Display for PushFailure<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VersionTaken { gherrit_id, version } => write!(
+                formatter,
+                "{gherrit_id}: version v{version} already exists on the remote; another push published it first"
+            ),
+            Self::StaleBranch { gherrit_id, branch } => write!(
+                formatter,
+                "{gherrit_id}: managed branch {branch} changed on the remote since it was observed"
+            ),
+            Self::Refused { gherrit_id, name, reason } => write!(
+                formatter,
+                "{gherrit_id}: the remote refused {name} ({reason}); a protected-branch rule or server hook may cover it"
+            ),
+            Self::Rejected { gherrit_id, name, reason } => {
+                write!(formatter, "{gherrit_id}: {name} was rejected ({reason})")
+            }
+        }
+    }
+}
+
+/// Attributes each rejection of a push of `targets` to the commit whose
+/// branch or version ref it names.
+///
+/// Refs that failed only because the push was atomic are omitted, as are refs
+/// that no target pushed.
+pub(super) fn diagnose<'a>(
+    layout: RefLayout,
+    targets: &[PushTarget<'a>],
+    rejections: &[RefRejection],
+) -> Vec<PushFailure<'a>> {
+    rejections
+        .iter()
+        .filter_map(|rejection| {
+            let name = rejection.name.as_str();
+            let (target, is_tag) = targets.iter().find_map(|target| {
+                if name.strip_prefix("refs/heads/") == Some(target.branch) {
+                    Some((target, false))
+                } else {
+                    (name == layout.version_ref(target.gherrit_id, target.version))
+                        .then_some((target, true))
+                }
+            })?;
+            let gherrit_id = target.gherrit_id;
+            Some(match &rejection.reason {
+                RejectionReason::AtomicPushFailed => return None,
+                RejectionReason::StaleLease if is_tag => {
+                    PushFailure::VersionTaken { gherrit_id, version: target.version }
+                }
+                RejectionReason::StaleLease => {
+                    PushFailure::StaleBranch { gherrit_id, branch: target.branch }
+                }
+                RejectionReason::RemoteRejected(reason) => PushFailure::Refused {
+                    gherrit_id,
+                    name: name.to_string(),
+                    reason: reason.clone(),
+                },
+                RejectionReason::Rejected(reason) => PushFailure::Rejected {
+                    gherrit_id,
+                    name: name.to_string(),
+                    reason: reason.clone(),
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use gix::ObjectId;
+
+    use super::*;
+
+    const OBJECT_ID: &str = "813d5c95432729b434160f31397f8b4815ca1484";
+
+    fn rejection(name: &str, reason: RejectionReason) -> RefRejection {
+        RefRejection { name: name.to_string(), reason }
+    }
+
+    #[test]
+    fn parses_rejected_refs_from_porcelain_output() {
+        let stdout = format!(
+            "To ../remote.git\n\
+             !\t{OBJECT_ID}:refs/heads/Gone\t[rejected] (atomic push failed)\n\
+             !\t{OBJECT_ID}:refs/tags/gherrit/Gone/v1\t[rejected] (stale info)\n\
+             !\t{OBJECT_ID}:refs/heads/Gtwo\t[remote rejected] (protected branch hook declined)\n\
+             !\t{OBJECT_ID}:refs/tags/gherrit/Gtwo/v1\t[remote rejected] (atomic transaction failed)\n\
+             !\t{OBJECT_ID}:refs/heads/Gthree\t[rejected] (non-fast-forward)\n\
+             *\t{OBJECT_ID}:refs/tags/gherrit/Gthree/v1\t[new tag]\n\
+             Done\n"
+        );
+        assert_eq!(
+            parse_rejections(&stdout),
+            [
+                rejection("refs/heads/Gone", RejectionReason::AtomicPushFailed),
+                rejection("refs/tags/gherrit/Gone/v1", RejectionReason::StaleLease),
+                rejection(
+                    "refs/heads/Gtwo",
+                    RejectionReason::RemoteRejected("protected branch hook declined".to_string())
+                ),
+                rejection("refs/tags/gherrit/Gtwo/v1", RejectionReason::AtomicPushFailed),
+                rejection(
+                    "refs/heads/Gthree",
+                    RejectionReason::Rejected("non-fast-forward".to_string())
+                ),
+            ]
+        );
+        assert!(parse_rejections("Done\n").is_empty());
+        assert!(parse_rejections("").is_empty());
+    }
+
+    #[test]
+    fn attributes_rejections_to_the_pushed_commits() {
+        let target = |gherrit_id, branch, version| PushTarget {
+            object_id: ObjectId::from_hex(OBJECT_ID.as_bytes()).unwrap(),
+            gherrit_id,
+            branch,
+            version,
+            expected_remote_sha: "",
+        };
+        let targets = [target("Gone", "Gone", 2), target("Gtwo", "stack/Gtwo", 1)];
+        let rejections = [
+            rejection("refs/tags/gherrit/Gone/v2", RejectionReason::StaleLease),
+            rejection("refs/heads/Gone", RejectionReason::AtomicPushFailed),
+            rejection("refs/heads/stack/Gtwo", RejectionReason::StaleLease),
+            rejection("refs/tags/gherrit/Gtwo/v1", RejectionReason::RemoteRejected("hook".into())),
+            rejection("refs/heads/unrelated", RejectionReason::StaleLease),
+        ];
+
+        let failures = diagnose(RefLayout::Tags, &targets, &rejections);
+        assert_eq!(
+            failures,
+            [
+                PushFailure::VersionTaken { gherrit_id: "Gone", version: 2 },
+                PushFailure::StaleBranch { gherrit_id: "Gtwo", branch: "stack/Gtwo" },
+                PushFailure::Refused {
+                    gherrit_id: "Gtwo",
+                    name: "refs/tags/gherrit/Gtwo/v1".to_string(),
+                    reason: "hook".to_string(),
+                },
+            ]
+        );
+        assert!(failures[0].is_version_taken() && !failures[1].is_version_taken());
+        assert_eq!(
+            failures.iter().map(PushFailure::gherrit_id).collect::<Vec<_>>(),
+            ["Gone", "Gtwo", "Gtwo"]
+        );
+        assert_eq!(
+            failures[1].to_string(),
+            "Gtwo: managed branch stack/Gtwo changed on the remote since it was observed"
+        );
+        assert!(diagnose(RefLayout::Refs, &targets, &rejections[..1]).is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use color_eyre::eyre::{Result, bail, eyre};
+use gix::ObjectId;
+
+/// One `<local ref> <local sha> <remote ref> <remote sha>` line that Git
+/// writes to the `pre-push` hook's stdin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct PushedRef<'a> {
+    /// The source as the user spelled it: a full ref name such as
+    /// `refs/heads/feature`, an expression such as `HEAD~2`, or `(delete)`.
+    pub local_ref: &'a str,
+    pub local_oid: ObjectId,
+    pub remote_ref: &'a str,
+}
+
+impl PushedRef<'_> {
+    fn is_deletion(&self) -> bool {
+        self.local_oid.is_null()
+    }
+}
+
+pub(super) fn parse_pushed_refs(input: &str) -> Result<Vec<PushedRef<'_>>> {
+    input
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let malformed = || eyre!("Git passed a malformed ref to the pre-push hook: {line:?}");
+            let mut fields = line.split(' ');
+            let (Some(local_ref), Some(local_oid), Some(remote_ref), Some(_), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(malformed());
+            };
+            let local_oid = ObjectId::from_hex(local_oid.as_bytes()).map_err(|_| malformed())?;
+            Ok(PushedRef { local_ref, local_oid, remote_ref })
+        })
+        .collect()
+}
+
+/// Returns each local branch whose stack is being pushed, with the pushed tip.
+///
+/// A pushed local branch names its own stack. Any other source, such as
+/// `HEAD~2`, names the stack of the local branch it is pushed to, which is how
+/// part of a public stack is published. Deletions, and pushes of anything that
+/// is not a local branch, publish no stack.
+pub(super) fn stacks_to_sync<'a>(
+    pushed: &[PushedRef<'a>],
+    is_local_branch: impl Fn(&str) -> bool,
+) -> Result<Vec<(&'a str, ObjectId)>> {
+    let mut stacks: Vec<(&str, ObjectId)> = Vec::new();
+    for pushed in pushed.iter().filter(|pushed| !pushed.is_deletion()) {
+        let branch = match pushed.local_ref.strip_prefix("refs/heads/") {
+            Some(branch) => branch,
+            None => match pushed.remote_ref.strip_prefix("refs/heads/") {
+                Some(branch) if is_local_branch(branch) => branch,
+                _ => continue,
+            },
+        };
+        match stacks.iter().find(|(existing, _)| *existing == branch) {
+            None => stacks.push((branch, pushed.local_oid)),
+            Some((_, tip)) if *tip == pushed.local_oid => {}
+            Some(_) => bail!("Cannot push two different tips of branch '{branch}' at once"),
+        }
+    }
+    Ok(stacks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oid(byte: u8) -> ObjectId {
+        ObjectId::from_bytes_or_panic(&[byte; 20])
+    }
+
+    fn line(local_ref: &str, local_oid: ObjectId, remote_ref: &str) -> String {
+        format!("{local_ref} {local_oid} {remote_ref} {}\n", oid(0))
+    }
+
+    #[test]
+    fn parses_each_pushed_ref() {
+        let input = [
+            line("refs/heads/feature", oid(0x11), "refs/heads/feature"),
+            line("HEAD~2", oid(0x22), "refs/heads/partial"),
+            line("(delete)", oid(0), "refs/heads/gone"),
+        ]
+        .concat();
+
+        assert_eq!(
+            parse_pushed_refs(&input).unwrap(),
+            [
+                PushedRef {
+                    local_ref: "refs/heads/feature",
+                    local_oid: oid(0x11),
+                    remote_ref: "refs/heads/feature",
+                },
+                PushedRef {
+                    local_ref: "HEAD~2",
+                    local_oid: oid(0x22),
+                    remote_ref: "refs/heads/partial",
+                },
+                PushedRef {
+                    local_ref: "(delete)",
+                    local_oid: oid(0),
+                    remote_ref: "refs/heads/gone"
+                },
+            ]
+        );
+        assert_eq!(parse_pushed_refs("").unwrap(), []);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for input in ["refs/heads/feature", "refs/heads/a xyz refs/heads/a 0", "a b c d e"] {
+            assert!(parse_pushed_refs(input).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn resolves_the_stack_of_each_pushed_ref() {
+        let input = [
+            line("refs/heads/feature", oid(0x11), "refs/heads/renamed"),
+            line("HEAD~2", oid(0x22), "refs/heads/partial"),
+            line("HEAD", oid(0x33), "refs/heads/remote-only"),
+            line("refs/tags/v1", oid(0x44), "refs/tags/v1"),
+            line("(delete)", oid(0), "refs/heads/partial"),
+            line("refs/heads/feature", oid(0x11), "refs/heads/feature"),
+        ]
+        .concat();
+        let pushed = parse_pushed_refs(&input).unwrap();
+
+        let stacks = stacks_to_sync(&pushed, |branch| branch != "remote-only").unwrap();
+
+        assert_eq!(stacks, [("feature", oid(0x11)), ("partial", oid(0x22))]);
+    }
+
+    #[test]
+    fn rejects_two_tips_for_one_stack() {
+        let input = [
+            line("refs/heads/feature", oid(0x11), "refs/heads/feature"),
+            line("HEAD~1", oid(0x22), "refs/heads/feature"),
+        ]
+        .concat();
+        let pushed = parse_pushed_refs(&input).unwrap();
+
+        assert!(stacks_to_sync(&pushed, |_| true).is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use serde::Deserialize;
 
+use super::triage::Triage;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub(super) enum PullRequestState {
@@ -8,6 +10,15 @@ pub(super) enum PullRequestState {
     Merged,
 }
 
+/// How a PR's reviews decide on it, for a base branch that requires reviews.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(super) enum ReviewDecision {
+    Approved,
+    ChangesRequested,
+    ReviewRequired,
+}
+
 /// A PR lifecycle observation that forbids mutation.
 ///
 /// `Open` is unrepresentable, so consumers do not rely on a field invariant.
@@ -69,9 +80,11 @@ pub(super) fn ensure_pull_requests_open(
 #[derive(Debug, PartialEq, Eq)]
 pub(super) struct StackEntry<T> {
     pub(super) item: T,
+    /// The branch of the parent, or the stack's base branch.
     pub(super) base_branch: String,
     pub(super) parent_id: Option<String>,
     pub(super) child_id: Option<String>,
+    pub(super) child_branch: Option<String>,
 }
 
 /// Derives stack topology from items ordered from base to head.
@@ -79,23 +92,26 @@ pub(super) fn link_stack<T>(
     base_branch: &str,
     items: impl IntoIterator<Item = T>,
     mut id_of: impl FnMut(&T) -> String,
+    mut branch_of: impl FnMut(&T) -> String,
 ) -> Vec<StackEntry<T>> {
     let mut items = items
         .into_iter()
         .map(|item| {
             let id = id_of(&item);
-            (item, id)
+            let branch = branch_of(&item);
+            (item, id, branch)
         })
         .peekable();
-    let mut previous_id = None;
+    let mut previous = None;
 
     std::iter::from_fn(|| {
-        let (item, id) = items.next()?;
-        let child_id = items.peek().map(|(_, id)| id.clone());
-        let parent_id = previous_id.replace(id);
-        let base_branch = parent_id.as_deref().unwrap_or(base_branch).to_owned();
+        let (item, id, branch) = items.next()?;
+        let (child_id, child_branch) =
+            items.peek().map(|(_, id, branch)| (id.clone(), branch.clone())).unzip();
+        let (parent_id, parent_branch) = previous.replace((id, branch)).unzip();
+        let base_branch = parent_branch.unwrap_or_else(|| base_branch.to_owned());
 
-        Some(StackEntry { item, base_branch, parent_id, child_id })
+        Some(StackEntry { item, base_branch, parent_id, child_id, child_branch })
     })
     .collect()
 }
@@ -106,6 +122,9 @@ pub(super) struct CurrentPr<'a> {
     pub(super) title: Option<&'a str>,
     pub(super) body: Option<&'a str>,
     pub(super) base_branch: &'a str,
+    pub(super) is_draft: bool,
+    /// The reviewers, labels, and assignees recorded in the PR's metadata.
+    pub(super) triage: &'a Triage,
 }
 
 /// Metadata derived from a local commit and its stack position.
@@ -113,10 +132,14 @@ pub(super) struct DesiredPr<'a> {
     pub(super) title: &'a str,
     pub(super) body: &'a str,
     pub(super) base_branch: &'a str,
+    pub(super) draft: bool,
+    pub(super) triage: &'a Triage,
+    /// The previous reviewers to ask to review the PR again.
+    pub(super) rereview: &'a [String],
 }
 
 /// The fields that must be changed to reconcile a PR.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct PrUpdate {
     /// The global node ID of the PR to update.
     pub(super) node_id: String,
@@ -125,6 +148,21 @@ pub(super) struct PrUpdate {
     // Omitting an unchanged base branch is required for PRs in the merge queue:
     // GitHub rejects even a no-op base update for those PRs. See #271.
     pub(super) base_branch: Option<String>,
+    /// Whether the PR must become a draft (`true`) or ready for review
+    /// (`false`). GitHub changes this with dedicated mutations rather than
+    /// `updatePullRequest`.
+    pub(super) draft: Option<bool>,
+    /// The reviewers, labels, and assignees to add.
+    pub(super) triage: Triage,
+    /// The logins of the previous reviewers to ask to review the PR again.
+    pub(super) rereview: Vec<String>,
+}
+
+impl PrUpdate {
+    /// Whether the update changes the title, body, or base branch.
+    pub(super) fn changes_metadata(&self) -> bool {
+        self.title.is_some() || self.body.is_some() || self.base_branch.is_some()
+    }
 }
 
 /// Returns the minimal update needed to make `current` match `desired`.
@@ -136,13 +174,24 @@ pub(super) fn plan_update(current: CurrentPr<'_>, desired: DesiredPr<'_>) -> Opt
         .then(|| desired.body.to_string());
     let base_branch =
         (current.base_branch != desired.base_branch).then(|| desired.base_branch.to_string());
+    let draft = (current.is_draft != desired.draft).then_some(desired.draft);
+    let triage = desired.triage.added_since(current.triage);
+    let rereview = desired.rereview.to_vec();
 
-    (title.is_some() || body.is_some() || base_branch.is_some()).then(|| PrUpdate {
+    let update = PrUpdate {
         node_id: current.node_id.to_string(),
         title,
         body,
         base_branch,
-    })
+        draft,
+        triage,
+        rereview,
+    };
+    (update.changes_metadata()
+        || update.draft.is_some()
+        || !update.triage.is_empty()
+        || !update.rereview.is_empty())
+    .then_some(update)
 }
 
 fn normalize_body(body: &str) -> String {
@@ -153,6 +202,9 @@ fn normalize_body(body: &str) -> String {
 mod tests {
     use super::*;
 
+    const NO_TRIAGE: &Triage =
+        &Triage { reviewers: Vec::new(), labels: Vec::new(), assignees: Vec::new() };
+
     const PR_STATES: [PullRequestState; 3] =
         [PullRequestState::Open, PullRequestState::Closed, PullRequestState::Merged];
 
@@ -220,7 +272,9 @@ mod tests {
     }
 
     fn link_ids(base_branch: &str, ids: &[&str]) -> Vec<StackEntry<String>> {
-        link_stack(base_branch, ids.iter().copied().map(str::to_owned), Clone::clone)
+        link_stack(base_branch, ids.iter().copied().map(str::to_owned), Clone::clone, |id| {
+            format!("{}-branch", id.to_lowercase())
+        })
     }
 
     #[test]
@@ -237,6 +291,7 @@ mod tests {
                 base_branch: "main".to_string(),
                 parent_id: None,
                 child_id: None,
+                child_branch: None,
             }]
         );
     }
@@ -251,18 +306,21 @@ mod tests {
                     base_branch: "main".to_string(),
                     parent_id: None,
                     child_id: Some("B".to_string()),
+                    child_branch: Some("b-branch".to_string()),
                 },
                 StackEntry {
                     item: "B".to_string(),
-                    base_branch: "A".to_string(),
+                    base_branch: "a-branch".to_string(),
                     parent_id: Some("A".to_string()),
                     child_id: Some("C".to_string()),
+                    child_branch: Some("c-branch".to_string()),
                 },
                 StackEntry {
                     item: "C".to_string(),
-                    base_branch: "B".to_string(),
+                    base_branch: "b-branch".to_string(),
                     parent_id: Some("B".to_string()),
                     child_id: None,
+                    child_branch: None,
                 },
             ]
         );
@@ -277,6 +335,7 @@ mod tests {
                 base_branch: "release".to_string(),
                 parent_id: None,
                 child_id: None,
+                child_branch: None,
             }]
         );
     }
@@ -291,18 +350,21 @@ mod tests {
                     base_branch: "release".to_string(),
                     parent_id: None,
                     child_id: Some("A".to_string()),
+                    child_branch: Some("a-branch".to_string()),
                 },
                 StackEntry {
                     item: "A".to_string(),
-                    base_branch: "C".to_string(),
+                    base_branch: "c-branch".to_string(),
                     parent_id: Some("C".to_string()),
                     child_id: Some("B".to_string()),
+                    child_branch: Some("b-branch".to_string()),
                 },
                 StackEntry {
                     item: "B".to_string(),
-                    base_branch: "A".to_string(),
+                    base_branch: "a-branch".to_string(),
                     parent_id: Some("A".to_string()),
                     child_id: None,
+                    child_branch: None,
                 },
             ]
         );
@@ -310,13 +372,21 @@ mod tests {
 
     #[test]
     fn extracts_each_id_once() {
-        let mut calls = 0;
-        let _ = link_stack("main", ["A", "B", "C"], |id| {
-            calls += 1;
-            (*id).to_owned()
-        });
+        let (mut id_calls, mut branch_calls) = (0, 0);
+        let _ = link_stack(
+            "main",
+            ["A", "B", "C"],
+            |id| {
+                id_calls += 1;
+                (*id).to_owned()
+            },
+            |id| {
+                branch_calls += 1;
+                (*id).to_owned()
+            },
+        );
 
-        assert_eq!(calls, 3);
+        assert_eq!((id_calls, branch_calls), (3, 3));
     }
 
     fn current<'a>(
@@ -324,11 +394,18 @@ mod tests {
         body: Option<&'a str>,
         base_branch: &'a str,
     ) -> CurrentPr<'a> {
-        CurrentPr { node_id: "PR_node", title, body, base_branch }
+        CurrentPr {
+            node_id: "PR_node",
+            title,
+            body,
+            base_branch,
+            is_draft: false,
+            triage: NO_TRIAGE,
+        }
     }
 
     fn desired<'a>(title: &'a str, body: &'a str, base_branch: &'a str) -> DesiredPr<'a> {
-        DesiredPr { title, body, base_branch }
+        DesiredPr { title, body, base_branch, draft: false, triage: NO_TRIAGE, rereview: &[] }
     }
 
     fn update(
@@ -341,6 +418,9 @@ mod tests {
             title: title.map(ToString::to_string),
             body: body.map(ToString::to_string),
             base_branch: base_branch.map(ToString::to_string),
+            draft: None,
+            triage: Triage::default(),
+            rereview: Vec::new(),
         })
     }
 
@@ -426,4 +506,52 @@ mod tests {
         assert_eq!(update.title.as_deref(), Some("Title"));
         assert_eq!(update.base_branch, None);
     }
+
+    #[test]
+    fn reconciles_the_draft_state_independently() {
+        let draft = |is_draft, draft| {
+            plan_update(
+                CurrentPr { is_draft, ..current(Some("Title"), Some("Body"), "main") },
+                DesiredPr { draft, ..desired("Title", "Body", "main") },
+            )
+        };
+
+        assert_eq!(draft(true, true), None);
+        let to_draft = draft(false, true).unwrap();
+        assert_eq!(to_draft.draft, Some(true));
+        assert!(!to_draft.changes_metadata());
+        assert_eq!(draft(true, false).unwrap().draft, Some(false));
+        assert!(update(Some("Title"), None, None).unwrap().changes_metadata());
+    }
+
+    #[test]
+    fn requests_only_the_triage_added_since_the_recorded_one() {
+        let recorded = Triage { reviewers: vec!["alice".to_string()], ..Triage::default() };
+        let wanted =
+            Triage { reviewers: vec!["alice".to_string(), "bob".to_string()], ..Triage::default() };
+        let plan = |recorded, wanted| {
+            plan_update(
+                CurrentPr { triage: recorded, ..current(Some("Title"), Some("Body"), "main") },
+                DesiredPr { triage: wanted, ..desired("Title", "Body", "main") },
+            )
+        };
+
+        assert_eq!(plan(&wanted, &recorded), None, "Removing a trailer undoes nothing");
+        let update = plan(&recorded, &wanted).unwrap();
+        assert_eq!(update.triage.reviewers, ["bob"]);
+        assert!(!update.changes_metadata());
+    }
+
+    #[test]
+    fn rerequests_review_even_if_nothing_else_changed() {
+        let rereview = ["alice".to_string()];
+        let update = plan_update(
+            current(Some("Title"), Some("Body"), "main"),
+            DesiredPr { rereview: &rereview, ..desired("Title", "Body", "main") },
+        )
+        .unwrap();
+
+        assert_eq!(update.rereview, rereview);
+        assert!(!update.changes_metadata());
+    }
 }
@@ -1,52 +1,226 @@
-use std::collections::{HashMap, hash_map::Entry};
+use std::{
+    collections::{BTreeMap, HashMap, hash_map::Entry},
+    process::Output,
+};
 
 use color_eyre::eyre::{Context as _, Result, bail};
 use gix::ObjectId;
+use octocrab::Octocrab;
 
-use super::publication::remote_query_batches;
+use super::{
+    BranchNaming, Commit, PrState, RefLayout, fetch_stack_prs,
+    publication::{RemoteRef, remote_query_batches},
+};
 use crate::util::{self, CommandExt as _};
 
-/// Observes the managed branches relevant to the current stack.
-pub(super) fn observe_managed_branches(
-    repo: &util::Repo,
-    gherrit_ids: &[String],
-) -> Result<HashMap<String, String>> {
-    remote_query_batches(gherrit_ids).try_fold(HashMap::new(), |mut states, chunk| {
-        let mut arguments = vec!["ls-remote".to_string(), repo.default_remote_name()];
-        arguments.extend(chunk.iter().map(|id| format!("refs/heads/{id}")));
+/// The state of the remote that the push of a stack is planned against.
+#[derive(Debug, Default)]
+pub(super) struct RemoteState {
+    /// The object ID of each managed branch that exists, by name.
+    pub branches: HashMap<String, String>,
+    /// The published version refs of each gherrit-pr-id, in ascending version
+    /// order.
+    pub versions: BTreeMap<String, Vec<(usize, RemoteRef)>>,
+}
+
+impl RemoteState {
+    /// Observes the managed branches and published version refs of `commits`
+    /// with `git ls-remote`s that run at once.
+    ///
+    /// Both observations run to completion, so a failure of both reports the
+    /// branches' regardless of which finished first.
+    pub(super) async fn observe(
+        repo: &util::Repo,
+        commits: &[Commit],
+        layout: RefLayout,
+    ) -> Result<Self> {
+        let branches: Vec<String> = commits.iter().map(|c| c.branch.clone()).collect();
+        let gherrit_ids: Vec<String> = commits.iter().map(|c| c.gherrit_id.clone()).collect();
+        let (branches, versions) = tokio::join!(
+            observe_managed_branches(repo, &branches),
+            observe_published_versions(repo, layout, &gherrit_ids),
+        );
+        Ok(Self { branches: branches?, versions: versions? })
+    }
+
+    /// Resolves the managed branch of each of `commits` with
+    /// [`fetch_stack_prs`], which looks up their PRs, while observing the
+    /// published version refs.
+    ///
+    /// Both run to completion, like the observations of [`Self::observe`].
+    pub(super) async fn observe_stack(
+        repo: &util::Repo,
+        octocrab: &Octocrab,
+        commits: &mut [Commit],
+        layout: RefLayout,
+    ) -> Result<(Vec<PrState>, Self)> {
+        let gherrit_ids: Vec<String> = commits.iter().map(|c| c.gherrit_id.clone()).collect();
+        let (branches, versions) = tokio::join!(
+            fetch_stack_prs(repo, octocrab, commits),
+            observe_published_versions(repo, layout, &gherrit_ids),
+        );
+        let (prs, branches) = branches?;
+        Ok((prs, Self { branches, versions: versions? }))
+    }
+}
 
-        let output = util::cmd("git", arguments).checked_output()?;
+/// Starts one `git ls-remote` for each batch of `items`, all of which run at
+/// once, and returns each batch with a future of its output.
+///
+/// `patterns` lists the patterns that an item's refs match.
+pub(super) fn ls_remote_batches<'a, T, P, I>(
+    repo: &util::Repo,
+    items: &'a [T],
+    patterns: P,
+) -> Result<Vec<(&'a [T], impl Future<Output = Result<Output>> + use<T, P, I>)>>
+where
+    P: Fn(&T) -> I,
+    I: IntoIterator<Item = String>,
+{
+    remote_query_batches(items)
+        .map(|chunk| {
+            let mut arguments = vec!["ls-remote".to_string(), repo.default_remote_name()];
+            arguments.extend(chunk.iter().flat_map(&patterns));
+            Ok((chunk, util::spawn_checked(&mut util::async_cmd("git", arguments))?))
+        })
+        .collect()
+}
 
-        parse_managed_branches(&output.stdout, chunk)?.into_iter().try_for_each(
-            |(id, object_id)| match states.entry(id) {
+/// Observes the managed branches relevant to the current stack, by name.
+pub(super) async fn observe_managed_branches(
+    repo: &util::Repo,
+    branches: &[String],
+) -> Result<HashMap<String, String>> {
+    let mut states = HashMap::new();
+    for (chunk, output) in
+        ls_remote_batches(repo, branches, |branch| [format!("refs/heads/{branch}")])?
+    {
+        let output = output.await?;
+        for (id, object_id) in parse_requested_refs(&output.stdout, "refs/heads/", chunk)? {
+            match states.entry(id) {
                 Entry::Vacant(entry) => {
                     entry.insert(object_id);
-                    Ok(())
                 }
                 Entry::Occupied(entry) => {
                     bail!("`git ls-remote` reported managed branch {} more than once", entry.key())
                 }
-            },
-        )?;
+            }
+        }
+    }
+    Ok(states)
+}
 
-        Ok(states)
-    })
+/// Observes the object ID of each of the fully qualified ref `names` that
+/// exists on the remote, by name.
+pub(super) async fn observe_refs(
+    repo: &util::Repo,
+    names: &[String],
+) -> Result<HashMap<String, String>> {
+    let mut states = HashMap::new();
+    for (chunk, output) in ls_remote_batches(repo, names, |name| [name.clone()])? {
+        states.extend(parse_requested_refs(&output.await?.stdout, "", chunk)?);
+    }
+    Ok(states)
+}
+
+/// Observes the version refs that the remote has published for each of
+/// `gherrit_ids`, in ascending version order.
+pub(super) async fn observe_published_versions(
+    repo: &util::Repo,
+    layout: RefLayout,
+    gherrit_ids: &[String],
+) -> Result<BTreeMap<String, Vec<(usize, RemoteRef)>>> {
+    let mut versions = BTreeMap::new();
+    for (chunk, output) in ls_remote_batches(repo, gherrit_ids, |id| [layout.versions_pattern(id)])?
+    {
+        let output = output.await?;
+        // The patterns match only version refs, so no branch is named.
+        let refs = parse_gherrit_refs(&output.stdout, layout, &BranchNaming::default())?;
+        versions.extend(refs.tags.into_iter().filter(|(id, _)| chunk.contains(id)));
+    }
+    Ok(versions)
+}
+
+/// The managed branches and version refs on the remote.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct GherritRefs {
+    /// The managed branches, by name.
+    pub branches: BTreeMap<String, RemoteRef>,
+    /// The version refs of each gherrit-pr-id, in ascending version order.
+    pub tags: BTreeMap<String, Vec<(usize, RemoteRef)>>,
+}
+
+/// Lists every managed branch that `naming` names and every version ref of
+/// `layout` on the remote.
+pub(super) fn list_gherrit_refs(
+    repo: &util::Repo,
+    layout: RefLayout,
+    naming: &BranchNaming,
+) -> Result<GherritRefs> {
+    let mut arguments = vec!["ls-remote".to_string(), repo.default_remote_name()];
+    arguments.extend([naming.pattern("G*"), "refs/heads/G*".to_string(), layout.pattern()]);
+    arguments.dedup();
+    let output = util::cmd("git", arguments).checked_output()?;
+    parse_gherrit_refs(&output.stdout, layout, naming)
 }
 
-/// Parses `git ls-remote` standard output.
+/// Parses `git ls-remote` standard output into managed branches and version
+/// refs of `layout`, ignoring every other ref.
+fn parse_gherrit_refs(
+    output: &[u8],
+    layout: RefLayout,
+    naming: &BranchNaming,
+) -> Result<GherritRefs> {
+    let mut refs = GherritRefs::default();
+    let output = output.strip_suffix(b"\n").unwrap_or(output);
+    for line in output.split(|byte| *byte == b'\n').filter(|line| !line.is_empty()) {
+        let mut fields = line.split(|byte| *byte == b'\t');
+        let (Some(object_id), Some(ref_name), None) = (fields.next(), fields.next(), fields.next())
+        else {
+            bail!("malformed `git ls-remote` line: {line:?}");
+        };
+        let object_id = ObjectId::from_hex(object_id)
+            .wrap_err_with(|| format!("invalid object ID in `git ls-remote` line: {line:?}"))?;
+        let Ok(name) = str::from_utf8(ref_name) else {
+            continue;
+        };
+        let remote_ref = RemoteRef { name: name.to_string(), object_id: object_id.to_string() };
+
+        if let Some(branch) = name.strip_prefix("refs/heads/")
+            && naming.parse(branch).is_some()
+        {
+            if refs.branches.insert(branch.to_string(), remote_ref).is_some() {
+                bail!("`git ls-remote` reported {name} more than once");
+            }
+        } else if let Some((id, version)) = layout.parse_version_ref(name) {
+            refs.tags.entry(id.to_string()).or_default().push((version, remote_ref));
+        }
+    }
+    for tags in refs.tags.values_mut() {
+        tags.sort_by_key(|(version, _)| *version);
+        if tags.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            bail!("`git ls-remote` reported a version tag more than once");
+        }
+    }
+    Ok(refs)
+}
+
+/// Parses `git ls-remote` standard output into the object ID of each of
+/// `requested` that exists, where `prefix` qualifies each requested name.
 ///
 /// The output is zero or more `<object ID>\t<fully qualified ref name>`
 /// records separated by line feeds; a requested ref that is absent produces no
 /// record. The final line feed is optional here. Ref names need not be UTF-8,
 /// so parsing stays byte-oriented and retains only exact requested refs after
 /// validating every complete record and object ID.
-fn parse_managed_branches(
+fn parse_requested_refs(
     output: &[u8],
-    requested_ids: &[String],
+    prefix: &str,
+    requested: &[String],
 ) -> Result<HashMap<String, String>> {
-    let requested_refs = requested_ids
+    let requested_refs = requested
         .iter()
-        .map(|id| (format!("refs/heads/{id}").into_bytes(), id))
+        .map(|id| (format!("{prefix}{id}").into_bytes(), id))
         .collect::<HashMap<_, _>>();
     if output.is_empty() {
         return Ok(HashMap::new());
@@ -74,7 +248,7 @@ fn parse_managed_branches(
                 entry.insert(object_id.to_string());
             }
             Entry::Occupied(_) => {
-                bail!("`git ls-remote` reported refs/heads/{id} more than once");
+                bail!("`git ls-remote` reported {prefix}{id} more than once");
             }
         }
         Ok(states)
@@ -104,13 +278,57 @@ mod tests {
         );
 
         assert_eq!(
-            parse_managed_branches(output.as_bytes(), &requested).unwrap(),
+            parse_requested_refs(output.as_bytes(), "refs/heads/", &requested).unwrap(),
             HashMap::from([
                 ("Gone".to_string(), OBJECT_A.to_string()),
                 ("Gtwo".to_string(), OBJECT_B.to_string()),
             ])
         );
-        assert!(parse_managed_branches(b"", &requested).unwrap().is_empty());
+        assert!(parse_requested_refs(b"", "refs/heads/", &requested).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_managed_branches_and_version_tags() {
+        let naming = BranchNaming::default();
+        let output = format!(
+            "{OBJECT_A}\trefs/heads/Gone\n\
+             {OBJECT_A}\trefs/heads/Gone/nested\n\
+             {OBJECT_A}\trefs/heads/Gaps-and-dashes\n\
+             {OBJECT_B}\trefs/tags/gherrit/Gone/v10\n\
+             {OBJECT_A}\trefs/tags/gherrit/Gone/v2\n\
+             {OBJECT_A}\trefs/tags/gherrit/Gone/v2^{{}}\n\
+             {OBJECT_A}\trefs/tags/gherrit/Gtwo/latest\n"
+        );
+
+        let refs = parse_gherrit_refs(output.as_bytes(), RefLayout::Tags, &naming).unwrap();
+        assert_eq!(refs.branches.keys().collect::<Vec<_>>(), ["Gone"]);
+        assert_eq!(refs.branches["Gone"].object_id, OBJECT_A);
+        let tags: Vec<_> =
+            refs.tags["Gone"].iter().map(|(version, tag)| (*version, tag.name.as_str())).collect();
+        assert_eq!(tags, [(2, "refs/tags/gherrit/Gone/v2"), (10, "refs/tags/gherrit/Gone/v10")]);
+        assert!(!refs.tags.contains_key("Gtwo"));
+
+        let refs = parse_gherrit_refs(output.as_bytes(), RefLayout::Refs, &naming).unwrap();
+        assert!(refs.tags.is_empty());
+
+        assert_eq!(
+            parse_gherrit_refs(b"", RefLayout::Tags, &naming).unwrap(),
+            GherritRefs::default()
+        );
+        assert!(parse_gherrit_refs(b"xyz\trefs/heads/Gone\n", RefLayout::Tags, &naming).is_err());
+    }
+
+    #[test]
+    fn parses_templated_managed_branches() {
+        let naming = BranchNaming::new("gherrit/{user}/{id}".to_string(), None).unwrap();
+        let output = format!(
+            "{OBJECT_A}\trefs/heads/gherrit/octo/Gone\n\
+             {OBJECT_A}\trefs/heads/gherrit/octo/nested/Gtwo\n\
+             {OBJECT_B}\trefs/heads/Gold\n"
+        );
+
+        let refs = parse_gherrit_refs(output.as_bytes(), RefLayout::Tags, &naming).unwrap();
+        assert_eq!(refs.branches.keys().collect::<Vec<_>>(), ["Gold", "gherrit/octo/Gone"]);
     }
 
     #[test]
@@ -121,7 +339,7 @@ mod tests {
         output.extend_from_slice(format!("{OBJECT_B}\trefs/heads/Gone\n").as_bytes());
 
         assert_eq!(
-            parse_managed_branches(&output, &requested).unwrap(),
+            parse_requested_refs(&output, "refs/heads/", &requested).unwrap(),
             HashMap::from([("Gone".to_string(), OBJECT_B.to_string())])
         );
     }
@@ -138,7 +356,10 @@ mod tests {
             b"xyz\trefs/heads/Gother\n".to_vec(),
             format!("{OBJECT_A}\trefs/heads/Gone\n{OBJECT_A}\trefs/heads/Gone\n").into_bytes(),
         ] {
-            assert!(parse_managed_branches(&output, &requested).is_err(), "output={output:?}");
+            assert!(
+                parse_requested_refs(&output, "refs/heads/", &requested).is_err(),
+                "output={output:?}"
+            );
         }
     }
 }
@@ -0,0 +1,127 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write as _,
+    process::Stdio,
+};
+
+use color_eyre::eyre::{Context as _, Result, bail};
+use gix::ObjectId;
+
+use super::Commit;
+use crate::util::{self, CommandExt as _};
+
+/// Whether a sync asks a PR's previous reviewers to review it again once its
+/// diff changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub(crate) enum RereviewPolicy {
+    /// Leave review requests alone.
+    Never,
+    /// Re-request review from everyone who reviewed the PR when a new version
+    /// changes its diff.
+    #[default]
+    Changed,
+}
+
+impl RereviewPolicy {
+    const CONFIG_KEY: &str = "gherrit.rereviewPolicy";
+
+    /// Reads the repository's policy, defaulting to `Changed`.
+    pub(crate) fn from_config(repo: &util::Repo) -> Result<Self> {
+        repo.config_choice(Self::CONFIG_KEY)
+    }
+}
+
+/// Finds the commits whose diff differs from that of the version that their
+/// managed branch was last published at, by gherrit-pr-id.
+///
+/// `branches` maps each managed branch to the object ID it was published at.
+/// Diffs are compared by patch ID, so a commit that was only reworded or
+/// rebased without conflicts does not count as revised. Neither does a commit
+/// whose previous version is not available locally, since its diff cannot be
+/// compared.
+pub(super) fn find_revised(
+    repo: &util::Repo,
+    commits: &[Commit],
+    branches: &HashMap<String, String>,
+) -> Result<HashSet<String>> {
+    let revisions: Vec<(&Commit, ObjectId)> = commits
+        .iter()
+        .filter_map(|c| {
+            let previous = ObjectId::from_hex(branches.get(&c.branch)?.as_bytes()).ok()?;
+            if previous == c.id {
+                return None;
+            }
+            if !repo.has_object(previous) {
+                log::debug!("Cannot compare {} with {previous}, which is not local", c.gherrit_id);
+                return None;
+            }
+            Some((c, previous))
+        })
+        .collect();
+    if revisions.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let object_ids = revisions.iter().flat_map(|(c, previous)| [c.id, *previous]);
+    let patch_ids = patch_ids(object_ids)?;
+    Ok(revisions
+        .into_iter()
+        .filter(|(c, previous)| patch_ids.get(&c.id) != patch_ids.get(previous))
+        .map(|(c, _)| c.gherrit_id.clone())
+        .collect())
+}
+
+/// Computes the stable patch ID of each commit. Commits with an empty diff
+/// have none.
+fn patch_ids(commits: impl IntoIterator<Item = ObjectId>) -> Result<HashMap<ObjectId, String>> {
+    let mut arguments =
+        vec!["show".to_string(), "--format=commit %H".to_string(), "--patch".into()];
+    arguments.extend(commits.into_iter().map(|id| id.to_string()));
+    let patches = util::cmd("git", arguments).checked_output()?.stdout;
+
+    let mut child = util::cmd("git", ["patch-id", "--stable"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .wrap_err("Failed to run `git patch-id`")?;
+    // `git patch-id` prints one short line per commit, so it cannot fill its
+    // output pipe and block before reading all of its input.
+    child.stdin.take().unwrap().write_all(&patches).wrap_err("Failed to run `git patch-id`")?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "`git patch-id` failed with status: {}. Stderr: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            let Some((patch_id, commit)) = line.split_once(' ') else {
+                bail!("`git patch-id` printed malformed line '{line}'");
+            };
+            Ok((ObjectId::from_hex(commit.as_bytes())?, patch_id.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum as _;
+
+    use super::*;
+
+    #[test]
+    fn parses_and_displays_policies() {
+        for (policy, name) in
+            [(RereviewPolicy::Never, "never"), (RereviewPolicy::Changed, "changed")]
+        {
+            assert_eq!(util::choice_name(&policy), name);
+            assert_eq!(RereviewPolicy::from_str(name, false), Ok(policy));
+        }
+        assert!(RereviewPolicy::from_str("always", false).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::HashSet, fs};
+
+use color_eyre::eyre::{Context as _, Result, bail, eyre};
+use gix::{ObjectId, refs::transaction::PreviousValue};
+use owo_colors::OwoColorize as _;
+
+use super::{
+    Commit, GithubEndpoint, fetch_stack_prs, github_client, parse_stack,
+    reconcile::PullRequestState,
+};
+use crate::util::{self, CommandExt as _, HeadState};
+
+/// Why a commit no longer belongs in its stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Landed {
+    /// The commit's PR has been merged.
+    Merged(u64),
+    /// A commit with the same patch ID exists upstream.
+    Upstream,
+}
+
+/// Drops the commits of the current stack that have landed and rebases the
+/// rest onto the default branch of the default remote.
+pub async fn run(repo: &util::Repo, github_endpoint: &GithubEndpoint) -> Result<()> {
+    let branch_name = match repo.current_branch() {
+        HeadState::Attached(branch_name) => branch_name,
+        HeadState::Pending(_) => {
+            bail!("A rebase is in progress. Finish it with 'git rebase --continue' first.")
+        }
+        HeadState::Detached => bail!("Cannot restack from detached HEAD"),
+    };
+    if !repo.is_managed(branch_name)? {
+        bail!("Branch '{branch_name}' is UNMANAGED, so it has no GHerrit stack to restack.");
+    }
+
+    let remote_name = repo.default_remote_name();
+    let default_branch = repo.find_default_branch_on_default_remote();
+    let upstream_name = format!("{remote_name}/{default_branch}");
+    let upstream_ref = format!("refs/remotes/{upstream_name}");
+    let refspec = format!("+refs/heads/{default_branch}:{upstream_ref}");
+    util::cmd("git", ["fetch", "--quiet", "--no-tags", remote_name.as_str(), refspec.as_str()])
+        .success()
+        .wrap_err_with(|| format!("Failed to fetch {upstream_name}"))?;
+
+    let upstream = repo.rev_parse_single(upstream_ref.as_str())?;
+    let head = repo.rev_parse_single("HEAD")?;
+    let base = repo.merge_base(head, upstream).map_err(|_| {
+        eyre!("The branch '{branch_name}' shares no history with '{upstream_name}'")
+    })?;
+    let commits = match repo.commits_between(base, head) {
+        Ok(commits) => commits,
+        Err(util::CommitsBetweenError::NotAncestor) => unreachable!("a merge base is an ancestor"),
+        Err(util::CommitsBetweenError::Eyre(err)) => return Err(err),
+    };
+    let mut commits = parse_stack(repo, &default_branch, commits)?;
+
+    let landed = find_landed(
+        repo,
+        github_endpoint,
+        &mut commits,
+        &default_branch,
+        upstream.detach(),
+        base.detach(),
+    )
+    .await?;
+    if landed.iter().all(Option::is_none) && base == upstream {
+        log::info!("Branch {} is already up to date with {upstream_name}.", branch_name.yellow());
+        return Ok(());
+    }
+
+    let mut kept = Vec::new();
+    for (commit, landed) in commits.iter().zip(&landed) {
+        match landed {
+            None => kept.push(commit.id),
+            Some(Landed::Merged(number)) => log::info!(
+                "Dropping {} ({}): PR #{} is merged.",
+                commit.message_title,
+                commit.gherrit_id,
+                number.green().bold()
+            ),
+            Some(Landed::Upstream) => log::info!(
+                "Dropping {} ({}): its changes are already in {upstream_name}.",
+                commit.message_title,
+                commit.gherrit_id
+            ),
+        }
+    }
+
+    fast_forward_default_branch(repo, &default_branch, upstream.detach())?;
+    rebase(repo, upstream.detach(), base.detach(), &kept)?;
+
+    log::info!("Restacked {} commits onto {}.", kept.len(), upstream_name.yellow());
+    Ok(())
+}
+
+/// Determines which commits of the stack have landed, either through their
+/// PRs or by being applied upstream under a different commit ID.
+///
+/// Only a PR merged into `default_branch` lands its commit. One merged into
+/// its parent's phantom branch never reached upstream, so its commit has
+/// landed only if `git cherry` finds it there.
+async fn find_landed(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    commits: &mut [Commit],
+    default_branch: &str,
+    upstream: ObjectId,
+    base: ObjectId,
+) -> Result<Vec<Option<Landed>>> {
+    let octocrab = github_client(github_endpoint)?;
+    let (prs, _) = fetch_stack_prs(repo, &octocrab, commits).await?;
+
+    // `git cherry` marks each commit in `base..HEAD` whose patch ID matches a
+    // commit in `base..upstream` with a `-`.
+    let output = util::cmd(
+        "git",
+        ["cherry", upstream.to_string().as_str(), "HEAD", base.to_string().as_str()],
+    )
+    .checked_output()
+    .wrap_err("Failed to compare the stack with upstream")?;
+    let applied_upstream = String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| line.strip_prefix("- "))
+        .map(|object_id| ObjectId::from_hex(object_id.as_bytes()))
+        .collect::<Result<HashSet<_>, _>>()?;
+
+    Ok(commits
+        .iter()
+        .map(|commit| {
+            let merged = prs.iter().find(|pr| {
+                pr.head_branch == commit.branch
+                    && pr.state == PullRequestState::Merged
+                    && pr.base_branch == default_branch
+            });
+            match merged {
+                Some(pr) => Some(Landed::Merged(pr.number)),
+                None if applied_upstream.contains(&commit.id) => Some(Landed::Upstream),
+                None => None,
+            }
+        })
+        .collect())
+}
+
+/// Moves the local default branch to `upstream` if that is a fast-forward.
+///
+/// The stack is synced relative to the local default branch, so leaving it
+/// behind would make the restacked branch appear to contain upstream commits.
+fn fast_forward_default_branch(
+    repo: &util::Repo,
+    default_branch: &str,
+    upstream: ObjectId,
+) -> Result<()> {
+    let name = format!("refs/heads/{default_branch}");
+    let Some(local) = repo.try_find_reference(name.as_str())? else {
+        return Ok(());
+    };
+    let local = local.id().detach();
+    if local == upstream {
+        return Ok(());
+    }
+    if repo.merge_base(local, upstream).map(|merge_base| merge_base.detach()).ok() != Some(local) {
+        log::warn!(
+            "Local branch {} has diverged from upstream. Update it before pushing the stack.",
+            default_branch.yellow()
+        );
+        return Ok(());
+    }
+    repo.reference(
+        name,
+        upstream,
+        PreviousValue::MustExistAndMatch(local.into()),
+        "gherrit: fast-forward default branch for restack",
+    )?;
+    Ok(())
+}
+
+/// Replays `kept` onto `upstream`, dropping every other commit after `base`.
+///
+/// The rebase is interactive only so that Git accepts an explicit list of
+/// commits: the sequence editor replaces Git's todo list with our own.
+fn rebase(repo: &util::Repo, upstream: ObjectId, base: ObjectId, kept: &[ObjectId]) -> Result<()> {
+    let todo = if kept.is_empty() {
+        "noop\n".to_string()
+    } else {
+        kept.iter().map(|object_id| format!("pick {object_id}\n")).collect()
+    };
+    let todo_path = repo.path().join("gherrit-restack-todo");
+    fs::write(&todo_path, todo).wrap_err("Failed to write the rebase todo list")?;
+    let todo_path = todo_path.to_str().ok_or_else(|| eyre!("Git directory is not valid UTF-8"))?;
+    let sequence_editor = format!("cp '{}'", todo_path.replace('\'', r"'\''"));
+
+    let result = util::cmd(
+        "git",
+        [
+            "-c",
+            "rebase.missingCommitsCheck=ignore",
+            "rebase",
+            "--quiet",
+            "--interactive",
+            "--no-autosquash",
+            "--onto",
+            upstream.to_string().as_str(),
+            base.to_string().as_str(),
+        ],
+    )
+    .env("GIT_SEQUENCE_EDITOR", sequence_editor)
+    .success();
+    let _ = fs::remove_file(todo_path);
+    result.wrap_err(
+        "Failed to rebase the stack. Resolve any conflicts and run 'git rebase --continue'.",
+    )
+}
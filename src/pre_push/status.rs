@@ -0,0 +1,193 @@
+use std::fmt;
+
+use color_eyre::eyre::{Context as _, Result};
+use gix::ObjectId;
+use owo_colors::OwoColorize as _;
+
+use super::{
+    GithubEndpoint, RefLayout, collect_commits, fetch_stack_prs, github_client,
+    read_local_versions, reconcile::PullRequestState,
+};
+use crate::util;
+
+/// How a local commit relates to its published managed branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncState {
+    /// The managed branch does not exist on the remote.
+    Unpublished,
+    /// The managed branch points to the local commit.
+    Current,
+    /// The managed branch points to a different commit.
+    Diverged,
+}
+
+impl SyncState {
+    fn observe(local: ObjectId, remote: Option<&str>) -> Self {
+        match remote {
+            None => Self::Unpublished,
+            Some(remote) if remote == local.to_string() => Self::Current,
+            Some(_) => Self::Diverged,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Unpublished => "not published",
+            Self::Current => "up to date",
+            Self::Diverged => "differs from local commit",
+        }
+    }
+}
+
+struct PullRequestStatus {
+    number: u64,
+    url: String,
+    state: PullRequestState,
+}
+
+struct StatusEntry {
+    gherrit_id: String,
+    title: String,
+    pull_request: Option<PullRequestStatus>,
+    version: Option<usize>,
+    sync: SyncState,
+}
+
+/// Prints the GitHub and publication state of every commit in the stack.
+pub async fn run(repo: &util::Repo, github_endpoint: &GithubEndpoint) -> Result<()> {
+    if let Some(branch_name) = repo.current_branch().name()
+        && !repo.is_managed(branch_name)?
+    {
+        log::info!("Branch {} is UNMANAGED. It has no GHerrit stack.", branch_name.yellow());
+        return Ok(());
+    }
+
+    let branch_name = repo.current_branch().name().unwrap_or("current branch");
+    let tip = repo.rev_parse_single("HEAD")?.detach();
+    let mut commits =
+        collect_commits(repo, branch_name, tip).wrap_err("Failed to collect commits")?;
+    if commits.is_empty() {
+        log::info!("No commits in stack.");
+        return Ok(());
+    }
+
+    let octocrab = github_client(github_endpoint)?;
+    let (prs, remote_branch_states) = fetch_stack_prs(repo, &octocrab, &mut commits).await?;
+    let remote = repo.default_remote()?;
+    let layout = RefLayout::from_config(repo)?;
+    let local_versions =
+        read_local_versions(repo, layout, commits.iter().map(|c| c.gherrit_id.as_str()))?;
+
+    let entries = commits
+        .into_iter()
+        .map(|c| -> Result<_> {
+            let pull_request =
+                prs.iter().find(|pr| pr.head_branch == c.branch).map(|pr| PullRequestStatus {
+                    number: pr.number,
+                    url: remote.pr_url(pr.number),
+                    state: pr.state,
+                });
+            let version = local_versions.latest(&c.gherrit_id).map(|local| local.version);
+            let sync =
+                SyncState::observe(c.id, remote_branch_states.get(&c.branch).map(String::as_str));
+            Ok(StatusEntry {
+                gherrit_id: c.gherrit_id,
+                title: c.message_title,
+                pull_request,
+                version,
+                sync,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    print!("{}", render(&entries));
+    Ok(())
+}
+
+/// Renders the stack from its tip to its base, matching the order of the
+/// navigation list in each PR body.
+fn render(entries: &[StatusEntry]) -> String {
+    let mut output = String::new();
+    entries
+        .iter()
+        .rev()
+        .try_for_each(|entry| write_entry(&mut output, entry))
+        .expect("writing a status report to a string cannot fail");
+    output
+}
+
+fn write_entry(mut output: impl fmt::Write, entry: &StatusEntry) -> fmt::Result {
+    writeln!(output, "{} {}", entry.gherrit_id, entry.title)?;
+    match &entry.pull_request {
+        Some(pr) => {
+            let state = match pr.state {
+                PullRequestState::Open => "open",
+                PullRequestState::Closed => "closed",
+                PullRequestState::Merged => "merged",
+            };
+            writeln!(output, "    PR:      #{} ({state}) {}", pr.number, pr.url)?;
+        }
+        None => output.write_str("    PR:      none\n")?,
+    }
+    match entry.version {
+        Some(version) => writeln!(output, "    Version: v{version}")?,
+        None => output.write_str("    Version: none\n")?,
+    }
+    writeln!(output, "    Remote:  {}", entry.sync.describe())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object_id(byte: u8) -> ObjectId {
+        ObjectId::from_bytes_or_panic(&[byte; 20])
+    }
+
+    #[test]
+    fn classifies_every_remote_branch_observation() {
+        let local = object_id(0x11);
+        assert_eq!(SyncState::observe(local, None), SyncState::Unpublished);
+        assert_eq!(SyncState::observe(local, Some(&local.to_string())), SyncState::Current);
+        assert_eq!(
+            SyncState::observe(local, Some(&object_id(0x22).to_string())),
+            SyncState::Diverged
+        );
+    }
+
+    #[test]
+    fn renders_a_stack_from_tip_to_base() {
+        let pull_request = |number, state| {
+            Some(PullRequestStatus {
+                number,
+                url: format!("https://github.com/octo/widgets/pull/{number}"),
+                state,
+            })
+        };
+        let entries = [
+            StatusEntry {
+                gherrit_id: "Groot".to_string(),
+                title: "Introduce widgets".to_string(),
+                pull_request: pull_request(11, PullRequestState::Merged),
+                version: Some(3),
+                sync: SyncState::Current,
+            },
+            StatusEntry {
+                gherrit_id: "Gmiddle".to_string(),
+                title: "Render 雪 correctly".to_string(),
+                pull_request: pull_request(22, PullRequestState::Open),
+                version: Some(1),
+                sync: SyncState::Diverged,
+            },
+            StatusEntry {
+                gherrit_id: "Gtip".to_string(),
+                title: "Finish the stack".to_string(),
+                pull_request: None,
+                version: None,
+                sync: SyncState::Unpublished,
+            },
+        ];
+
+        insta::assert_snapshot!(render(&entries));
+    }
+}
@@ -0,0 +1,46 @@
+use color_eyre::eyre::{Result, bail, eyre};
+use owo_colors::OwoColorize as _;
+
+use super::{GithubEndpoint, SyncOptions, journal::SyncJournal, lock::SyncLock, sync_stack};
+use crate::util;
+
+/// Syncs the stack of a managed branch to GitHub without running `git push`.
+///
+/// Without an explicit `branch`, syncs the current branch as of `HEAD`, just
+/// like the `pre-push` hook. An explicit branch is synced at its tip and need
+/// not be checked out. With `options.resume`, the branch must have an
+/// interrupted sync, which is finished without pushing.
+pub async fn run(
+    repo: &util::Repo,
+    github_endpoint: &GithubEndpoint,
+    branch: Option<&str>,
+    options: SyncOptions,
+) -> Result<()> {
+    let (branch_name, tip) = match branch {
+        Some(branch) => {
+            let tip = repo
+                .rev_parse_single(format!("refs/heads/{branch}").as_str())
+                .map_err(|_| eyre!("Branch '{branch}' does not exist"))?;
+            (branch, tip.detach())
+        }
+        None => match repo.current_branch().name() {
+            Some(branch) => (branch, repo.rev_parse_single("HEAD")?.detach()),
+            None => bail!("Cannot sync from detached HEAD. Pass --branch to choose a stack."),
+        },
+    };
+
+    if !repo.is_managed(branch_name)? {
+        bail!(
+            "Branch '{branch_name}' is UNMANAGED, so it has no GHerrit stack to sync.\n\
+             Check it out and run 'gherrit manage' to sync it as a GHerrit stack."
+        );
+    }
+    if options.resume && SyncJournal::load(repo, branch_name)?.is_none() {
+        bail!("Branch '{branch_name}' has no interrupted sync to resume.");
+    }
+    let _lock = SyncLock::acquire(repo)?;
+    log::info!("Syncing stack of branch {}...", branch_name.yellow());
+
+    sync_stack(repo, github_endpoint, branch_name, tip, options).await?;
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::str;
+
+use color_eyre::eyre::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// The login that stands for the authenticated user, as in `gherrit-assignees:
+/// @me`.
+pub(super) const VIEWER: &str = "me";
+
+/// The reviewers, labels, and assignees that a commit's trailers request for
+/// its PR.
+///
+/// Reviewers are user logins or `org/team` slugs, and assignees are user
+/// logins, all without the leading `@`. GHerrit records what it requested in
+/// the PR's metadata, so each sync requests only what the trailers added since,
+/// and never undoes what was changed on GitHub.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct Triage {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) reviewers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) assignees: Vec<String>,
+}
+
+impl Triage {
+    /// Parses the `gherrit-reviewers`, `gherrit-labels`, and
+    /// `gherrit-assignees` trailers, each a comma-separated list.
+    ///
+    /// The singular `gherrit-reviewer`, `gherrit-label`, and `gherrit-assignee`
+    /// are accepted too, and repeated trailers add to each other.
+    pub(super) fn from_trailers(trailers: &[u8]) -> Result<Self> {
+        let mut triage = Self::default();
+        for line in trailers.split(|byte| *byte == b'\n') {
+            let Some(position) = line.windows(2).position(|separator| separator == b": ") else {
+                continue;
+            };
+            let (name, value) = (&line[..position], &line[position + 2..]);
+            let key = name.strip_suffix(b"s").unwrap_or(name);
+            let (list, allows_teams) = match key {
+                b"gherrit-reviewer" => (&mut triage.reviewers, true),
+                b"gherrit-assignee" => (&mut triage.assignees, false),
+                b"gherrit-label" => (&mut triage.labels, false),
+                _ => continue,
+            };
+            let Ok(value) = str::from_utf8(value) else {
+                bail!("The {} trailer is not valid UTF-8", String::from_utf8_lossy(name));
+            };
+            let is_label = key == b"gherrit-label";
+            for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                let entry = if is_label {
+                    entry
+                } else {
+                    let login = entry.strip_prefix('@').unwrap_or(entry);
+                    if !is_valid_login(login, allows_teams) {
+                        bail!(
+                            "The {} trailer names '{entry}', which is not a GitHub {}",
+                            String::from_utf8_lossy(name),
+                            if allows_teams { "user or team" } else { "user" }
+                        );
+                    }
+                    login
+                };
+                if !list.iter().any(|existing| existing == entry) {
+                    list.push(entry.to_string());
+                }
+            }
+        }
+        Ok(triage)
+    }
+
+    /// Whether `name` is one of the trailers that [`Triage::from_trailers`]
+    /// parses.
+    pub(super) fn is_trailer(name: &str) -> bool {
+        let key = name.strip_suffix('s').unwrap_or(name);
+        matches!(key, "gherrit-reviewer" | "gherrit-assignee" | "gherrit-label")
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.reviewers.is_empty() && self.labels.is_empty() && self.assignees.is_empty()
+    }
+
+    /// The reviewers, labels, and assignees that `recorded` does not contain.
+    pub(super) fn added_since(&self, recorded: &Triage) -> Triage {
+        let added = |desired: &[String], recorded: &[String]| {
+            desired.iter().filter(|entry| !recorded.contains(entry)).cloned().collect()
+        };
+        Triage {
+            reviewers: added(&self.reviewers, &recorded.reviewers),
+            labels: added(&self.labels, &recorded.labels),
+            assignees: added(&self.assignees, &recorded.assignees),
+        }
+    }
+}
+
+/// Whether `login` is a GitHub login or, if `allows_teams`, an `org/team`
+/// slug.
+fn is_valid_login(login: &str, allows_teams: bool) -> bool {
+    let is_name = |name: &str| {
+        !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    };
+    match login.split_once('/') {
+        Some((organization, team)) => allows_teams && is_name(organization) && is_name(team),
+        None => is_name(login),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn parses_comma_separated_trailers() {
+        let trailers = b"Signed-off-by: A U Thor <author@example.com>\n\
+            gherrit-reviewers: @alice, @org/team\n\
+            gherrit-labels: perf, good first issue\n\
+            gherrit-assignee: @me\n\
+            gherrit-reviewer: bob, @alice\n\
+            gherrit-pr-id: G123\n";
+
+        assert_eq!(
+            Triage::from_trailers(trailers).unwrap(),
+            Triage {
+                reviewers: strings(&["alice", "org/team", "bob"]),
+                labels: strings(&["perf", "good first issue"]),
+                assignees: strings(&[VIEWER]),
+            }
+        );
+        assert!(Triage::from_trailers(b"gherrit-pr-id: G123\n").unwrap().is_empty());
+        assert!(Triage::is_trailer("gherrit-labels") && Triage::is_trailer("gherrit-assignee"));
+        assert!(!Triage::is_trailer("gherrit-pr-id") && !Triage::is_trailer("gherrit-labelss"));
+    }
+
+    #[test]
+    fn rejects_malformed_logins() {
+        assert_eq!(
+            Triage::from_trailers(b"gherrit-assignees: @org/team\n").unwrap_err().to_string(),
+            "The gherrit-assignees trailer names '@org/team', which is not a GitHub user"
+        );
+        assert!(Triage::from_trailers(b"gherrit-reviewers: @alice bob\n").is_err());
+        assert!(Triage::from_trailers(b"gherrit-reviewers: @org/\n").is_err());
+    }
+
+    #[test]
+    fn requests_only_additions() {
+        let recorded = Triage {
+            reviewers: strings(&["alice"]),
+            labels: strings(&["perf"]),
+            assignees: Vec::new(),
+        };
+        let desired = Triage {
+            reviewers: strings(&["alice", "bob"]),
+            labels: Vec::new(),
+            assignees: strings(&[VIEWER]),
+        };
+
+        assert_eq!(
+            desired.added_since(&recorded),
+            Triage {
+                reviewers: strings(&["bob"]),
+                labels: Vec::new(),
+                assignees: strings(&[VIEWER]),
+            }
+        );
+        assert!(recorded.added_since(&recorded).is_empty());
+    }
+}
@@ -1,4 +1,7 @@
-use std::{ffi::OsStr, process::Command};
+use std::{
+    ffi::OsStr,
+    process::{Command, Stdio},
+};
 
 use eyre::{OptionExt, Result, WrapErr, bail, eyre};
 use gix::{Commit, Id, bstr::ByteSlice, state::InProgress};
@@ -84,6 +87,41 @@ pub fn cmd<I: AsRef<OsStr>>(name: &str, args: impl IntoIterator<Item = I>) -> Co
     c
 }
 
+/// Constructs a `tokio::process::Command`, whose process the runtime drives
+/// so that several commands can run at once.
+pub fn async_cmd<I: AsRef<OsStr>>(
+    name: &str,
+    args: impl IntoIterator<Item = I>,
+) -> tokio::process::Command {
+    let mut c = tokio::process::Command::new(name);
+    c.args(args).kill_on_drop(true);
+    c
+}
+
+/// Starts `command` with its output captured.
+///
+/// The process runs as soon as this returns; the returned future resolves to
+/// its output, failing if the command did.
+pub fn spawn_checked(
+    command: &mut tokio::process::Command,
+) -> Result<impl Future<Output = Result<std::process::Output>> + use<>> {
+    let description = format!("{:?}", command.as_std());
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .wrap_err_with(|| format!("Failed to run {description}"))?;
+    Ok(async move {
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("Command {description} failed with status: {}. Stderr: {stderr}", output.status);
+        }
+        Ok(output)
+    })
+}
+
 use std::path::PathBuf;
 
 /// Represents the state of the HEAD reference.
@@ -135,6 +173,22 @@ impl Repo {
         Ok(Some(s.trim().to_string()))
     }
 
+    /// Reads a setting that takes one of the values of `T`, defaulting to
+    /// `T::default()` when it is unset.
+    pub fn config_choice<T: clap::ValueEnum + Default>(&self, key: &str) -> Result<T> {
+        let Some(value) = self.config_string(key)? else {
+            return Ok(T::default());
+        };
+        T::from_str(&value, false).map_err(|_| {
+            let names: Vec<_> = T::value_variants().iter().map(choice_name).collect();
+            let expected = match names.as_slice() {
+                [init @ .., last] if init.len() > 1 => format!("{}, or {last}", init.join(", ")),
+                _ => names.join(" or "),
+            };
+            eyre!("Invalid {key}: '{value}' is not one of {expected}.")
+        })
+    }
+
     pub fn config_path(&self, key: &str) -> Result<Option<PathBuf>> {
         let snapshot = self.inner.config_snapshot();
         let Some(path_val) = snapshot.path(key) else {
@@ -381,6 +435,12 @@ impl CommandExt for Command {
     }
 }
 
+/// The name that a value of a [`clap::ValueEnum`] goes by on the command line
+/// and in config settings.
+pub fn choice_name<T: clap::ValueEnum>(value: &T) -> String {
+    value.to_possible_value().map_or_else(String::new, |value| value.get_name().to_string())
+}
+
 pub fn get_github_token() -> Result<String> {
     // Priority 1: GITHUB_TOKEN env var
     if let Ok(token) = std::env::var("GITHUB_TOKEN")
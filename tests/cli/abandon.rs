@@ -0,0 +1,72 @@
+fn pull_request(ctx: &testutil::TestContext, number: usize) -> testutil::PullRequestSnapshot {
+    ctx.github().pull_requests().into_iter().find(|pr| pr.number == number).unwrap()
+}
+
+#[test]
+fn abandon_closes_the_pr_of_a_dropped_commit() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("abandon");
+    let base_id = ctx.commit_with_gherrit_id("Base work");
+    let middle_id = ctx.commit_with_gherrit_id("Middle work");
+    let tip_id = ctx.commit_with_gherrit_id("Tip work");
+    ctx.hook_cmd("pre-push").assert().success();
+    assert!(ctx.remote_ref_oid(&format!("refs/heads/{middle_id}")).is_some());
+
+    // Drop the middle commit.
+    ctx.run_git(&["rebase", "--quiet", "--onto", "HEAD~2", "HEAD~1"]);
+    assert_eq!(ctx.gherrit_id("HEAD~1").unwrap(), base_id);
+
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["abandon", middle_id.as_str(), "--comment", "Folded into #1."]),
+        "abandon_dropped_commit"
+    );
+
+    let middle = pull_request(&ctx, 2);
+    assert_eq!(middle.state, testutil::PullRequestState::Closed);
+    assert_eq!(ctx.github().comments(2), ["Folded into #1."]);
+    assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{middle_id}")), None);
+    assert!(
+        !ctx.remote_refs(&format!("refs/tags/gherrit/{middle_id}/")).is_empty(),
+        "abandoning keeps the version tags"
+    );
+
+    // The remaining PRs are re-stacked and link to each other.
+    let tip = pull_request(&ctx, 3);
+    assert_eq!(tip.base, base_id);
+    assert!(!tip.body.unwrap().contains(&middle_id));
+    assert!(pull_request(&ctx, 1).body.unwrap().contains(&tip_id));
+}
+
+#[test]
+fn abandon_rejects_a_commit_that_is_still_in_the_stack() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("abandon");
+    ctx.commit_with_gherrit_id("Base work");
+    let tip_id = ctx.commit_with_gherrit_id("Tip work");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    testutil::assert_failure_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["abandon", tip_id.as_str()]),
+        "abandon_commit_in_stack"
+    );
+    assert_eq!(pull_request(&ctx, 2).state, testutil::PullRequestState::Open);
+    assert!(ctx.remote_ref_oid(&format!("refs/heads/{tip_id}")).is_some());
+
+    testutil::assert_failure_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["abandon", "Gdoesnotexist"]),
+        "abandon_unknown_id"
+    );
+}
@@ -0,0 +1,73 @@
+#[test]
+fn checkout_reconstructs_the_stack_around_a_pr() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("author-stack");
+    ctx.commit_with_gherrit_id("Base work");
+    ctx.commit_with_gherrit_id("Middle work");
+    ctx.commit_with_gherrit_id("Tip work");
+    let tip = ctx.head_oid();
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.run_git(&["checkout", "main"]);
+
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["checkout", "2", "--branch", "review"]),
+        "checkout_stack"
+    );
+
+    assert_eq!(ctx.head_oid(), tip);
+    ctx.assert_config("branch.review.gherritManaged", Some(testutil::MANAGED_PRIVATE));
+    ctx.assert_config("branch.review.pushRemote", Some("."));
+}
+
+#[test]
+fn checkout_by_gherrit_id_stops_at_merged_prs() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("partly-merged");
+    ctx.commit_with_gherrit_id("Merged work");
+    ctx.commit_with_gherrit_id("Open work");
+    let id = ctx.gherrit_id("HEAD").unwrap();
+    let tip = ctx.head_oid();
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.github().set_pull_request_state(1, testutil::PullRequestState::Merged);
+    ctx.run_git(&["checkout", "main"]);
+
+    ctx.gherrit_cmd().args(["checkout", &id]).assert().success();
+
+    assert_eq!(ctx.head_oid(), tip);
+    ctx.assert_config(&format!("branch.pr-{id}.gherritManaged"), Some(testutil::MANAGED_PRIVATE));
+}
+
+#[test]
+fn checkout_rejects_unknown_prs_and_existing_branches() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("existing");
+    ctx.commit_with_gherrit_id("Work");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    testutil::assert_failure_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["checkout", "7"]),
+        "checkout_unknown_pr"
+    );
+    testutil::assert_failure_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["checkout", "1", "--branch", "existing"]),
+        "checkout_existing_branch"
+    );
+}
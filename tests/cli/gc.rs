@@ -0,0 +1,119 @@
+/// Pushes a stack whose bottom PR is merged, whose middle PR is closed with
+/// two versions, and whose top PR is still open. Returns the three IDs.
+fn finished_stack(ctx: &testutil::TestContext) -> [String; 3] {
+    ctx.checkout_managed_private("collect");
+    let merged_id = ctx.commit_with_gherrit_id("Merged work");
+    let closed_id = ctx.commit_with_gherrit_id("Closed work");
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.amend_with_message("Closed work, revised");
+    ctx.hook_cmd("pre-push").assert().success();
+    let open_id = ctx.commit_with_gherrit_id("Open work");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    ctx.github().set_pull_request_state(1, testutil::PullRequestState::Merged);
+    ctx.github().set_pull_request_state(2, testutil::PullRequestState::Closed);
+    [merged_id, closed_id, open_id]
+}
+
+#[test]
+fn gc_deletes_the_refs_of_finished_prs() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    let [merged_id, closed_id, open_id] = finished_stack(&ctx);
+    let refs_before = ctx.remote_refs("refs/");
+
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args([
+            "gc",
+            "--dry-run",
+            "--include-closed",
+            "--tags",
+            "prune",
+            "--tag-age",
+            "0"
+        ]),
+        "gc_dry_run"
+    );
+    assert_eq!(ctx.remote_refs("refs/"), refs_before);
+
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["gc", "--include-closed", "--tags", "compact", "--tag-age", "0"]),
+        "gc_compact"
+    );
+    assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{merged_id}")), None);
+    // The open PR is still based on the closed PR's branch.
+    assert!(ctx.remote_ref_oid(&format!("refs/heads/{closed_id}")).is_some());
+    assert!(ctx.remote_ref_oid(&format!("refs/heads/{open_id}")).is_some());
+    assert_eq!(
+        ctx.remote_refs("refs/tags/gherrit/"),
+        [
+            format!("refs/tags/gherrit/{closed_id}/v2"),
+            format!("refs/tags/gherrit/{merged_id}/v1"),
+            format!("refs/tags/gherrit/{open_id}/v1"),
+        ]
+        .into_iter()
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn gc_keeps_tags_younger_than_the_configured_age() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    finished_stack(&ctx);
+    let tags_before = ctx.remote_refs("refs/tags/");
+
+    // The fixture's commits date from 2000, so only an age reaching back
+    // before then keeps them.
+    ctx.set_config("gherrit.gcTags", Some("prune"));
+    ctx.set_config("gherrit.gcTagAge", Some("36500"));
+    ctx.gherrit_cmd().args(["gc", "--include-closed"]).assert().success();
+    assert_eq!(ctx.remote_refs("refs/tags/"), tags_before);
+
+    ctx.set_config("gherrit.gcTagAge", Some("soon"));
+    testutil::assert_failure_snapshot!(ctx, ctx.gherrit_cmd().arg("gc"), "gc_invalid_tag_age");
+}
+
+#[test]
+fn gc_keeps_the_refs_of_closed_prs_so_they_can_be_reopened() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("reopen");
+    let closed_id = ctx.commit_with_gherrit_id("Closed work");
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.github().set_pull_request_state(1, testutil::PullRequestState::Closed);
+
+    ctx.gherrit_cmd().args(["gc", "--tags", "prune", "--tag-age", "0"]).assert().success();
+    assert!(ctx.remote_ref_oid(&format!("refs/heads/{closed_id}")).is_some());
+    assert!(ctx.remote_ref_oid(&format!("refs/tags/gherrit/{closed_id}/v1")).is_some());
+
+    ctx.gherrit_cmd().args(["sync", "--on-closed-pr", "reopen"]).assert().success();
+    let pr = ctx.github().pull_requests().into_iter().find(|pr| pr.number == 1).unwrap();
+    assert_eq!(pr.state, testutil::PullRequestState::Open);
+
+    // Once its head branch is collected, GitHub can no longer reopen it.
+    ctx.github().set_pull_request_state(1, testutil::PullRequestState::Closed);
+    ctx.gherrit_cmd().args(["gc", "--include-closed"]).assert().success();
+    assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{closed_id}")), None);
+    ctx.gherrit_cmd()
+        .args(["sync", "--on-closed-pr", "reopen"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("its head branch was deleted"));
+}
@@ -0,0 +1,151 @@
+use testutil::PullRequestState;
+
+fn pull_request(ctx: &testutil::TestContext, number: usize) -> testutil::PullRequestSnapshot {
+    ctx.github().pull_requests().into_iter().find(|pr| pr.number == number).unwrap()
+}
+
+/// Pushes a stack of three commits whose PRs are approved and pass their
+/// checks, returning their gherrit-pr-ids from the bottom up.
+fn push_landable_stack(ctx: &testutil::TestContext) -> [String; 3] {
+    ctx.checkout_managed_private("land");
+    let ids = ["Base work", "Middle work", "Tip work"].map(|msg| ctx.commit_with_gherrit_id(msg));
+    ctx.hook_cmd("pre-push").assert().success();
+    for number in 1..=3 {
+        ctx.github().add_review(number, "alice", "APPROVED");
+        ctx.github().set_review_decision(number, Some("APPROVED"));
+        ctx.github().set_check_state(number, Some("SUCCESS"));
+    }
+    ids
+}
+
+#[test]
+fn land_merges_the_bottom_of_the_stack() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    let [_, _, tip_id] = push_landable_stack(&ctx);
+    ctx.set_config("gherrit.mergeMethod", Some("squash"));
+
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["land", "--count", "2"]),
+        "land_bottom_two"
+    );
+
+    assert_eq!(pull_request(&ctx, 1).state, PullRequestState::Merged);
+    assert_eq!(pull_request(&ctx, 1).merge_method.as_deref(), Some("SQUASH"));
+    let middle = pull_request(&ctx, 2);
+    assert_eq!(middle.state, PullRequestState::Merged);
+    assert_eq!(middle.base, "main", "the middle PR is retargeted before its base merges");
+    let tip = pull_request(&ctx, 3);
+    assert_eq!(tip.state, PullRequestState::Open);
+    assert_eq!(tip.base, "main");
+    assert!(ctx.remote_ref_oid(&format!("refs/heads/{tip_id}")).is_some());
+}
+
+#[test]
+fn land_refuses_prs_that_are_not_ready() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    let [_, middle_id, _] = push_landable_stack(&ctx);
+    ctx.github().set_review_decision(1, Some("REVIEW_REQUIRED"));
+    ctx.github().set_check_state(2, Some("PENDING"));
+
+    testutil::assert_failure_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["land", "--upto", middle_id.as_str()]),
+        "land_not_ready"
+    );
+    for number in 1..=3 {
+        assert_eq!(pull_request(&ctx, number).state, PullRequestState::Open);
+    }
+
+    testutil::assert_failure_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["land", "--count", "4"]),
+        "land_count_too_large"
+    );
+
+    // Only the PRs being landed need to be ready.
+    ctx.github().set_review_decision(1, Some("APPROVED"));
+    ctx.gherrit_cmd().args(["land", "--method", "rebase"]).assert().success();
+    assert_eq!(pull_request(&ctx, 1).state, PullRequestState::Merged);
+    assert_eq!(pull_request(&ctx, 1).merge_method.as_deref(), Some("REBASE"));
+    assert_eq!(pull_request(&ctx, 2).state, PullRequestState::Open);
+}
+
+#[test]
+fn land_checks_the_reviews_of_prs_based_on_other_prs() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    push_landable_stack(&ctx);
+    // GitHub only decides on the reviews of PRs based on the default branch,
+    // so the middle PR's decision would only show once it is retargeted.
+    ctx.github().add_review(2, "alice", "COMMENTED");
+    ctx.github().set_review_decision(2, Some("REVIEW_REQUIRED"));
+
+    let output = ctx.gherrit_cmd().args(["land", "--count", "2"]).assert().failure();
+    let stderr = String::from_utf8_lossy(&output.get_output().stderr).into_owned();
+    assert!(stderr.contains("PR #2 is not approved"), "{stderr}");
+    assert_eq!(pull_request(&ctx, 1).state, PullRequestState::Open);
+    assert_ne!(pull_request(&ctx, 2).base, "main");
+
+    ctx.github().add_review(2, "bob", "CHANGES_REQUESTED");
+    ctx.github().add_review(2, "alice", "APPROVED");
+    let output = ctx.gherrit_cmd().args(["land", "--count", "2"]).assert().failure();
+    let stderr = String::from_utf8_lossy(&output.get_output().stderr).into_owned();
+    assert!(stderr.contains("PR #2 has changes requested"), "{stderr}");
+
+    ctx.github().add_review(2, "bob", "APPROVED");
+    ctx.github().set_review_decision(2, Some("APPROVED"));
+    ctx.gherrit_cmd().args(["land", "--count", "2"]).assert().success();
+    assert_eq!(pull_request(&ctx, 2).state, PullRequestState::Merged);
+}
+
+#[test]
+fn land_refuses_prs_that_do_not_match_their_commits() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    push_landable_stack(&ctx);
+    ctx.amend_with_message("Tip work, revised");
+
+    let output = ctx.gherrit_cmd().args(["land", "--count", "3"]).assert().failure();
+    let stderr = String::from_utf8_lossy(&output.get_output().stderr).into_owned();
+    assert!(stderr.contains("PR #3 does not match its local commit"), "{stderr}");
+    assert_eq!(pull_request(&ctx, 1).state, PullRequestState::Open);
+}
+
+#[test]
+fn land_adds_prs_to_the_merge_queue() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    push_landable_stack(&ctx);
+    ctx.github().enable_merge_queue();
+
+    testutil::assert_success_snapshot!(ctx, ctx.gherrit_cmd().arg("land"), "land_merge_queue");
+
+    let bottom = pull_request(&ctx, 1);
+    assert_eq!(bottom.state, PullRequestState::Open);
+    assert!(bottom.is_in_merge_queue);
+    assert_eq!(pull_request(&ctx, 2).base, "main");
+    assert!(!pull_request(&ctx, 2).is_in_merge_queue);
+}
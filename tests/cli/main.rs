@@ -1,5 +1,13 @@
+mod abandon;
+mod checkout;
 mod commit_msg;
+mod gc;
 mod install;
+mod land;
 mod manage;
+mod migrate_refs;
 mod post_checkout;
 mod production;
+mod restack;
+mod status;
+mod sync;
@@ -0,0 +1,46 @@
+fn local_refs(ctx: &testutil::TestContext, prefix: &str) -> Vec<String> {
+    let assert =
+        ctx.git_cmd().args(["for-each-ref", "--format=%(refname)", prefix]).assert().success();
+    String::from_utf8(assert.get_output().stdout.clone()).unwrap().lines().map(Into::into).collect()
+}
+
+#[test]
+fn migrate_refs_moves_version_tags_to_a_dedicated_namespace() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("migrate");
+    let id = ctx.commit_with_gherrit_id("Work");
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.amend_with_message("Work, revised");
+    ctx.hook_cmd("pre-push").assert().success();
+    let expected = [format!("refs/gherrit/{id}/v1"), format!("refs/gherrit/{id}/v2")];
+
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["migrate-refs", "refs"]),
+        "migrate_refs_to_refs"
+    );
+    assert_eq!(ctx.remote_refs("refs/gherrit/"), expected);
+    assert!(ctx.remote_refs("refs/tags/").is_empty());
+    assert_eq!(local_refs(&ctx, "refs/gherrit/"), expected);
+    assert!(local_refs(&ctx, "refs/tags/").is_empty());
+
+    // Migrating again finds nothing left to move.
+    ctx.gherrit_cmd().args(["migrate-refs", "refs"]).assert().success();
+    assert_eq!(ctx.remote_refs("refs/gherrit/"), expected);
+
+    // The next version continues the history in the new layout, and the
+    // compare links name each version by its object ID.
+    ctx.amend_with_message("Work, revised again");
+    ctx.hook_cmd("pre-push").assert().success();
+    let v3 = ctx.remote_ref_oid(&format!("refs/gherrit/{id}/v3")).expect("v3 is pushed");
+    let v2 = ctx.remote_ref_oid(&format!("refs/gherrit/{id}/v2")).unwrap();
+    let body = ctx.github().pull_requests().pop().unwrap().body.unwrap();
+    assert!(body.contains(&format!("/compare/{v2}..{v3})")), "{body}");
+    assert!(!body.contains(&format!("gherrit/{id}/v")));
+    assert!(ctx.remote_refs("refs/tags/").is_empty());
+}
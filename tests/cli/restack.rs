@@ -0,0 +1,97 @@
+use std::fs;
+
+fn commit_file(ctx: &testutil::TestContext, file: &str, contents: &str, message: &str) -> String {
+    fs::write(ctx.repo_path.join(file), contents).unwrap();
+    ctx.run_git(&["add", file]);
+    ctx.commit_with_gherrit_id(message)
+}
+
+fn rev_parse(ctx: &testutil::TestContext, rev: &str) -> String {
+    let assert = ctx.git_cmd().args(["rev-parse", rev]).assert().success();
+    String::from_utf8(assert.get_output().stdout.clone()).unwrap().trim().to_string()
+}
+
+#[test]
+fn restack_drops_landed_commits_and_rebases_the_rest() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("landing");
+    commit_file(&ctx, "base.txt", "base\n", "Base work");
+    commit_file(&ctx, "middle.txt", "middle\n", "Middle work");
+    let tip_id = commit_file(&ctx, "tip.txt", "tip\n", "Tip work");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    // PR #1 is squash-merged with review changes, and the change of PR #2 is
+    // applied upstream without merging its PR.
+    ctx.github().set_pull_request_state(1, testutil::PullRequestState::Merged);
+    ctx.run_git(&["checkout", "main"]);
+    fs::write(ctx.repo_path.join("base.txt"), "base, after review\n").unwrap();
+    fs::write(ctx.repo_path.join("middle.txt"), "middle\n").unwrap();
+    ctx.run_git(&["add", "base.txt"]);
+    ctx.commit("Base work (#1)");
+    ctx.run_git(&["add", "middle.txt"]);
+    ctx.commit("Middle work");
+    ctx.run_git(&["push", "--quiet", "--no-verify", "origin", "main"]);
+    ctx.run_git(&["reset", "--quiet", "--hard", "HEAD~2"]);
+    ctx.run_git(&["checkout", "landing"]);
+
+    testutil::assert_success_snapshot!(ctx, ctx.gherrit_cmd().arg("restack"), "restack_stack");
+
+    let upstream = rev_parse(&ctx, "origin/main");
+    assert_eq!(rev_parse(&ctx, "HEAD~1"), upstream);
+    assert_eq!(rev_parse(&ctx, "main"), upstream, "restack fast-forwards the default branch");
+    assert_eq!(ctx.gherrit_id("HEAD").unwrap(), tip_id);
+
+    ctx.hook_cmd("pre-push").assert().success();
+    let tip_pr = ctx.github().pull_requests().into_iter().find(|pr| pr.number == 3).unwrap();
+    assert_eq!(tip_pr.base, "main");
+}
+
+#[test]
+fn restack_leaves_an_up_to_date_stack_alone() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("current");
+    commit_file(&ctx, "work.txt", "work\n", "Work");
+    ctx.hook_cmd("pre-push").assert().success();
+    let head = ctx.head_oid();
+
+    testutil::assert_success_snapshot!(ctx, ctx.gherrit_cmd().arg("restack"), "restack_up_to_date");
+    assert_eq!(ctx.head_oid(), head);
+
+    ctx.run_git(&["checkout", "main"]);
+    ctx.gherrit_cmd().arg("restack").assert().failure();
+}
+
+#[test]
+fn restack_keeps_commits_merged_into_their_parent() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("nested");
+    let base_id = commit_file(&ctx, "base.txt", "base\n", "Base work");
+    let tip_id = commit_file(&ctx, "tip.txt", "tip\n", "Tip work");
+    ctx.hook_cmd("pre-push").assert().success();
+    let head = ctx.head_oid();
+
+    // PR #2 is merged into the phantom branch of PR #1, so its change never
+    // reached the default branch.
+    let tip_pr = ctx.github().pull_requests().into_iter().find(|pr| pr.number == 2).unwrap();
+    assert_eq!(tip_pr.base, base_id);
+    ctx.github().set_pull_request_state(2, testutil::PullRequestState::Merged);
+
+    ctx.gherrit_cmd().arg("restack").assert().success();
+    assert_eq!(ctx.head_oid(), head);
+    assert_eq!(ctx.gherrit_id("HEAD").unwrap(), tip_id);
+}
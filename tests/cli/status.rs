@@ -0,0 +1,17 @@
+#[test]
+fn status_reports_each_commit_in_the_stack() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("status-stack");
+    ctx.commit_with_gherrit_id("Published work");
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.amend();
+    ctx.commit_with_gherrit_id("Unpublished work");
+
+    testutil::assert_success_snapshot!(ctx, ctx.gherrit_cmd().arg("status"), "status_stack");
+    assert_eq!(ctx.recorded_pushes().len(), 1, "status must not publish anything");
+}
@@ -0,0 +1,98 @@
+use testutil::GraphQlOperation;
+
+#[test]
+fn sync_dry_run_prints_the_plan_without_changing_anything() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("dry-run-stack");
+    ctx.commit_with_gherrit_id("Published base");
+    ctx.commit_with_gherrit_id("Published tip");
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.amend_with_message("Reworded tip");
+    ctx.commit_with_gherrit_id("Unpublished work");
+    let tags = ctx.git_cmd().args(["tag", "--list"]).output().unwrap().stdout;
+    let requests = ctx.github().requests().len();
+
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["sync", "--dry-run"]),
+        "sync_dry_run"
+    );
+
+    assert_eq!(ctx.recorded_pushes().len(), 1, "a dry run must not push");
+    assert_eq!(ctx.git_cmd().args(["tag", "--list"]).output().unwrap().stdout, tags);
+    let dry_run_requests = ctx.github().requests();
+    assert!(
+        dry_run_requests[requests..].iter().flatten().all(|op| *op == GraphQlOperation::Query),
+        "a dry run must only query GitHub"
+    );
+}
+
+#[test]
+fn dry_run_hook_rejects_the_push() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_public("dry-run-hook");
+    ctx.commit_with_gherrit_id("Preview only");
+
+    ctx.hook_cmd("pre-push").env("GHERRIT_DRY_RUN", "1").assert().failure();
+
+    assert!(ctx.recorded_pushes().is_empty());
+    assert!(ctx.github().pull_requests().is_empty());
+}
+
+#[test]
+fn sync_publishes_a_branch_that_is_not_checked_out() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("background-stack");
+    let id = ctx.commit_with_gherrit_id("Background work");
+    let tip = ctx.head_oid();
+    ctx.run_git(&["checkout", "--detach", "main"]);
+
+    ctx.gherrit_cmd().args(["sync", "--branch", "background-stack"]).assert().success();
+
+    assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{id}")).as_deref(), Some(tip.as_str()));
+    let pull_requests = ctx.github().pull_requests();
+    assert_eq!(pull_requests.len(), 1);
+    assert_eq!(pull_requests[0].title.as_deref(), Some("Background work"));
+}
+
+#[test]
+fn sync_rejects_detached_head_and_unmanaged_branches() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_new("plain");
+    ctx.set_config("branch.plain.gherritManaged", Some("false"));
+    ctx.commit("Plain work");
+    ctx.run_git(&["checkout", "--detach"]);
+
+    testutil::assert_failure_snapshot!(ctx, ctx.gherrit_cmd().arg("sync"), "sync_detached_head");
+    testutil::assert_failure_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["sync", "--branch", "plain"]),
+        "sync_unmanaged_branch"
+    );
+    testutil::assert_failure_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["sync", "--branch", "missing"]),
+        "sync_missing_branch"
+    );
+    assert!(ctx.recorded_pushes().is_empty());
+}
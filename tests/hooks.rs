@@ -51,3 +51,83 @@ fn installed_pre_push_blocks_the_enclosing_push() {
     assert_eq!(ctx.remote_ref_oid("refs/heads/blocked-boundary"), None);
     assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{id}")), None);
 }
+
+#[test]
+fn installed_pre_push_syncs_the_pushed_branch_rather_than_head() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_installed_hooks()
+        .with_initial_commit()
+        .with_mock_github()
+        .build();
+
+    ctx.checkout_new("background-boundary");
+    ctx.commit("Background work");
+    let id = ctx.gherrit_id("HEAD").unwrap();
+    let oid = ctx.head_oid();
+    ctx.run_git(&["checkout", "main"]);
+
+    ctx.git_cmd().args(["push", "origin", "background-boundary"]).assert().success();
+
+    assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{id}")).as_deref(), Some(oid.as_str()));
+    assert_eq!(ctx.github().pull_requests().len(), 1);
+}
+
+#[test]
+fn installed_pre_push_publishes_only_the_pushed_part_of_a_stack() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_installed_hooks()
+        .with_initial_commit()
+        .with_mock_github()
+        .build();
+
+    ctx.checkout_new("partial-boundary");
+    ctx.commit("Ready work");
+    ctx.commit("Unfinished work");
+    let ready_id = ctx.gherrit_id("HEAD~1").unwrap();
+    let unfinished_id = ctx.gherrit_id("HEAD").unwrap();
+    let ready_oid = ctx.git_cmd().args(["rev-parse", "HEAD~1"]).output().unwrap().stdout;
+    let ready_oid = String::from_utf8(ready_oid).unwrap();
+
+    ctx.git_cmd().args(["push", "origin", "HEAD~1:refs/heads/partial-boundary"]).assert().success();
+
+    assert_eq!(
+        ctx.remote_ref_oid(&format!("refs/heads/{ready_id}")).as_deref(),
+        Some(ready_oid.trim())
+    );
+    assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{unfinished_id}")), None);
+    let prs = ctx.github().pull_requests();
+    assert_eq!(prs.len(), 1);
+    assert_eq!(prs[0].head, ready_id);
+}
+
+#[test]
+fn installed_pre_push_keeps_prs_above_a_partial_push() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_installed_hooks()
+        .with_initial_commit()
+        .with_mock_github()
+        .build();
+    ctx.set_config("gherrit.orphanedPrPolicy", Some("close"));
+
+    ctx.checkout_new("partial-resume");
+    ctx.commit("Ready work");
+    ctx.commit("Unfinished work");
+    let unfinished_id = ctx.gherrit_id("HEAD").unwrap();
+    ctx.git_cmd().args(["push", "origin", "partial-resume"]).assert().success();
+
+    // The unfinished commit is still on the branch, so its PR is not orphaned.
+    ctx.git_cmd().args(["push", "origin", "+HEAD~1:refs/heads/partial-resume"]).assert().success();
+    let unfinished = ctx.github().pull_requests().into_iter().find(|pr| pr.number == 2).unwrap();
+    assert_eq!(unfinished.head, unfinished_id);
+    assert_eq!(unfinished.state, testutil::PullRequestState::Open);
+    assert!(!unfinished.body.unwrap().contains("removed from its stack"));
+
+    ctx.git_cmd().args(["push", "origin", "partial-resume"]).assert().success();
+    let prs = ctx.github().pull_requests();
+    assert_eq!(prs.len(), 2);
+    assert!(prs.iter().all(|pr| pr.state == testutil::PullRequestState::Open));
+    assert!(prs[0].body.as_deref().unwrap().contains(&unfinished_id));
+}
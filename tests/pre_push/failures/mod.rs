@@ -140,14 +140,19 @@ fn test_pre_push_ls_remote_failure() {
         .build();
     // Manage branch
     ctx.checkout_managed_private("feature-ls-remote-fail");
-    ctx.commit_with_gherrit_id("Work");
+    let gherrit_id = ctx.commit_with_gherrit_id("Work");
 
     let refs_before = ctx.remote_refs("refs");
     ctx.expect_git_failure(testutil::GitOperation::LsRemote);
+    // The managed branch and the version tags are observed at once, so either
+    // query may be the one that fails.
+    let (branch, tags) =
+        (format!("refs/heads/{gherrit_id}"), format!("refs/tags/gherrit/{gherrit_id}/v*"));
     testutil::assert_failure_snapshot!(
         ctx,
         ctx.hook_cmd("pre-push"),
-        "ls_remote_observation_failure"
+        "ls_remote_observation_failure",
+        &[(branch.as_str(), "[OBSERVED_REF]"), (tags.as_str(), "[OBSERVED_REF]")]
     );
 
     ctx.assert_failure_consumed();
@@ -207,3 +212,309 @@ fn test_pre_push_pr_create_failure() {
     assert!(ctx.github().pull_requests().is_empty());
     assert_eq!(ctx.recorded_pushes().iter().filter(|push| push.succeeded()).count(), 1);
 }
+
+/// Creates `ref_name` on the remote, pointing to its default branch, but hides
+/// it from `git ls-remote`, as if another push created it after GHerrit
+/// observed the remote.
+fn create_unobserved_remote_ref(ctx: &testutil::TestContext, ref_name: &str) {
+    let main = ctx.remote_ref_oid("refs/heads/main").unwrap();
+    ctx.remote_git_cmd().args(["update-ref", ref_name, &main]).assert().success();
+    ctx.remote_git_cmd()
+        .args(["config", "--add", "uploadpack.hideRefs", ref_name])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_push_retries_when_another_push_takes_the_version() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-taken-version");
+    let gherrit_id = ctx.commit_with_gherrit_id("Work");
+    create_unobserved_remote_ref(&ctx, &format!("refs/tags/gherrit/{gherrit_id}/v1"));
+
+    ctx.hook_cmd("pre-push")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("retrying with the next versions"));
+
+    let pushes = ctx.recorded_pushes();
+    assert_eq!(pushes.len(), 2);
+    assert!(!pushes[0].succeeded() && pushes[1].succeeded());
+    assert_eq!(
+        ctx.remote_ref_oid(&format!("refs/tags/gherrit/{gherrit_id}/v2")).as_deref(),
+        Some(ctx.head_oid().as_str())
+    );
+    let body = ctx.github().pull_requests()[0].body.clone().unwrap();
+    assert!(body.contains("**Latest Update:** v2"));
+}
+
+#[test]
+fn test_push_reports_a_stale_branch_lease() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-stale-lease");
+    let gherrit_id = ctx.commit_with_gherrit_id("Work");
+    create_unobserved_remote_ref(&ctx, &format!("refs/heads/{gherrit_id}"));
+
+    ctx.hook_cmd("pre-push").assert().failure().stderr(predicate::str::contains(format!(
+        "{gherrit_id}: managed branch {gherrit_id} changed on the remote since it was observed"
+    )));
+
+    assert_eq!(ctx.recorded_pushes().len(), 1, "A stale branch must not be retried");
+    assert!(ctx.remote_ref_oid(&format!("refs/tags/gherrit/{gherrit_id}/v1")).is_none());
+    assert!(ctx.github().pull_requests().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_push_reports_a_ref_that_the_remote_refuses() {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-protected");
+    let gherrit_id = ctx.commit_with_gherrit_id("Work");
+
+    let hooks = ctx.dir.path().join("remote-hooks");
+    std::fs::create_dir(&hooks).unwrap();
+    let hook = hooks.join("pre-receive");
+    std::fs::write(&hook, "#!/bin/sh\necho 'protected branch hook declined' >&2\nexit 1\n")
+        .unwrap();
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+    ctx.remote_git_cmd()
+        .args(["config", "core.hooksPath", hooks.to_str().unwrap()])
+        .assert()
+        .success();
+
+    ctx.hook_cmd("pre-push").assert().failure().stderr(predicate::str::contains(format!(
+        "{gherrit_id}: the remote refused refs/heads/{gherrit_id} (pre-receive hook declined)"
+    )));
+    assert_eq!(ctx.recorded_pushes().len(), 1);
+}
+
+#[test]
+fn test_push_reports_an_unreachable_remote() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-unreachable");
+    ctx.commit_with_gherrit_id("Work");
+    // Observation reads the remote's fetch URL, so only the push fails.
+    ctx.run_git(&["config", "remote.origin.pushurl", "missing/repo.git"]);
+
+    ctx.hook_cmd("pre-push")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("authentication or network error"));
+    assert_eq!(ctx.recorded_pushes().len(), 1);
+    assert!(ctx.github().pull_requests().is_empty());
+}
+
+/// Builds a stack one commit larger than a push batch, whose top commit's
+/// managed branch was created on the remote after GHerrit observed it, so that
+/// the second push batch fails.
+fn stack_whose_second_push_batch_fails(branch_name: &str) -> testutil::TestContext {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private(branch_name);
+    let mut gherrit_id = String::new();
+    for i in 0..81 {
+        gherrit_id = ctx.commit_with_gherrit_id(&format!("Commit {i}"));
+    }
+    create_unobserved_remote_ref(&ctx, &format!("refs/heads/{gherrit_id}"));
+    ctx
+}
+
+fn publication_journal(ctx: &testutil::TestContext) -> std::path::PathBuf {
+    ctx.repo_path.join(".git/gherrit/publication.json")
+}
+
+#[test]
+fn test_failed_push_batch_rolls_back_the_published_batches() {
+    let ctx = stack_whose_second_push_batch_fails("feature-rolled-back");
+
+    ctx.hook_cmd("pre-push")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "rolled back the 160 refs that its earlier push batches published",
+        ))
+        .stderr(predicate::str::contains("changed on the remote since it was observed"));
+
+    let pushes = ctx.recorded_pushes();
+    assert_eq!(pushes.len(), 4, "Two batches and a rollback of two batches");
+    assert!(pushes[0].succeeded() && !pushes[1].succeeded());
+    assert!(pushes[2..].iter().all(testutil::PushRecord::succeeded));
+    assert_eq!(ctx.remote_refs("refs/heads/").len(), 2, "Only main and the hidden branch remain");
+    assert!(ctx.remote_refs("refs/tags/gherrit/").is_empty());
+    let local_tags = ctx.git_cmd().args(["tag", "--list", "gherrit/*"]).output().unwrap();
+    assert!(local_tags.stdout.is_empty(), "No version is persisted locally");
+    assert!(!publication_journal(&ctx).exists());
+    assert!(ctx.github().pull_requests().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unfinished_rollback_is_finished_by_the_next_sync() {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let ctx = stack_whose_second_push_batch_fails("feature-unfinished-rollback");
+    // The remote refuses every deletion, and so the rollback.
+    let hooks = ctx.dir.path().join("remote-hooks");
+    std::fs::create_dir(&hooks).unwrap();
+    let hook = hooks.join("pre-receive");
+    std::fs::write(
+        &hook,
+        "#!/bin/sh\nwhile read old new ref; do\n  \
+         case $new in 0000000000000000000000000000000000000000) exit 1;; esac\ndone\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+    ctx.remote_git_cmd()
+        .args(["config", "core.hooksPath", hooks.to_str().unwrap()])
+        .assert()
+        .success();
+
+    ctx.hook_cmd("pre-push")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("The next sync retries the rollback."));
+    assert!(publication_journal(&ctx).exists());
+    assert_eq!(ctx.remote_refs("refs/tags/gherrit/").len(), 80);
+
+    // A dry run only reports the unfinished publication.
+    ctx.gherrit_cmd()
+        .args(["sync", "--dry-run"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("An earlier sync left a partial publication"));
+    assert!(publication_journal(&ctx).exists());
+
+    ctx.remote_git_cmd().args(["config", "--unset", "core.hooksPath"]).assert().success();
+    ctx.hook_cmd("pre-push")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Rolling back the partial publication of an earlier sync"))
+        .stderr(predicate::str::contains("rolled back the 160 refs"));
+    assert!(!publication_journal(&ctx).exists());
+    assert!(ctx.remote_refs("refs/tags/gherrit/").is_empty());
+}
+
+fn sync_journal(ctx: &testutil::TestContext, branch_name: &str) -> std::path::PathBuf {
+    ctx.repo_path.join(format!(".git/gherrit/sync/{branch_name}.json"))
+}
+
+#[test]
+fn test_sync_interrupted_by_pr_updates_is_resumed_without_pushing() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-interrupted-update");
+    ctx.commit_with_gherrit_id("Work");
+    ctx.inject_failure(testutil::FailureKind::UpdatePr);
+
+    ctx.hook_cmd("pre-push")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Injected UpdatePr failure"));
+    ctx.assert_failure_consumed();
+    let journal =
+        std::fs::read_to_string(sync_journal(&ctx, "feature-interrupted-update")).unwrap();
+    assert!(journal.contains("\"created\""));
+    assert_eq!(ctx.github().pull_requests().len(), 1);
+
+    ctx.gherrit_cmd().args(["sync", "--resume"]).assert().success().stderr(
+        predicate::str::contains(
+            "Resuming the interrupted sync of feature-interrupted-update: 1 PRs created.",
+        ),
+    );
+
+    assert_eq!(ctx.recorded_pushes().len(), 1, "A resumed sync must not push again");
+    let pull_requests = ctx.github().pull_requests();
+    assert_eq!(pull_requests.len(), 1);
+    assert!(pull_requests[0].body.as_deref().unwrap().contains("gherrit-meta"));
+    assert!(!sync_journal(&ctx, "feature-interrupted-update").exists());
+}
+
+#[test]
+fn test_sync_interrupted_by_pr_creation_resumes_on_the_next_hook_run() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-interrupted-create");
+    ctx.commit_with_gherrit_id("Work");
+    ctx.inject_failure(testutil::FailureKind::CreatePr);
+
+    ctx.hook_cmd("pre-push").assert().failure();
+    ctx.assert_failure_consumed();
+    assert!(sync_journal(&ctx, "feature-interrupted-create").exists());
+
+    ctx.hook_cmd("pre-push")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Resuming the interrupted sync"));
+
+    assert_eq!(ctx.recorded_pushes().len(), 1, "A resumed sync must not push again");
+    assert_eq!(ctx.github().pull_requests().len(), 1);
+    assert!(!sync_journal(&ctx, "feature-interrupted-create").exists());
+}
+
+#[test]
+fn test_resume_requires_an_unchanged_interrupted_sync() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-resume");
+    ctx.commit_with_gherrit_id("Work");
+
+    ctx.gherrit_cmd()
+        .args(["sync", "--resume"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("has no interrupted sync to resume"));
+
+    ctx.inject_failure(testutil::FailureKind::UpdatePr);
+    ctx.hook_cmd("pre-push").assert().failure();
+    ctx.amend_with_message("Work, revised");
+    ctx.gherrit_cmd()
+        .args(["sync", "--resume"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("changed since its interrupted sync"));
+    assert_eq!(ctx.recorded_pushes().len(), 1);
+
+    // A sync without `--resume` pushes the changed stack and replaces the
+    // journal.
+    ctx.hook_cmd("pre-push").assert().success();
+    assert_eq!(ctx.recorded_pushes().len(), 2);
+    assert!(!sync_journal(&ctx, "feature-resume").exists());
+}
@@ -0,0 +1,110 @@
+use predicates::prelude::*;
+
+fn sync_lock(ctx: &testutil::TestContext) -> std::path::PathBuf {
+    ctx.repo_path.join(".git/gherrit/sync.lock")
+}
+
+/// Records `pid` as the holder of the sync lock, without locking it.
+fn write_sync_lock(ctx: &testutil::TestContext, pid: u32) {
+    std::fs::create_dir_all(sync_lock(ctx).parent().unwrap()).unwrap();
+    let holder = serde_json::json!({
+        "pid": pid,
+        "acquired_at": 0,
+        "worktree": "/elsewhere",
+        "command": "gherrit hook pre-push",
+    });
+    std::fs::write(sync_lock(ctx), holder.to_string()).unwrap();
+}
+
+/// Holds the sync lock on behalf of this process until the file is dropped.
+fn hold_sync_lock(ctx: &testutil::TestContext) -> std::fs::File {
+    write_sync_lock(ctx, std::process::id());
+    let file = std::fs::File::open(sync_lock(ctx)).unwrap();
+    file.try_lock().unwrap();
+    file
+}
+
+#[test]
+fn test_sync_fails_while_another_sync_holds_the_lock() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-locked");
+    ctx.commit_with_gherrit_id("Work");
+    // The test itself stands in for a running sync.
+    let pid = std::process::id();
+    let _held = hold_sync_lock(&ctx);
+
+    let assert = ctx
+        .hook_cmd("pre-push")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Another GHerrit sync holds"))
+        .stderr(predicate::str::contains(".git/gherrit/sync.lock"));
+    // Windows does not let other processes read a locked file.
+    if cfg!(unix) {
+        assert.stderr(predicate::str::contains(format!(
+            "process {pid}, running `gherrit hook pre-push` in /elsewhere"
+        )));
+    }
+    ctx.gherrit_cmd()
+        .args(["sync"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Another GHerrit sync holds"));
+
+    assert!(ctx.recorded_pushes().is_empty());
+    assert!(ctx.github().requests().is_empty());
+}
+
+#[test]
+fn test_sync_takes_over_the_lock_of_an_exited_sync() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-stale-lock");
+    ctx.commit_with_gherrit_id("Work");
+    // The OS released the lock of the exited sync, which left its file.
+    write_sync_lock(&ctx, 4242);
+
+    ctx.hook_cmd("pre-push").assert().success();
+
+    assert_eq!(ctx.github().pull_requests().len(), 1);
+    let lock = std::fs::File::open(sync_lock(&ctx)).unwrap();
+    lock.try_lock().expect("The lock is released after the sync");
+}
+
+#[test]
+fn test_lock_guards_only_managed_stacks_and_ref_rewrites() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_new("feature-unmanaged");
+    ctx.unmanage_cmd().assert().success();
+    ctx.commit("Unmanaged work");
+    let _held = hold_sync_lock(&ctx);
+
+    // A push of an unmanaged branch syncs nothing, so it need not wait.
+    ctx.hook_cmd("pre-push")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Branch feature-unmanaged is UNMANAGED"));
+
+    // Collecting and migrating version refs race with a sync's version refs.
+    for args in [&["gc"][..], &["migrate-refs", "refs"]] {
+        ctx.gherrit_cmd()
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Another GHerrit sync holds"));
+    }
+}
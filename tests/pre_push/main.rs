@@ -1,4 +1,5 @@
 mod compatibility;
 mod failures;
+mod lock;
 mod policy;
 mod publication;
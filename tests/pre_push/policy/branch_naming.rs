@@ -0,0 +1,91 @@
+#[test]
+fn branch_template_names_managed_branches() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.set_config("gherrit.branchTemplate", Some("gherrit/{user}/{id}"));
+    ctx.set_config("gherrit.user", Some("octo"));
+    ctx.checkout_managed_private("feature-named");
+    let base_id = ctx.commit_with_gherrit_id("Base");
+    let tip_id = ctx.commit_with_gherrit_id("Tip");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    let (base_branch, tip_branch) =
+        (format!("gherrit/octo/{base_id}"), format!("gherrit/octo/{tip_id}"));
+    assert_eq!(
+        ctx.remote_refs("refs/heads/gherrit/"),
+        [format!("refs/heads/{base_branch}"), format!("refs/heads/{tip_branch}")]
+    );
+    assert!(ctx.remote_ref_oid(&format!("refs/tags/gherrit/{tip_id}/v1")).is_some());
+    let pull_requests = ctx.github().pull_requests();
+    assert_eq!(pull_requests.len(), 2);
+    assert_eq!(
+        (pull_requests[0].head.as_str(), pull_requests[0].base.as_str()),
+        (base_branch.as_str(), "main")
+    );
+    assert_eq!(
+        (pull_requests[1].head.as_str(), pull_requests[1].base.as_str()),
+        (tip_branch.as_str(), base_branch.as_str())
+    );
+
+    let base_body = pull_requests[0].body.as_deref().unwrap();
+    assert!(base_body.contains(&format!(
+        r#"<!-- gherrit-meta: {{"id":"{base_id}","parent":null,"child":"{tip_id}","branch":"{base_branch}","child_branch":"{tip_branch}"}} -->"#
+    )));
+    assert!(base_body.contains(&format!("git pull origin refs/heads/{base_branch}")));
+
+    // Someone else's user name does not rename the branches of pushed commits.
+    ctx.set_config("gherrit.user", Some("someone-else"));
+    ctx.amend_with_message("Tip, revised");
+    ctx.hook_cmd("pre-push").assert().success();
+    let pull_requests = ctx.github().pull_requests();
+    assert_eq!(pull_requests.len(), 2);
+    assert_eq!(pull_requests[1].head, tip_branch);
+    assert!(ctx.remote_ref_oid(&format!("refs/tags/gherrit/{tip_id}/v2")).is_some());
+    assert!(ctx.remote_refs("refs/heads/gherrit/someone-else/").is_empty());
+}
+
+#[test]
+fn branch_template_keeps_branches_named_by_id() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-legacy");
+    let base_id = ctx.commit_with_gherrit_id("Base");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    ctx.set_config("gherrit.branchTemplate", Some("{id}-{slug}"));
+    let tip_id = ctx.commit_with_gherrit_id("Add the widget parser");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    let pull_requests = ctx.github().pull_requests();
+    assert_eq!(pull_requests.len(), 2);
+    assert_eq!(pull_requests[0].head, base_id);
+    let tip_branch = format!("{tip_id}-add-the-widget-parser");
+    assert_eq!(
+        (pull_requests[1].head.as_str(), pull_requests[1].base.as_str()),
+        (tip_branch.as_str(), base_id.as_str())
+    );
+    assert!(ctx.remote_ref_oid(&format!("refs/heads/{tip_branch}")).is_some());
+}
+
+#[test]
+fn invalid_branch_template_fails_the_sync() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.set_config("gherrit.branchTemplate", Some("gherrit/{user}"));
+    ctx.checkout_managed_private("feature-invalid");
+    ctx.commit_with_gherrit_id("Base");
+    testutil::assert_failure_snapshot!(ctx, ctx.hook_cmd("pre-push"), "invalid_branch_template");
+    assert!(ctx.github().pull_requests().is_empty());
+}
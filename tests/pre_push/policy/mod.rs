@@ -1,3 +1,4 @@
+mod branch_naming;
 mod pr_state;
 mod unmanaged;
 
@@ -42,3 +42,396 @@ fn non_open_pr_blocks_all_mutations() {
         "rejected push must only observe GitHub state"
     );
 }
+
+#[test]
+fn closed_pr_policy_reopens_closed_prs() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-reopen");
+    ctx.commit_with_gherrit_id("Closed by mistake");
+    ctx.commit_with_gherrit_id("Still open");
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.github().set_pull_request_state(1, testutil::PullRequestState::Closed);
+
+    ctx.set_config("gherrit.closedPrPolicy", Some("bogus"));
+    testutil::assert_failure_snapshot!(ctx, ctx.hook_cmd("pre-push"), "closed_pr_policy_invalid",);
+
+    ctx.set_config("gherrit.closedPrPolicy", Some("reopen"));
+    testutil::assert_success_snapshot!(ctx, ctx.hook_cmd("pre-push"), "closed_pr_reopened");
+
+    let states: Vec<_> = ctx.github().pull_requests().into_iter().map(|pr| pr.state).collect();
+    assert_eq!(states, [testutil::PullRequestState::Open, testutil::PullRequestState::Open]);
+    assert!(ctx.github().requests().concat().contains(&testutil::GraphQlOperation::ReopenPr));
+}
+
+#[test]
+fn closed_pr_policy_recreates_closed_prs() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-recreate");
+    let closed_id = ctx.commit_with_gherrit_id("Closed by mistake");
+    let tip_id = ctx.commit_with_gherrit_id("Still open");
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.github().set_pull_request_state(1, testutil::PullRequestState::Closed);
+
+    // A dry run rewrites no commits, not even in the object database.
+    let count_objects = || {
+        let output = ctx.git_cmd().args(["count-objects", "-v"]).assert().success();
+        String::from_utf8(output.get_output().stdout.clone()).unwrap()
+    };
+    let objects = count_objects();
+    let head = ctx.head_oid();
+    ctx.gherrit_cmd().args(["sync", "--dry-run", "--on-closed-pr", "recreate"]).assert().success();
+    assert_eq!(count_objects(), objects);
+    assert_eq!(ctx.head_oid(), head);
+
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["sync", "--on-closed-pr", "recreate"]),
+        "closed_pr_recreated",
+    );
+
+    let fresh_id = ctx.gherrit_id("HEAD~1").unwrap();
+    assert_ne!(fresh_id, closed_id);
+    assert_eq!(ctx.gherrit_id("HEAD").unwrap(), tip_id);
+
+    let pull_requests = ctx.github().pull_requests();
+    let summary: Vec<_> = pull_requests
+        .iter()
+        .map(|pr| (pr.number, pr.state, pr.head.as_str(), pr.base.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (1, testutil::PullRequestState::Closed, closed_id.as_str(), "main"),
+            (2, testutil::PullRequestState::Open, tip_id.as_str(), fresh_id.as_str()),
+            (3, testutil::PullRequestState::Open, fresh_id.as_str(), "main"),
+        ]
+    );
+}
+
+#[test]
+fn recreating_prs_rejects_the_stale_push_of_a_public_branch() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_public("feature-public-recreate");
+    let closed_id = ctx.commit_with_gherrit_id("Closed by mistake");
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.github().set_pull_request_state(1, testutil::PullRequestState::Closed);
+    ctx.set_config("gherrit.closedPrPolicy", Some("recreate"));
+
+    testutil::assert_failure_snapshot!(ctx, ctx.hook_cmd("pre-push"), "closed_pr_recreated_public",);
+
+    assert_ne!(ctx.gherrit_id("HEAD").unwrap(), closed_id);
+    assert_eq!(ctx.github().pull_requests().len(), 2);
+}
+
+#[test]
+fn orphaned_prs_are_marked_as_removed_from_the_stack() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-orphan");
+    let base_id = ctx.commit_with_gherrit_id("Base");
+    let middle_id = ctx.commit_with_gherrit_id("Middle");
+    ctx.commit_with_gherrit_id("Tip");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    // Drop the middle commit.
+    ctx.run_git(&["rebase", "--quiet", "--onto", "HEAD~2", "HEAD~1"]);
+    testutil::assert_success_snapshot!(ctx, ctx.hook_cmd("pre-push"), "orphaned_pr_marked");
+
+    let pull_requests = ctx.github().pull_requests();
+    let orphan = &pull_requests[1];
+    assert_eq!(orphan.state, testutil::PullRequestState::Open);
+    let body = orphan.body.as_deref().unwrap();
+    assert!(body.contains("This PR's commit was removed from its stack."));
+    assert!(body.contains(&format!(
+        r#"<!-- gherrit-meta: {{"id":"{middle_id}","parent":null,"child":null}} -->"#
+    )));
+    assert_eq!(pull_requests[2].base, base_id);
+
+    // The marked PR no longer links to the stack, so it is found only once.
+    let pull_requests_before = ctx.github().pull_requests();
+    ctx.hook_cmd("pre-push").assert().success();
+    assert_eq!(ctx.github().pull_requests(), pull_requests_before);
+}
+
+#[test]
+fn orphaned_pr_policy_closes_removed_prs() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-orphan-close");
+    ctx.commit_with_gherrit_id("Base");
+    ctx.commit_with_gherrit_id("Middle");
+    ctx.commit_with_gherrit_id("Tip");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    // Drop the top two commits.
+    ctx.run_git(&["reset", "--quiet", "--hard", "HEAD~2"]);
+    testutil::assert_success_snapshot!(
+        ctx,
+        ctx.gherrit_cmd().args(["sync", "--dry-run", "--on-orphaned-pr", "close"]),
+        "orphaned_pr_close_dry_run",
+    );
+    assert!(
+        ctx.github().pull_requests().iter().all(|pr| pr.state == testutil::PullRequestState::Open)
+    );
+
+    ctx.set_config("gherrit.orphanedPrPolicy", Some("close"));
+    testutil::assert_success_snapshot!(ctx, ctx.hook_cmd("pre-push"), "orphaned_pr_closed");
+
+    let states: Vec<_> = ctx.github().pull_requests().into_iter().map(|pr| pr.state).collect();
+    assert_eq!(
+        states,
+        [
+            testutil::PullRequestState::Open,
+            testutil::PullRequestState::Closed,
+            testutil::PullRequestState::Closed,
+        ]
+    );
+}
+
+#[test]
+fn draft_trailers_and_prefixes_control_draft_prs() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-drafts");
+    ctx.set_config("gherrit.draftPrefix", Some("WIP:"));
+    ctx.commit_with_gherrit_id("WIP: Sketch the API");
+    let id = ctx.commit_with_gherrit_id("Implement the API");
+    ctx.amend_with_message(&format!(
+        "Implement the API\n\nServes the sketched endpoints.\n\ngherrit-draft: true\n\
+         gherrit-pr-id: {id}"
+    ));
+    ctx.hook_cmd("pre-push").assert().success();
+
+    let drafts =
+        || ctx.github().pull_requests().into_iter().map(|pr| pr.is_draft).collect::<Vec<_>>();
+    assert_eq!(drafts(), [true, true]);
+    // The trailer controls the PR, so its description leaves it out.
+    let body = ctx.github().pull_requests()[1].body.clone().unwrap();
+    assert!(body.contains("Serves the sketched endpoints."));
+    assert!(!body.contains("gherrit-draft"), "{body}");
+    let requests_before = ctx.github().requests().len();
+
+    ctx.amend_with_message(&format!(
+        "Implement the API\n\ngherrit-draft: false\ngherrit-pr-id: {id}"
+    ));
+    ctx.hook_cmd("pre-push").assert().success();
+    assert_eq!(drafts(), [true, false]);
+    let requests = ctx.github().requests()[requests_before..].concat();
+    assert!(requests.contains(&testutil::GraphQlOperation::MarkPrReady));
+    assert!(!requests.contains(&testutil::GraphQlOperation::ConvertPrToDraft));
+
+    ctx.amend_with_message(&format!(
+        "Implement the API\n\ngherrit-draft: maybe\ngherrit-pr-id: {id}"
+    ));
+    ctx.hook_cmd("pre-push").assert().failure().stderr(predicates::str::contains(
+        "has invalid gherrit-draft trailer 'maybe'; expected 'true' or 'false'",
+    ));
+}
+
+#[test]
+fn triage_trailers_request_reviewers_labels_and_assignees() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-triage");
+    let id = ctx.commit_with_gherrit_id("Speed up parsing");
+    ctx.amend_with_message(&format!(
+        "Speed up parsing\n\ngherrit-reviewers: @alice, @org/team\ngherrit-labels: perf\n\
+         gherrit-assignee: @me\ngherrit-pr-id: {id}"
+    ));
+    ctx.hook_cmd("pre-push").assert().success();
+
+    let pr = ctx.github().pull_requests().remove(0);
+    assert_eq!(pr.review_requests, ["alice", "org/team"]);
+    assert_eq!(pr.labels, ["perf"]);
+    assert_eq!(pr.assignees, ["test-user"]);
+    // The metadata records the triage, so the description leaves the
+    // trailers out.
+    let body = pr.body.unwrap();
+    for trailer in ["gherrit-reviewers", "gherrit-labels", "gherrit-assignee"] {
+        assert!(!body.contains(trailer), "{body}");
+    }
+
+    // A reviewer removed on GitHub stays removed unless the trailers add it
+    // again; only the new reviewer is requested.
+    ctx.github().clear_review_requests(1);
+    let requests_before = ctx.github().requests().len();
+    ctx.amend_with_message(&format!(
+        "Speed up parsing\n\ngherrit-reviewers: @alice, @org/team, @bob\ngherrit-labels: perf\n\
+         gherrit-assignee: @me\ngherrit-pr-id: {id}"
+    ));
+    ctx.hook_cmd("pre-push").assert().success();
+    let pr = ctx.github().pull_requests().remove(0);
+    assert_eq!(pr.review_requests, ["bob"]);
+    assert!(!pr.body.unwrap().contains("gherrit-reviewers"));
+    let requests = ctx.github().requests()[requests_before..].concat();
+    assert!(!requests.contains(&testutil::GraphQlOperation::AddLabels));
+    assert!(!requests.contains(&testutil::GraphQlOperation::AddAssignees));
+
+    ctx.amend_with_message(&format!(
+        "Speed up parsing\n\ngherrit-assignees: @org/team\ngherrit-pr-id: {id}"
+    ));
+    ctx.hook_cmd("pre-push").assert().failure().stderr(predicates::str::contains(
+        "The gherrit-assignees trailer names '@org/team', which is not a GitHub user",
+    ));
+}
+
+#[test]
+fn changed_diffs_rerequest_review_from_previous_reviewers() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-rereview");
+    let edit = |contents: &str| {
+        std::fs::write(ctx.repo_path.join("parser.rs"), contents).unwrap();
+        ctx.run_git(&["add", "parser.rs"]);
+    };
+    edit("fn parse() {}\n");
+    ctx.commit_with_gherrit_id("Add a parser");
+    ctx.hook_cmd("pre-push").assert().success();
+    for reviewer in ["alice", "test-user", "ci[bot]", "bob"] {
+        ctx.github().add_review(1, reviewer, "COMMENTED");
+    }
+    let review_requests = || ctx.github().pull_requests()[0].review_requests.clone();
+
+    // Rewording the commit leaves its diff, and so its reviews, alone.
+    ctx.amend_with_message("Add a fast parser");
+    ctx.hook_cmd("pre-push").assert().success();
+    assert_eq!(ctx.github().pull_requests()[0].title.as_deref(), Some("Add a fast parser"));
+    assert_eq!(review_requests(), Vec::<String>::new());
+
+    edit("fn parse() -> bool { true }\n");
+    ctx.amend();
+    ctx.hook_cmd("pre-push").assert().success();
+    assert_eq!(review_requests(), ["alice", "bob"]);
+
+    ctx.github().clear_review_requests(1);
+    ctx.set_config("gherrit.rereviewPolicy", Some("never"));
+    edit("fn parse() -> bool { false }\n");
+    ctx.amend();
+    ctx.hook_cmd("pre-push").assert().success();
+    assert_eq!(review_requests(), Vec::<String>::new());
+}
+
+#[test]
+fn new_versions_of_queued_prs_require_allow_requeue() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-queued");
+    ctx.commit_with_gherrit_id("Land first");
+    let tip_id = ctx.commit_with_gherrit_id("Land second");
+    ctx.hook_cmd("pre-push").assert().success();
+    let rewrite_stack = || {
+        ctx.run_git(&[
+            "rebase",
+            "--quiet",
+            "--exec",
+            "git commit --amend --allow-empty --no-edit --quiet",
+            "HEAD~2",
+        ])
+    };
+
+    // A new version of an approved PR only warrants a warning.
+    ctx.github().set_review_decision(1, Some("APPROVED"));
+    rewrite_stack();
+    ctx.hook_cmd("pre-push").assert().success().stderr(predicates::str::contains(
+        "PR #1 is approved; pushing a new version of it may dismiss its approvals.",
+    ));
+
+    // Only the tip changes, so the queued PR is left alone.
+    ctx.github().set_in_merge_queue(1, true);
+    ctx.amend_with_message("Land the second change");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    rewrite_stack();
+    let published_tip = ctx.remote_ref_oid(&format!("refs/heads/{tip_id}"));
+    ctx.hook_cmd("pre-push").assert().failure().stderr(predicates::str::contains(
+        "Syncing would remove #1 (new version) from the merge queue.",
+    ));
+    assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{tip_id}")), published_tip);
+
+    ctx.hook_cmd("pre-push").env("GHERRIT_ALLOW_REQUEUE", "1").assert().success();
+    assert_eq!(ctx.remote_ref_oid(&format!("refs/heads/{tip_id}")), Some(ctx.head_oid()));
+}
+
+#[test]
+fn updates_of_queued_prs_require_allow_requeue() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+    ctx.checkout_managed_private("feature-queued");
+    let bottom_id = ctx.commit_with_gherrit_id("Land first");
+    ctx.amend_with_message(&format!(
+        "Land first\n\ngherrit-draft: true\ngherrit-pr-id: {bottom_id}"
+    ));
+    ctx.commit_with_gherrit_id("Land second");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    // The author marks the draft ready on GitHub, and landing both PRs
+    // retargets the second one to the default branch.
+    ctx.github().set_draft(1, false);
+    for number in [1, 2] {
+        ctx.github().add_review(number, "alice", "APPROVED");
+        ctx.github().set_review_decision(number, Some("APPROVED"));
+        ctx.github().set_check_state(number, Some("SUCCESS"));
+    }
+    ctx.github().enable_merge_queue();
+    ctx.gherrit_cmd().args(["land", "--count", "2"]).assert().success();
+    let pr = |number: usize| {
+        ctx.github().pull_requests().into_iter().find(|pr| pr.number == number).unwrap()
+    };
+    assert!(pr(1).is_in_merge_queue && pr(2).is_in_merge_queue);
+
+    // Nothing is pushed, but restoring the draft and the base branch would
+    // still dequeue the PRs.
+    ctx.hook_cmd("pre-push").assert().failure().stderr(predicates::str::contains(
+        "Syncing would remove #1 (conversion to draft), #2 (new base branch) from the merge \
+         queue.",
+    ));
+    assert!(!pr(1).is_draft);
+    assert_eq!(pr(2).base, "main");
+
+    ctx.hook_cmd("pre-push").env("GHERRIT_ALLOW_REQUEUE", "1").assert().success();
+    assert!(pr(1).is_draft);
+    assert_ne!(pr(2).base, "main");
+}
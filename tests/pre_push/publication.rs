@@ -141,7 +141,7 @@ fn test_version_increment() {
 }
 
 #[test]
-fn test_optimistic_locking_conflict() {
+fn test_remote_versions_take_precedence() {
     let ctx = testutil::test_context!()
         .with_remote()
         .with_initial_commit()
@@ -160,9 +160,8 @@ fn test_optimistic_locking_conflict() {
     let managed_ref = format!("refs/heads/{gherrit_id}");
     let pushed_oid = ctx.remote_ref_oid(&managed_ref).expect("Managed ref was not pushed");
 
-    // Simulate race condition: Create v2 tag on REMOTE manually. The next
-    // version should be v2 (since v1 exists). Note that in a bare repo, we can
-    // create refs directly.
+    // Simulate a push from another machine: Create the v2 tag on the REMOTE
+    // only. Note that in a bare repo, we can create refs directly.
     let tag_name = format!("gherrit/{}/v2", gherrit_id);
 
     // Create tag pointing to the branch we just pushed
@@ -178,18 +177,53 @@ fn test_optimistic_locking_conflict() {
     let new_msg = format!("Commit V1 (Amended)\n\ngherrit-pr-id: {}", gherrit_id);
     ctx.amend_with_message(&new_msg);
 
-    // Attempt push - should fail due to atomic lock
-    testutil::assert_failure_snapshot!(ctx, ctx.hook_cmd("pre-push"), "optimistic_locking_v2_fail");
+    // The remote's v2 is restored locally, and the amended commit becomes v3
+    // rather than colliding with it.
+    testutil::assert_success_snapshot!(ctx, ctx.hook_cmd("pre-push"), "remote_versions_v3");
 
     let pushes = ctx.recorded_pushes();
-    assert_eq!(pushes.len(), 2, "Expected one successful and one failed push");
-    assert!(pushes[0].succeeded(), "Initial push should succeed");
-    assert!(!pushes[1].succeeded(), "Conflicting push should fail");
-    assert_eq!(
-        ctx.remote_ref_oid(&managed_ref).as_deref(),
-        Some(pushed_oid.as_str()),
-        "Failed atomic push must not update the managed ref"
-    );
+    assert_eq!(pushes.len(), 2, "Expected one push per published version");
+    assert!(pushes.iter().all(testutil::PushRecord::succeeded));
+    let v3_ref = format!("refs/tags/gherrit/{gherrit_id}/v3");
+    assert_eq!(ctx.remote_ref_oid(&v3_ref).as_deref(), Some(ctx.head_oid().as_str()));
+    ctx.git_cmd()
+        .args(["rev-parse", "--verify", "--quiet", &format!("refs/tags/{tag_name}")])
+        .assert()
+        .success()
+        .stdout(format!("{pushed_oid}\n"));
+}
+
+#[test]
+fn test_versions_continue_after_local_tags_are_deleted() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+
+    ctx.checkout_managed_private("feature-lost-tags");
+    ctx.commit_with_gherrit_id("Lost Tags");
+    let gherrit_id = ctx.gherrit_id("HEAD").unwrap();
+    ctx.hook_cmd("pre-push").assert().success();
+    ctx.amend();
+    ctx.hook_cmd("pre-push").assert().success();
+
+    // Lose every local version tag, as in a fresh clone.
+    for version in ["v1", "v2"] {
+        ctx.run_git(&["tag", "-d", &format!("gherrit/{gherrit_id}/{version}")]);
+    }
+    ctx.amend();
+    ctx.hook_cmd("pre-push").assert().success();
+
+    let tags = ctx.remote_refs(&format!("refs/tags/gherrit/{gherrit_id}/"));
+    assert_eq!(tags.len(), 3, "Expected v3 to follow the remote's v2: {tags:?}");
+    for version in ["v1", "v2", "v3"] {
+        let tag = format!("refs/tags/gherrit/{gherrit_id}/{version}");
+        ctx.git_cmd().args(["rev-parse", "--verify", "--quiet", &tag]).assert().success();
+    }
+    let body = ctx.github().pull_requests()[0].body.clone().unwrap();
+    assert!(body.contains("**Latest Update:** v3"));
 }
 
 #[test]
@@ -225,3 +259,86 @@ fn test_graphql_batch_backoff() {
         .count();
     assert_eq!(v1_refs, 4, "Expected every v1 tag on the remote");
 }
+
+#[test]
+fn test_unchanged_commits_keep_their_version() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+
+    ctx.checkout_managed_private("unchanged-commits");
+    ctx.commit_with_gherrit_id("Bottom");
+    let bottom_id = ctx.gherrit_id("HEAD").unwrap();
+    ctx.commit_with_gherrit_id("Top");
+    let top_id = ctx.gherrit_id("HEAD").unwrap();
+
+    ctx.hook_cmd("pre-push").assert().success();
+
+    // Re-pushing an identical stack publishes nothing.
+    ctx.hook_cmd("pre-push").assert().success();
+    assert_eq!(ctx.recorded_pushes().len(), 1, "An unchanged stack must not be pushed");
+
+    // Amending the top commit publishes only that commit.
+    ctx.amend();
+    ctx.hook_cmd("pre-push").assert().success();
+
+    let pushes = ctx.recorded_pushes();
+    assert_eq!(pushes.len(), 2);
+    assert!(
+        pushes[1].arguments().iter().all(|argument| !argument.contains(&bottom_id)),
+        "The unchanged bottom commit must not be republished: {:?}",
+        pushes[1].arguments()
+    );
+    assert!(ctx.remote_ref_oid(&format!("refs/tags/gherrit/{bottom_id}/v2")).is_none());
+    assert_eq!(
+        ctx.remote_ref_oid(&format!("refs/tags/gherrit/{top_id}/v2")).as_deref(),
+        Some(ctx.head_oid().as_str())
+    );
+}
+
+#[test]
+fn test_versions_are_indexed_among_many_packed_tags() {
+    let ctx = testutil::test_context!()
+        .with_remote()
+        .with_initial_commit()
+        .with_mock_github()
+        .with_git_interceptor()
+        .build();
+
+    ctx.checkout_managed_private("many-tags");
+    let bottom_id = ctx.commit_with_gherrit_id("Bottom");
+    let top_id = ctx.commit_with_gherrit_id("Top");
+    ctx.hook_cmd("pre-push").assert().success();
+
+    // Bury the stack's tags among those of thousands of old commits.
+    ctx.run_git(&["pack-refs", "--all"]);
+    let packed_refs = ctx.repo_path.join(".git/packed-refs");
+    let packed = std::fs::read_to_string(&packed_refs).unwrap();
+    let (header, lines) = packed.split_once('\n').unwrap();
+    let head = ctx.head_oid();
+    let mut lines: Vec<String> = lines.lines().map(ToString::to_string).collect();
+    for i in 0..5_000 {
+        lines.extend((1..=4).map(|v| format!("{head} refs/tags/gherrit/Old{i:05}/v{v}")));
+    }
+    lines.sort_by(|a, b| a.split_once(' ').unwrap().1.cmp(b.split_once(' ').unwrap().1));
+    std::fs::write(&packed_refs, format!("{header}\n{}\n", lines.join("\n"))).unwrap();
+
+    ctx.amend();
+    ctx.hook_cmd("pre-push").assert().success();
+
+    let pushes = ctx.recorded_pushes();
+    assert_eq!(pushes.len(), 2);
+    assert!(
+        pushes[1].arguments().iter().all(|argument| !argument.contains(&bottom_id)),
+        "The unchanged bottom commit must not be republished: {:?}",
+        pushes[1].arguments()
+    );
+    assert_eq!(
+        ctx.remote_ref_oid(&format!("refs/tags/gherrit/{top_id}/v2")).as_deref(),
+        Some(ctx.head_oid().as_str())
+    );
+    assert!(ctx.remote_refs("refs/tags/gherrit/Old").is_empty());
+}
@@ -460,6 +460,16 @@ pub enum GraphQlOperation {
     Query,
     CreatePr,
     UpdatePr,
+    ReopenPr,
+    ClosePr,
+    MergePr,
+    EnqueuePr,
+    ConvertPrToDraft,
+    MarkPrReady,
+    RequestReviews,
+    AddLabels,
+    AddAssignees,
+    AddComment,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
@@ -507,6 +517,18 @@ pub struct PullRequestSnapshot {
     pub body: Option<String>,
     pub head: String,
     pub base: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_draft: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub review_requests: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub assignees: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_in_merge_queue: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_method: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -535,6 +557,12 @@ impl From<&mock_server::PrEntry> for PullRequestSnapshot {
             body: pr.body.clone(),
             head: pr.head.ref_field.clone(),
             base: pr.base.ref_field.clone(),
+            is_draft: pr.is_draft,
+            review_requests: pr.review_requests.clone(),
+            labels: pr.labels.clone(),
+            assignees: pr.assignees.clone(),
+            is_in_merge_queue: pr.is_in_merge_queue,
+            merge_method: pr.merge_method.clone(),
         }
     }
 }
@@ -553,6 +581,14 @@ impl MockGithub<'_> {
             .inspect_mock_state(|state| state.prs.iter().map(PullRequestSnapshot::from).collect())
     }
 
+    /// Returns the comments posted on a pull request, oldest first.
+    pub fn comments(&self, number: usize) -> Vec<String> {
+        self.context.inspect_mock_state(|state| {
+            let pr = state.prs.iter().find(|pr| pr.number == number);
+            pr.unwrap_or_else(|| panic!("pull request #{number} does not exist")).comments.clone()
+        })
+    }
+
     pub fn requests(&self) -> Vec<Vec<GraphQlOperation>> {
         self.context.inspect_mock_state(|state| state.graphql_requests.clone())
     }
@@ -582,6 +618,92 @@ impl MockGithub<'_> {
             pr.state = new_state.as_str().to_string();
         });
     }
+
+    /// Sets how the reviews of a pull request decide on it, such as
+    /// `APPROVED`. GitHub only reports the decision while the pull request is
+    /// based on `main`, the branch that requires reviews.
+    pub fn set_review_decision(&self, number: usize, decision: Option<&str>) {
+        self.context.mutate_mock_state(|state| {
+            let pr = state
+                .prs
+                .iter_mut()
+                .find(|pr| pr.number == number)
+                .unwrap_or_else(|| panic!("pull request #{number} does not exist"));
+            pr.review_decision = decision.map(ToString::to_string);
+        });
+    }
+
+    /// Marks a pull request as a draft or as ready for review, as its author
+    /// would on GitHub.
+    pub fn set_draft(&self, number: usize, is_draft: bool) {
+        self.context.mutate_mock_state(|state| {
+            let pr = state
+                .prs
+                .iter_mut()
+                .find(|pr| pr.number == number)
+                .unwrap_or_else(|| panic!("pull request #{number} does not exist"));
+            pr.is_draft = is_draft;
+        });
+    }
+
+    pub fn set_in_merge_queue(&self, number: usize, is_in_merge_queue: bool) {
+        self.context.mutate_mock_state(|state| {
+            let pr = state
+                .prs
+                .iter_mut()
+                .find(|pr| pr.number == number)
+                .unwrap_or_else(|| panic!("pull request #{number} does not exist"));
+            pr.is_in_merge_queue = is_in_merge_queue;
+        });
+    }
+
+    /// Sets the state of the checks on the head commit of a pull request,
+    /// such as `SUCCESS`.
+    pub fn set_check_state(&self, number: usize, check_state: Option<&str>) {
+        self.context.mutate_mock_state(|state| {
+            let pr = state
+                .prs
+                .iter_mut()
+                .find(|pr| pr.number == number)
+                .unwrap_or_else(|| panic!("pull request #{number} does not exist"));
+            pr.check_state = check_state.map(ToString::to_string);
+        });
+    }
+
+    /// Makes landing pull requests add them to a merge queue.
+    pub fn enable_merge_queue(&self) {
+        self.context.mutate_mock_state(|state| state.merge_queue_enabled = true);
+    }
+
+    /// Records a review of a pull request by `login`, such as `APPROVED`,
+    /// which replaces their previous one.
+    pub fn add_review(&self, number: usize, login: &str, review_state: &str) {
+        self.context.mutate_mock_state(|state| {
+            let pr = state
+                .prs
+                .iter_mut()
+                .find(|pr| pr.number == number)
+                .unwrap_or_else(|| panic!("pull request #{number} does not exist"));
+            pr.reviews.retain(|review| review.author != login);
+            pr.reviews.push(mock_server::Review {
+                author: login.to_string(),
+                state: review_state.to_string(),
+            });
+        });
+    }
+
+    /// Withdraws every review request of a pull request, as a reviewer who
+    /// removes themselves would.
+    pub fn clear_review_requests(&self, number: usize) {
+        self.context.mutate_mock_state(|state| {
+            let pr = state
+                .prs
+                .iter_mut()
+                .find(|pr| pr.number == number)
+                .unwrap_or_else(|| panic!("pull request #{number} does not exist"));
+            pr.review_requests.clear();
+        });
+    }
 }
 
 impl Drop for TestContext {
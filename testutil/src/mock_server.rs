@@ -13,6 +13,18 @@ use tokio::net::TcpListener;
 use crate::{git_interceptor, FailureKind, GraphQlOperation, TestEnvironment};
 
 const MAX_PULL_REQUEST_CANDIDATES: usize = 100;
+const PULL_REQUEST_NODE_FIELDS: &[&str] = &[
+    "number",
+    "id",
+    "title",
+    "body",
+    "baseRefName",
+    "state",
+    "isDraft",
+    "reviewDecision",
+    "isInMergeQueue",
+    "isCrossRepository",
+];
 
 static GITHUB_SCHEMA: LazyLock<Valid<apollo_compiler::Schema>> = LazyLock::new(|| {
     apollo_compiler::Schema::parse_and_validate(
@@ -32,6 +44,9 @@ pub struct MockState {
     pub repo_owner: String,
     pub repo_name: String,
     pub faults: VecDeque<FailureKind>,
+    /// Whether landing a pull request adds it to a merge queue rather than
+    /// merging it.
+    pub merge_queue_enabled: bool,
 }
 
 impl MockState {
@@ -44,6 +59,18 @@ impl MockState {
     }
 }
 
+/// The branch whose protection requires reviews. GitHub only decides on the
+/// reviews of pull requests based on a branch that requires them.
+const PROTECTED_BRANCH: &str = "main";
+
+/// The latest review of a pull request by one reviewer.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Review {
+    pub author: String,
+    /// `APPROVED`, `CHANGES_REQUESTED`, or `COMMENTED`.
+    pub state: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PrEntry {
     pub id: u64,
@@ -66,6 +93,33 @@ pub struct PrEntry {
     pub base: RefInfo,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(skip)]
+    pub is_draft: bool,
+    /// Logins and `org/team` slugs whose review was requested.
+    #[serde(skip)]
+    pub review_requests: Vec<String>,
+    #[serde(skip)]
+    pub labels: Vec<String>,
+    #[serde(skip)]
+    pub assignees: Vec<String>,
+    /// The latest review of each reviewer, oldest first. Logins ending in
+    /// `[bot]` are reported as apps rather than users.
+    #[serde(skip)]
+    pub reviews: Vec<Review>,
+    /// `APPROVED`, `CHANGES_REQUESTED`, or `REVIEW_REQUIRED`, as decided
+    /// while the pull request is based on [`PROTECTED_BRANCH`].
+    #[serde(skip)]
+    pub review_decision: Option<String>,
+    #[serde(skip)]
+    pub is_in_merge_queue: bool,
+    /// How the pull request was merged, such as `SQUASH`.
+    #[serde(skip)]
+    pub merge_method: Option<String>,
+    /// The `statusCheckRollup` state of the head commit, such as `SUCCESS`.
+    #[serde(skip)]
+    pub check_state: Option<String>,
+    #[serde(skip)]
+    pub comments: Vec<String>,
 }
 
 pub struct MockPrArgs<'a> {
@@ -121,6 +175,16 @@ impl PrEntry {
             base: RefInfo { ref_field: base, sha: "".to_string() },
             created_at: "2023-01-01T00:00:00Z".to_string(),
             updated_at: "2023-01-01T00:00:00Z".to_string(),
+            is_draft: false,
+            review_requests: Vec::new(),
+            labels: Vec::new(),
+            assignees: Vec::new(),
+            reviews: Vec::new(),
+            review_decision: None,
+            is_in_merge_queue: false,
+            merge_method: None,
+            check_state: None,
+            comments: Vec::new(),
         }
     }
 }
@@ -221,9 +285,19 @@ fn graphql_operations(document: &ExecutableDocument) -> Vec<GraphQlOperation> {
         .filter_map(|selection| {
             let executable::Selection::Field(field) = selection else { return None };
             match field.name.as_str() {
-                "repository" => Some(GraphQlOperation::Query),
+                "repository" | "user" | "organization" | "viewer" => Some(GraphQlOperation::Query),
                 "createPullRequest" => Some(GraphQlOperation::CreatePr),
                 "updatePullRequest" => Some(GraphQlOperation::UpdatePr),
+                "reopenPullRequest" => Some(GraphQlOperation::ReopenPr),
+                "closePullRequest" => Some(GraphQlOperation::ClosePr),
+                "mergePullRequest" => Some(GraphQlOperation::MergePr),
+                "enqueuePullRequest" => Some(GraphQlOperation::EnqueuePr),
+                "convertPullRequestToDraft" => Some(GraphQlOperation::ConvertPrToDraft),
+                "markPullRequestReadyForReview" => Some(GraphQlOperation::MarkPrReady),
+                "requestReviews" => Some(GraphQlOperation::RequestReviews),
+                "addLabelsToLabelable" => Some(GraphQlOperation::AddLabels),
+                "addAssigneesToAssignable" => Some(GraphQlOperation::AddAssignees),
+                "addComment" => Some(GraphQlOperation::AddComment),
                 _ => None,
             }
         })
@@ -413,7 +487,7 @@ fn validate_pull_requests_field(field: &executable::Field) -> Result<(), String>
             "nodes" => validate_scalar_fields(
                 &field.selection_set,
                 "repository.pullRequests.nodes",
-                &["number", "id", "title", "body", "baseRefName", "state", "isCrossRepository"],
+                PULL_REQUEST_NODE_FIELDS,
             )?,
             "pageInfo" => validate_scalar_fields(
                 &field.selection_set,
@@ -431,6 +505,116 @@ fn validate_pull_requests_field(field: &executable::Field) -> Result<(), String>
     Ok(())
 }
 
+fn validate_pull_request_field(field: &executable::Field) -> Result<(), String> {
+    const PATH: &str = "repository.pullRequest";
+    validate_argument_names(field, PATH, &["number"])?;
+    pull_request_number(field)?;
+
+    // Unlike the connection, a lookup by number does not already know the
+    // head branch, so it must also select it.
+    let allowed = [PULL_REQUEST_NODE_FIELDS, &["headRefName", "isMergeQueueEnabled"]].concat();
+    for field in selected_fields(&field.selection_set, PATH)? {
+        match field.name.as_str() {
+            "author" => {
+                validate_scalar_fields(&field.selection_set, ACTOR_PATH, ACTOR_FIELDS)?;
+            }
+            "latestReviews" => {
+                const REVIEWS_PATH: &str = "repository.pullRequest.latestReviews";
+                validate_argument_names(field, REVIEWS_PATH, &["first"])?;
+                for nodes in selected_fields(&field.selection_set, REVIEWS_PATH)? {
+                    if nodes.name != "nodes" {
+                        return Err(format!(
+                            "The mock GitHub API does not support field `{REVIEWS_PATH}.{}`",
+                            nodes.name
+                        ));
+                    }
+                    for review in selected_fields(&nodes.selection_set, REVIEWS_PATH)? {
+                        match review.name.as_str() {
+                            "author" => validate_scalar_fields(
+                                &review.selection_set,
+                                ACTOR_PATH,
+                                ACTOR_FIELDS,
+                            )?,
+                            "state" => {}
+                            name => {
+                                return Err(format!(
+                                    "The mock GitHub API does not support field \
+                                     `{REVIEWS_PATH}.nodes.{name}`"
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            "commits" => validate_commits_field(field)?,
+            name if allowed.contains(&name) => {}
+            name => {
+                return Err(format!("The mock GitHub API does not support field `{PATH}.{name}`"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validates `commits(last: 1) { nodes { commit { statusCheckRollup { state } } } }`,
+/// which selects the check state of a pull request's head commit.
+fn validate_commits_field(field: &executable::Field) -> Result<(), String> {
+    let mut path = "repository.pullRequest.commits".to_string();
+    validate_argument_names(field, &path, &["last"])?;
+    let last = field.arguments.iter().find(|argument| argument.name == "last");
+    if !matches!(last.map(|argument| &*argument.value), Some(ast::Value::Int(last)) if last.as_str() == "1")
+    {
+        return Err(format!("The mock GitHub API requires `{path}(last: 1)`"));
+    }
+
+    let mut selection_set = &field.selection_set;
+    for name in ["nodes", "commit", "statusCheckRollup"] {
+        let fields = selected_fields(selection_set, &path)?;
+        let [field] = fields.as_slice() else {
+            return Err(format!("The mock GitHub API requires exactly `{path}.{name}`"));
+        };
+        if field.name != name {
+            return Err(format!(
+                "The mock GitHub API does not support field `{path}.{}`",
+                field.name
+            ));
+        }
+        path = format!("{path}.{name}");
+        selection_set = &field.selection_set;
+    }
+    validate_scalar_fields(selection_set, &path, &["state"])
+}
+
+const ACTOR_PATH: &str = "repository.pullRequest.author";
+const ACTOR_FIELDS: &[&str] = &["__typename", "login"];
+
+/// Projects the actor with `login` onto the fields that `field` selects.
+fn project_actor(login: &str, field: &executable::Field) -> Result<serde_json::Value, String> {
+    let mut actor = serde_json::Map::new();
+    for field in selected_fields(&field.selection_set, ACTOR_PATH)? {
+        let value = match field.name.as_str() {
+            "__typename" if login.ends_with("[bot]") => serde_json::json!("Bot"),
+            "__typename" => serde_json::json!("User"),
+            "login" => serde_json::json!(login),
+            _ => unreachable!("request was checked by validate_pull_request_field"),
+        };
+        actor.insert(response_key(field), value);
+    }
+    Ok(serde_json::Value::Object(actor))
+}
+
+fn pull_request_number(field: &executable::Field) -> Result<usize, String> {
+    argument(field, "number")
+        .and_then(|value| match value {
+            ast::Value::Int(value) => value.as_str().parse().ok(),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            "The mock GitHub API requires an inline integer at `repository.pullRequest(number:)`"
+                .to_string()
+        })
+}
+
 fn validate_repository_field(
     field: &executable::Field,
     variables: &GraphQlVariables,
@@ -452,6 +636,12 @@ fn validate_repository_field(
                     variables,
                 )?;
             }
+            "pullRequest" => validate_pull_request_field(field)?,
+            "label" => {
+                validate_argument_names(field, "repository.label", &["name"])?;
+                resolve_string_argument(field, "name", "repository.label", variables)?;
+                validate_scalar_fields(&field.selection_set, "repository.label", &["id"])?;
+            }
             _ => {
                 return Err(format!(
                     "The mock GitHub API does not support field `{PATH}.{}`",
@@ -463,10 +653,75 @@ fn validate_repository_field(
     Ok(())
 }
 
+/// Validates the `user`, `organization`, and `viewer` lookups of node IDs.
+fn validate_lookup_field(
+    field: &executable::Field,
+    variables: &GraphQlVariables,
+) -> Result<(), String> {
+    let path = field.name.as_str();
+    match path {
+        "viewer" => validate_argument_names(field, path, &[])?,
+        _ => {
+            validate_argument_names(field, path, &["login"])?;
+            resolve_string_argument(field, "login", path, variables)?;
+        }
+    }
+    if path != "organization" {
+        return validate_scalar_fields(&field.selection_set, path, &["id"]);
+    }
+    for field in selected_fields(&field.selection_set, path)? {
+        if field.name != "team" {
+            return Err(format!(
+                "The mock GitHub API does not support field `{path}.{}`",
+                field.name
+            ));
+        }
+        validate_argument_names(field, "organization.team", &["slug"])?;
+        resolve_string_argument(field, "slug", "organization.team", variables)?;
+        validate_scalar_fields(&field.selection_set, "organization.team", &["id"])?;
+    }
+    Ok(())
+}
+
+/// Validates a mutation whose input names a pull request by `id_field` and
+/// lists node IDs in `list_fields`, such as `addLabelsToLabelable`.
+fn validate_node_list_field(
+    field: &executable::Field,
+    id_field: &str,
+    list_fields: &[&str],
+) -> Result<(), String> {
+    let path = field.name.as_str();
+    validate_argument_names(field, path, &["input"])?;
+    let input = input_object(field, path)?;
+    for (name, value) in input {
+        let is_valid = match &**value {
+            ast::Value::String(_) => name == id_field,
+            ast::Value::List(values) => {
+                list_fields.contains(&name.as_str())
+                    && values.iter().all(|value| matches!(**value, ast::Value::String(_)))
+            }
+            ast::Value::Boolean(true) => name == "union" && path == "requestReviews",
+            _ => false,
+        };
+        if !is_valid {
+            return Err(format!("The mock GitHub API does not support `{path}.input.{name}`"));
+        }
+    }
+    required_string_field(input, id_field, path)?;
+    validate_scalar_fields(&field.selection_set, path, &["clientMutationId"])
+}
+
 fn validate_create_field(field: &executable::Field) -> Result<(), String> {
     const PATH: &str = "createPullRequest";
     validate_argument_names(field, PATH, &["input"])?;
     let input = input_object(field, PATH)?;
+    // `draft` is the only non-string input that the mock supports.
+    let (draft, input): (Vec<_>, Vec<_>) =
+        input.iter().cloned().partition(|(name, _)| name == "draft");
+    if draft.iter().any(|(_, value)| !matches!(**value, ast::Value::Boolean(_))) {
+        return Err(format!("The mock GitHub API requires boolean field `{PATH}.input.draft`"));
+    }
+    let input = input.as_slice();
     validate_input_fields(
         input,
         PATH,
@@ -508,6 +763,78 @@ fn validate_update_field(field: &executable::Field) -> Result<(), String> {
     validate_scalar_fields(&field.selection_set, PATH, &["clientMutationId"])
 }
 
+fn validate_reopen_field(field: &executable::Field) -> Result<(), String> {
+    const PATH: &str = "reopenPullRequest";
+    validate_argument_names(field, PATH, &["input"])?;
+    let input = input_object(field, PATH)?;
+    validate_input_fields(input, PATH, &["pullRequestId"])?;
+    required_string_field(input, "pullRequestId", PATH)?;
+    validate_scalar_fields(&field.selection_set, PATH, &["clientMutationId"])
+}
+
+fn validate_close_field(field: &executable::Field) -> Result<(), String> {
+    const PATH: &str = "closePullRequest";
+    validate_argument_names(field, PATH, &["input"])?;
+    let input = input_object(field, PATH)?;
+    validate_input_fields(input, PATH, &["pullRequestId"])?;
+    required_string_field(input, "pullRequestId", PATH)?;
+    validate_scalar_fields(&field.selection_set, PATH, &["clientMutationId"])
+}
+
+/// Validates `mergePullRequest` or `enqueuePullRequest`.
+fn validate_merge_field(field: &executable::Field) -> Result<(), String> {
+    let path = field.name.as_str();
+    validate_argument_names(field, path, &["input"])?;
+    let input = input_object(field, path)?;
+    // Only a merge takes a method, which is an enum value rather than a string.
+    let is_merge = path == "mergePullRequest";
+    if is_merge {
+        merge_method(input, path)?;
+    }
+    let strings: Vec<_> =
+        input.iter().filter(|(name, _)| !(is_merge && name == "mergeMethod")).cloned().collect();
+    validate_input_fields(&strings, path, &["pullRequestId", "expectedHeadOid"])?;
+    required_string_field(input, "pullRequestId", path)?;
+    required_string_field(input, "expectedHeadOid", path)?;
+    validate_scalar_fields(&field.selection_set, path, &["clientMutationId"])
+}
+
+/// The `mergeMethod` of a `mergePullRequest` input, which GitHub defaults to
+/// `MERGE`.
+fn merge_method(input: &[(Name, Node<ast::Value>)], path: &str) -> Result<String, String> {
+    let Some((_, value)) = input.iter().find(|(key, _)| key == "mergeMethod") else {
+        return Ok("MERGE".to_string());
+    };
+    match &**value {
+        ast::Value::Enum(method) if ["MERGE", "SQUASH", "REBASE"].contains(&method.as_str()) => {
+            Ok(method.to_string())
+        }
+        _ => Err(format!(
+            "The mock GitHub API requires `{path}.input.mergeMethod` to be MERGE, SQUASH, or REBASE"
+        )),
+    }
+}
+
+/// Validates `convertPullRequestToDraft` or `markPullRequestReadyForReview`.
+fn validate_draft_field(field: &executable::Field) -> Result<(), String> {
+    let path = field.name.as_str();
+    validate_argument_names(field, path, &["input"])?;
+    let input = input_object(field, path)?;
+    validate_input_fields(input, path, &["pullRequestId"])?;
+    required_string_field(input, "pullRequestId", path)?;
+    validate_scalar_fields(&field.selection_set, path, &["clientMutationId"])
+}
+
+fn validate_comment_field(field: &executable::Field) -> Result<(), String> {
+    const PATH: &str = "addComment";
+    validate_argument_names(field, PATH, &["input"])?;
+    let input = input_object(field, PATH)?;
+    validate_input_fields(input, PATH, &["subjectId", "body"])?;
+    required_string_field(input, "subjectId", PATH)?;
+    required_string_field(input, "body", PATH)?;
+    validate_scalar_fields(&field.selection_set, PATH, &["clientMutationId"])
+}
+
 fn validate_supported_document(
     document: &ExecutableDocument,
     variables: &GraphQlVariables,
@@ -528,6 +855,23 @@ fn validate_supported_document(
             "repository" => validate_repository_field(field, variables)?,
             "createPullRequest" => validate_create_field(field)?,
             "updatePullRequest" => validate_update_field(field)?,
+            "reopenPullRequest" => validate_reopen_field(field)?,
+            "closePullRequest" => validate_close_field(field)?,
+            "mergePullRequest" | "enqueuePullRequest" => validate_merge_field(field)?,
+            "convertPullRequestToDraft" | "markPullRequestReadyForReview" => {
+                validate_draft_field(field)?
+            }
+            "user" | "organization" | "viewer" => validate_lookup_field(field, variables)?,
+            "requestReviews" => {
+                validate_node_list_field(field, "pullRequestId", &["userIds", "teamIds"])?
+            }
+            "addLabelsToLabelable" => {
+                validate_node_list_field(field, "labelableId", &["labelIds"])?
+            }
+            "addAssigneesToAssignable" => {
+                validate_node_list_field(field, "assignableId", &["assigneeIds"])?
+            }
+            "addComment" => validate_comment_field(field)?,
             _ => {
                 return Err(format!(
                     "The mock GitHub API does not support root field `{}`",
@@ -604,6 +948,24 @@ async fn graphql(
                     "createPullRequest" => handle_create_pr(&mut mock_state, field, &|branch| {
                         remote_branch_exists(&app_state, branch)
                     }),
+                    "reopenPullRequest" => handle_reopen_pr(&mut mock_state, field, &|branch| {
+                        remote_branch_head(&app_state, branch)
+                    }),
+                    "closePullRequest" => handle_close_pr(&mut mock_state, field),
+                    "mergePullRequest" | "enqueuePullRequest" => {
+                        handle_merge_pr(&mut mock_state, field, &|branch| {
+                            remote_branch_head(&app_state, branch)
+                        })
+                    }
+                    "convertPullRequestToDraft" => handle_set_draft(&mut mock_state, field, true),
+                    "markPullRequestReadyForReview" => {
+                        handle_set_draft(&mut mock_state, field, false)
+                    }
+                    "requestReviews" => handle_add_nodes(&mut mock_state, field),
+                    "addLabelsToLabelable" => handle_add_nodes(&mut mock_state, field),
+                    "addAssigneesToAssignable" => handle_add_nodes(&mut mock_state, field),
+                    "user" | "organization" | "viewer" => handle_lookup(field, &variables),
+                    "addComment" => handle_add_comment(&mut mock_state, field),
                     "repository" => handle_repository_query(&mock_state, field, &variables),
                     _ => unreachable!("request was checked by validate_supported_document"),
                 };
@@ -658,6 +1020,25 @@ fn remote_branch_exists(app_state: &AppState, branch: &str) -> Result<bool, Stri
     }
 }
 
+/// The object ID that a branch of the remote repository points to, if the
+/// branch exists.
+fn remote_branch_head(app_state: &AppState, branch: &str) -> Result<Option<String>, String> {
+    let reference = format!("refs/heads/{branch}");
+    let output = app_state
+        .test_environment
+        .command(&app_state.system_git)
+        .arg("--git-dir")
+        .arg(&app_state.remote_path)
+        .args(["rev-parse", "--verify", "--quiet", &reference])
+        .output()
+        .map_err(|error| format!("Failed to inspect remote Git ref `{reference}`: {error}"))?;
+    match output.status.code() {
+        Some(0) => Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string())),
+        Some(1) => Ok(None),
+        code => Err(format!("Inspecting remote Git ref `{reference}` exited with {code:?}")),
+    }
+}
+
 fn extract_input_field<'a>(
     field: &'a executable::Field,
     arg_name: &str,
@@ -736,6 +1117,238 @@ fn handle_update_pr(
     Ok(serde_json::Value::Object(response))
 }
 
+/// Reopens a closed pull request, which GitHub refuses once its head branch is
+/// deleted.
+fn handle_reopen_pr(
+    mock_state: &mut MockState,
+    field: &executable::Field,
+    branch_head: &dyn Fn(&str) -> Result<Option<String>, String>,
+) -> Result<serde_json::Value, String> {
+    const PATH: &str = "reopenPullRequest";
+    let input = input_object(field, PATH)?;
+    let node_id = required_string_field(input, "pullRequestId", PATH)?;
+    if let Some(pr) = mock_state.prs.iter().find(|pr| pr.node_id == node_id) {
+        if branch_head(&pr.head.ref_field)?.is_none() {
+            return Err(format!(
+                "Pull request #{} cannot be reopened because its head branch was deleted",
+                pr.number
+            ));
+        }
+    }
+    transition_pr_state(mock_state, field, PATH, "CLOSED", "OPEN")
+}
+
+fn handle_close_pr(
+    mock_state: &mut MockState,
+    field: &executable::Field,
+) -> Result<serde_json::Value, String> {
+    transition_pr_state(mock_state, field, "closePullRequest", "OPEN", "CLOSED")
+}
+
+/// Merges a pull request, or adds it to the merge queue, if its head branch is
+/// still at `expectedHeadOid` and its reviews allow it.
+fn handle_merge_pr(
+    mock_state: &mut MockState,
+    field: &executable::Field,
+    branch_head: &dyn Fn(&str) -> Result<Option<String>, String>,
+) -> Result<serde_json::Value, String> {
+    let path = field.name.as_str();
+    let enqueue = path == "enqueuePullRequest";
+    let input = input_object(field, path)?;
+    let node_id = required_string_field(input, "pullRequestId", path)?;
+    let expected_head_oid = required_string_field(input, "expectedHeadOid", path)?;
+
+    let merge_queue_enabled = mock_state.merge_queue_enabled;
+    let Some(pr) = mock_state.prs.iter_mut().find(|pr| pr.node_id == node_id) else {
+        return Err(format!("Pull request node `{node_id}` does not exist"));
+    };
+    if enqueue != merge_queue_enabled {
+        return Err(if enqueue {
+            format!("Pull request #{} has no merge queue", pr.number)
+        } else {
+            format!("Pull request #{} must be merged through the merge queue", pr.number)
+        });
+    }
+    if pr.state != "OPEN" || pr.is_draft || pr.is_in_merge_queue {
+        return Err(format!("Pull request #{} is not mergeable", pr.number));
+    }
+    if matches!(review_decision(pr), Some("CHANGES_REQUESTED" | "REVIEW_REQUIRED")) {
+        return Err(format!("Pull request #{} is not approved", pr.number));
+    }
+    if branch_head(&pr.head.ref_field)?.as_deref() != Some(expected_head_oid.as_str()) {
+        return Err(format!(
+            "Head branch of pull request #{} was modified. Review and try the merge again.",
+            pr.number
+        ));
+    }
+
+    if enqueue {
+        pr.is_in_merge_queue = true;
+    } else {
+        pr.state = "MERGED".to_string();
+        pr.merge_method = Some(merge_method(input, path)?);
+    }
+    client_mutation_response(field, path)
+}
+
+/// Moves the pull request named by a `pullRequestId` input from state `from`
+/// to state `to`.
+fn transition_pr_state(
+    mock_state: &mut MockState,
+    field: &executable::Field,
+    path: &str,
+    from: &str,
+    to: &str,
+) -> Result<serde_json::Value, String> {
+    let input = input_object(field, path)?;
+    let node_id = required_string_field(input, "pullRequestId", path)?;
+
+    let Some(pr) = mock_state.prs.iter_mut().find(|pr| pr.node_id == node_id) else {
+        return Err(format!("Pull request node `{node_id}` does not exist"));
+    };
+    if pr.state != from {
+        return Err(format!("Pull request #{} is {} and cannot become {to}", pr.number, pr.state));
+    }
+    pr.state = to.to_string();
+    client_mutation_response(field, path)
+}
+
+/// Converts an open pull request to a draft, or marks it ready for review.
+fn handle_set_draft(
+    mock_state: &mut MockState,
+    field: &executable::Field,
+    draft: bool,
+) -> Result<serde_json::Value, String> {
+    let path = field.name.as_str();
+    let input = input_object(field, path)?;
+    let node_id = required_string_field(input, "pullRequestId", path)?;
+
+    let Some(pr) = mock_state.prs.iter_mut().find(|pr| pr.node_id == node_id) else {
+        return Err(format!("Pull request node `{node_id}` does not exist"));
+    };
+    if pr.state != "OPEN" {
+        return Err(format!("Pull request #{} is {} and cannot change", pr.number, pr.state));
+    }
+    pr.is_draft = draft;
+    client_mutation_response(field, path)
+}
+
+/// The login of the authenticated user, who also authors every mock PR.
+const VIEWER_LOGIN: &str = "test-user";
+
+/// Resolves a `user`, `organization.team`, or `viewer` lookup. Every user and
+/// team exists; their node IDs are their names behind a `U_` or `T_` prefix.
+fn handle_lookup(
+    field: &executable::Field,
+    variables: &GraphQlVariables,
+) -> Result<serde_json::Value, String> {
+    let path = field.name.as_str();
+    let id = |id: String| -> Result<serde_json::Value, String> {
+        let mut node = serde_json::Map::new();
+        for field in selected_fields(&field.selection_set, path)? {
+            node.insert(response_key(field), serde_json::json!(id));
+        }
+        Ok(serde_json::Value::Object(node))
+    };
+    match path {
+        "viewer" => id(format!("U_{VIEWER_LOGIN}")),
+        "user" => id(format!("U_{}", resolve_string_argument(field, "login", path, variables)?)),
+        _ => {
+            let organization = resolve_string_argument(field, "login", path, variables)?;
+            let mut response = serde_json::Map::new();
+            for team in selected_fields(&field.selection_set, path)? {
+                let slug = resolve_string_argument(team, "slug", "organization.team", variables)?;
+                let mut node = serde_json::Map::new();
+                for field in selected_fields(&team.selection_set, "organization.team")? {
+                    node.insert(
+                        response_key(field),
+                        serde_json::json!(format!("T_{organization}/{slug}")),
+                    );
+                }
+                response.insert(response_key(team), serde_json::Value::Object(node));
+            }
+            Ok(serde_json::Value::Object(response))
+        }
+    }
+}
+
+/// Applies `requestReviews`, `addLabelsToLabelable`, or
+/// `addAssigneesToAssignable`, adding each named node that the pull request
+/// does not list yet.
+fn handle_add_nodes(
+    mock_state: &mut MockState,
+    field: &executable::Field,
+) -> Result<serde_json::Value, String> {
+    let path = field.name.as_str();
+    let input = input_object(field, path)?;
+    let (id_field, prefixes) = match path {
+        "requestReviews" => ("pullRequestId", ["U_", "T_"].as_slice()),
+        "addLabelsToLabelable" => ("labelableId", ["L_"].as_slice()),
+        _ => ("assignableId", ["U_"].as_slice()),
+    };
+    let node_id = required_string_field(input, id_field, path)?;
+    let mut names = Vec::new();
+    for (_, value) in input {
+        let ast::Value::List(values) = &**value else { continue };
+        for value in values {
+            let ast::Value::String(id) = &**value else { continue };
+            let name = prefixes
+                .iter()
+                .find_map(|prefix| id.strip_prefix(prefix))
+                .ok_or_else(|| format!("Node `{id}` cannot be used in `{path}`"))?;
+            names.push(name.to_string());
+        }
+    }
+
+    let Some(pr) = mock_state.prs.iter_mut().find(|pr| pr.node_id == node_id) else {
+        return Err(format!("Pull request node `{node_id}` does not exist"));
+    };
+    let list = match path {
+        "requestReviews" => &mut pr.review_requests,
+        "addLabelsToLabelable" => &mut pr.labels,
+        _ => &mut pr.assignees,
+    };
+    for name in names {
+        if !list.contains(&name) {
+            list.push(name);
+        }
+    }
+    client_mutation_response(field, path)
+}
+
+fn handle_add_comment(
+    mock_state: &mut MockState,
+    field: &executable::Field,
+) -> Result<serde_json::Value, String> {
+    const PATH: &str = "addComment";
+    let input = input_object(field, PATH)?;
+    let subject_id = required_string_field(input, "subjectId", PATH)?;
+    let body = required_string_field(input, "body", PATH)?;
+
+    let Some(pr) = mock_state.prs.iter_mut().find(|pr| pr.node_id == subject_id) else {
+        return Err(format!("Pull request node `{subject_id}` does not exist"));
+    };
+    pr.comments.push(body);
+    client_mutation_response(field, PATH)
+}
+
+/// Responds to a mutation whose payload selects only `clientMutationId`.
+fn client_mutation_response(
+    field: &executable::Field,
+    path: &str,
+) -> Result<serde_json::Value, String> {
+    let mut response = serde_json::Map::new();
+    for field in selected_fields(&field.selection_set, path)? {
+        match field.name.as_str() {
+            "clientMutationId" => {
+                response.insert(response_key(field), serde_json::Value::Null);
+            }
+            _ => unreachable!("the mutation selection was validated"),
+        }
+    }
+    Ok(serde_json::Value::Object(response))
+}
+
 fn handle_create_pr(
     mock_state: &mut MockState,
     field: &executable::Field,
@@ -748,6 +1361,9 @@ fn handle_create_pr(
     let head = required_string_field(input, "headRefName", PATH)?;
     let title = required_string_field(input, "title", PATH)?;
     let body = get_string_field(input, "body").unwrap_or_default();
+    let draft = input
+        .iter()
+        .any(|(name, value)| name == "draft" && matches!(**value, ast::Value::Boolean(true)));
 
     if repository_id != "REPO_NODE_ID" {
         return Err(format!("Repository node `{repository_id}` does not exist"));
@@ -772,15 +1388,18 @@ fn handle_create_pr(
     let number = mock_state.prs.iter().map(|pr| pr.number as u64).max().unwrap_or(0) + 1;
     let owner = mock_state.repo_owner.clone();
     let repo = mock_state.repo_name.clone();
-    let entry = PrEntry::mock(MockPrArgs {
-        id: number,
-        title,
-        body,
-        head,
-        base,
-        repo_owner: &owner,
-        repo_name: &repo,
-    });
+    let entry = PrEntry {
+        is_draft: draft,
+        ..PrEntry::mock(MockPrArgs {
+            id: number,
+            title,
+            body,
+            head,
+            base,
+            repo_owner: &owner,
+            repo_name: &repo,
+        })
+    };
     let node_id = entry.node_id.clone();
     let html_url = entry.html_url.clone();
     mock_state.prs.push(entry);
@@ -864,9 +1483,10 @@ fn handle_repository_query(
                                 .take(MAX_PULL_REQUEST_CANDIDATES)
                                 .map(|pr| {
                                     project_pr_node(
+                                        mock_state,
                                         pr,
-                                        mock_state.cross_repository_prs.contains(&pr.number),
                                         &field.selection_set,
+                                        "repository.pullRequests.nodes",
                                     )
                                 })
                                 .collect::<Result<Vec<_>, _>>()?;
@@ -898,6 +1518,27 @@ fn handle_repository_query(
                 }
                 repo_data.insert(response_key(field), serde_json::Value::Object(connection));
             }
+            "label" => {
+                let name = resolve_string_argument(field, "name", "repository.label", variables)?;
+                let mut label = serde_json::Map::new();
+                for field in selected_fields(&field.selection_set, "repository.label")? {
+                    label.insert(response_key(field), serde_json::json!(format!("L_{name}")));
+                }
+                repo_data.insert(response_key(field), serde_json::Value::Object(label));
+            }
+            "pullRequest" => {
+                let number = pull_request_number(field)?;
+                let node = match mock_state.prs.iter().find(|pr| pr.number == number) {
+                    Some(pr) => project_pr_node(
+                        mock_state,
+                        pr,
+                        &field.selection_set,
+                        "repository.pullRequest",
+                    )?,
+                    None => serde_json::Value::Null,
+                };
+                repo_data.insert(response_key(field), node);
+            }
             "id" => {
                 repo_data.insert(
                     response_key(field),
@@ -911,28 +1552,97 @@ fn handle_repository_query(
     Ok(serde_json::Value::Object(repo_data))
 }
 
+/// The `reviewDecision` of a pull request, which GitHub leaves null unless its
+/// base branch requires reviews.
+fn review_decision(pr: &PrEntry) -> Option<&str> {
+    pr.review_decision.as_deref().filter(|_| pr.base.ref_field == PROTECTED_BRANCH)
+}
+
 fn project_pr_node(
+    mock_state: &MockState,
     pr: &PrEntry,
-    is_cross_repository: bool,
     selection_set: &executable::SelectionSet,
+    path: &str,
 ) -> Result<serde_json::Value, String> {
+    let is_cross_repository = mock_state.cross_repository_prs.contains(&pr.number);
     let mut node = serde_json::Map::new();
-    for field in selected_fields(selection_set, "repository.pullRequests.nodes")? {
+    for field in selected_fields(selection_set, path)? {
         let value = match field.name.as_str() {
             "number" => serde_json::json!(pr.number),
             "id" => serde_json::json!(pr.node_id),
             "title" => serde_json::json!(pr.title),
             "body" => serde_json::json!(pr.body),
             "baseRefName" => serde_json::json!(pr.base.ref_field),
+            "headRefName" => serde_json::json!(pr.head.ref_field),
             "state" => serde_json::json!(pr.state),
+            "isDraft" => serde_json::json!(pr.is_draft),
+            "reviewDecision" => serde_json::json!(review_decision(pr)),
+            "isInMergeQueue" => serde_json::json!(pr.is_in_merge_queue),
+            "isMergeQueueEnabled" => serde_json::json!(mock_state.merge_queue_enabled),
             "isCrossRepository" => serde_json::json!(is_cross_repository),
-            _ => unreachable!("request was checked by validate_pull_requests_field"),
+            "author" => project_actor(&pr.user.login, field)?,
+            "latestReviews" => {
+                let mut reviews = serde_json::Map::new();
+                for nodes in selected_fields(&field.selection_set, path)? {
+                    let nodes_json = pr
+                        .reviews
+                        .iter()
+                        .map(|review| {
+                            let mut review_json = serde_json::Map::new();
+                            for field in selected_fields(&nodes.selection_set, path)? {
+                                let value = match field.name.as_str() {
+                                    "author" => project_actor(&review.author, field)?,
+                                    _ => serde_json::json!(review.state),
+                                };
+                                review_json.insert(response_key(field), value);
+                            }
+                            Ok(serde_json::Value::Object(review_json))
+                        })
+                        .collect::<Result<Vec<_>, String>>()?;
+                    reviews.insert(response_key(nodes), serde_json::Value::Array(nodes_json));
+                }
+                serde_json::Value::Object(reviews)
+            }
+            "commits" => project_head_commit(field, pr.check_state.as_deref(), path)?,
+            _ => unreachable!("request was checked by the pull request field validators"),
         };
         node.insert(response_key(field), value);
     }
     Ok(serde_json::Value::Object(node))
 }
 
+/// Projects the `commits(last: 1)` connection of a pull request whose head
+/// commit has the check state `check_state`, if it has checks.
+fn project_head_commit(
+    field: &executable::Field,
+    check_state: Option<&str>,
+    path: &str,
+) -> Result<serde_json::Value, String> {
+    let mut commits = serde_json::Map::new();
+    for nodes in selected_fields(&field.selection_set, path)? {
+        let mut node = serde_json::Map::new();
+        for commit in selected_fields(&nodes.selection_set, path)? {
+            let mut commit_json = serde_json::Map::new();
+            for rollup in selected_fields(&commit.selection_set, path)? {
+                let rollup_json = match check_state {
+                    Some(check_state) => {
+                        let mut rollup_json = serde_json::Map::new();
+                        for state in selected_fields(&rollup.selection_set, path)? {
+                            rollup_json.insert(response_key(state), serde_json::json!(check_state));
+                        }
+                        serde_json::Value::Object(rollup_json)
+                    }
+                    None => serde_json::Value::Null,
+                };
+                commit_json.insert(response_key(rollup), rollup_json);
+            }
+            node.insert(response_key(commit), serde_json::Value::Object(commit_json));
+        }
+        commits.insert(response_key(nodes), serde_json::json!([node]));
+    }
+    Ok(serde_json::Value::Object(commits))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1008,6 +1718,13 @@ mod tests {
         );
         validate_supported_document(&lookup, &None).unwrap();
 
+        let by_number = parse_document(
+            "query { op0: repository(owner: \"owner\", name: \"repo\") { \
+             pullRequest(number: 7) { number, id, title, body, baseRefName, \
+             headRefName, state, isCrossRepository } } }",
+        );
+        validate_supported_document(&by_number, &None).unwrap();
+
         let create = parse_document(
             "mutation { op0: createPullRequest(input: { repositoryId: \
              \"REPO_NODE_ID\", baseRefName: \"main\", headRefName: \"Ghead\", \
@@ -1027,7 +1744,12 @@ mod tests {
         let viewer = parse_document("query { viewer { login } }");
         assert!(validate_supported_document(&viewer, &None)
             .unwrap_err()
-            .contains("root field `viewer`"));
+            .contains("field `viewer.login`"));
+
+        let node = parse_document("query { node(id: \"PR_1\") { id } }");
+        assert!(validate_supported_document(&node, &None)
+            .unwrap_err()
+            .contains("root field `node`"));
 
         let repository_name =
             parse_document("query { repository(owner: \"owner\", name: \"repo\") { name } }");
@@ -1209,4 +1931,184 @@ mod tests {
         let error = handle_update_pr(&mut state, root_field(&update), &|_| Ok(true)).unwrap_err();
         assert!(error.contains("head and base branches must differ"));
     }
+
+    #[test]
+    fn reopen_only_reopens_closed_pull_requests() {
+        let mut state = MockState::new("owner".to_string(), "repo".to_string());
+        state.add_pr(PrEntry::mock(MockPrArgs {
+            id: 1,
+            title: "Title".to_string(),
+            body: String::new(),
+            head: "Ghead".to_string(),
+            base: "main".to_string(),
+            repo_owner: "owner",
+            repo_name: "repo",
+        }));
+        let reopen = parse_document(
+            "mutation { reopenPullRequest(input: { pullRequestId: \"PR_1\" }) \
+             { clientMutationId } }",
+        );
+        validate_supported_document(&reopen, &None).unwrap();
+        let branch_head = |_: &str| Ok(Some("abc".to_string()));
+
+        let error = handle_reopen_pr(&mut state, root_field(&reopen), &branch_head).unwrap_err();
+        assert!(error.contains("is OPEN and cannot become OPEN"));
+
+        state.prs[0].state = "CLOSED".to_string();
+        let deleted = |_: &str| Ok(None);
+        let error = handle_reopen_pr(&mut state, root_field(&reopen), &deleted).unwrap_err();
+        assert!(error.contains("its head branch was deleted"));
+        handle_reopen_pr(&mut state, root_field(&reopen), &branch_head).unwrap();
+        assert_eq!(state.prs[0].state, "OPEN");
+    }
+
+    #[test]
+    fn close_and_comment_update_the_pull_request() {
+        let mut state = MockState::new("owner".to_string(), "repo".to_string());
+        state.add_pr(PrEntry::mock(MockPrArgs {
+            id: 1,
+            title: "Title".to_string(),
+            body: String::new(),
+            head: "Ghead".to_string(),
+            base: "main".to_string(),
+            repo_owner: "owner",
+            repo_name: "repo",
+        }));
+        let comment = parse_document(
+            "mutation { addComment(input: { subjectId: \"PR_1\", body: \"Bye\" }) \
+             { clientMutationId } }",
+        );
+        let close = parse_document(
+            "mutation { closePullRequest(input: { pullRequestId: \"PR_1\" }) \
+             { clientMutationId } }",
+        );
+        validate_supported_document(&comment, &None).unwrap();
+        validate_supported_document(&close, &None).unwrap();
+
+        handle_add_comment(&mut state, root_field(&comment)).unwrap();
+        handle_close_pr(&mut state, root_field(&close)).unwrap();
+        assert_eq!(state.prs[0].comments, ["Bye"]);
+        assert_eq!(state.prs[0].state, "CLOSED");
+
+        let error = handle_close_pr(&mut state, root_field(&close)).unwrap_err();
+        assert!(error.contains("is CLOSED and cannot become CLOSED"));
+    }
+
+    #[test]
+    fn draft_mutations_toggle_open_pull_requests() {
+        let mut state = MockState::new("owner".to_string(), "repo".to_string());
+        state.add_pr(PrEntry::mock(MockPrArgs {
+            id: 1,
+            title: "Title".to_string(),
+            body: String::new(),
+            head: "Ghead".to_string(),
+            base: "main".to_string(),
+            repo_owner: "owner",
+            repo_name: "repo",
+        }));
+        let convert = parse_document(
+            "mutation { convertPullRequestToDraft(input: { pullRequestId: \"PR_1\" }) \
+             { clientMutationId } }",
+        );
+        let ready = parse_document(
+            "mutation { markPullRequestReadyForReview(input: { pullRequestId: \"PR_1\" }) \
+             { clientMutationId } }",
+        );
+        validate_supported_document(&convert, &None).unwrap();
+        validate_supported_document(&ready, &None).unwrap();
+
+        handle_set_draft(&mut state, root_field(&convert), true).unwrap();
+        assert!(state.prs[0].is_draft);
+        handle_set_draft(&mut state, root_field(&ready), false).unwrap();
+        assert!(!state.prs[0].is_draft);
+
+        state.prs[0].state = "MERGED".to_string();
+        let error = handle_set_draft(&mut state, root_field(&convert), true).unwrap_err();
+        assert!(error.contains("is MERGED and cannot change"));
+    }
+
+    #[test]
+    fn merging_checks_the_head_and_reviews() {
+        let mut state = MockState::new("owner".to_string(), "repo".to_string());
+        for (id, head, base) in [(1, "G1", "main"), (2, "G2", "G1")] {
+            state.add_pr(PrEntry::mock(MockPrArgs {
+                id,
+                title: "Title".to_string(),
+                body: String::new(),
+                head: head.to_string(),
+                base: base.to_string(),
+                repo_owner: "owner",
+                repo_name: "repo",
+            }));
+        }
+        let merge = |oid: &str| {
+            parse_document(&format!(
+                "mutation {{ mergePullRequest(input: {{ pullRequestId: \"PR_1\", \
+                 expectedHeadOid: \"{oid}\", mergeMethod: SQUASH }}) {{ clientMutationId }} }}"
+            ))
+        };
+        let branch_head = |_: &str| Ok(Some("abc".to_string()));
+        validate_supported_document(&merge("abc"), &None).unwrap();
+
+        let error = handle_merge_pr(&mut state, root_field(&merge("def")), &branch_head);
+        assert!(error.unwrap_err().contains("was modified"));
+        state.prs[0].review_decision = Some("REVIEW_REQUIRED".to_string());
+        let error = handle_merge_pr(&mut state, root_field(&merge("abc")), &branch_head);
+        assert!(error.unwrap_err().contains("is not approved"));
+
+        state.prs[0].review_decision = Some("APPROVED".to_string());
+        handle_merge_pr(&mut state, root_field(&merge("abc")), &branch_head).unwrap();
+        assert_eq!(state.prs[0].state, "MERGED");
+        assert_eq!(state.prs[0].merge_method.as_deref(), Some("SQUASH"));
+
+        let enqueue = parse_document(
+            "mutation { enqueuePullRequest(input: { pullRequestId: \"PR_2\", \
+             expectedHeadOid: \"abc\" }) { clientMutationId } }",
+        );
+        let error = handle_merge_pr(&mut state, root_field(&enqueue), &branch_head);
+        assert!(error.unwrap_err().contains("has no merge queue"));
+        state.merge_queue_enabled = true;
+        handle_merge_pr(&mut state, root_field(&enqueue), &branch_head).unwrap();
+        assert!(state.prs[1].is_in_merge_queue);
+        assert_eq!(state.prs[1].state, "OPEN");
+    }
+
+    #[test]
+    fn triage_mutations_add_each_node_once() {
+        let mut state = MockState::new("owner".to_string(), "repo".to_string());
+        state.add_pr(PrEntry::mock(MockPrArgs {
+            id: 1,
+            title: "Title".to_string(),
+            body: String::new(),
+            head: "Ghead".to_string(),
+            base: "main".to_string(),
+            repo_owner: "owner",
+            repo_name: "repo",
+        }));
+        let request = parse_document(
+            "mutation { requestReviews(input: { pullRequestId: \"PR_1\", \
+             userIds: [\"U_alice\"], teamIds: [\"T_org/team\"], union: true }) \
+             { clientMutationId } }",
+        );
+        let label = parse_document(
+            "mutation { addLabelsToLabelable(input: { labelableId: \"PR_1\", \
+             labelIds: [\"L_perf\", \"L_perf\"] }) { clientMutationId } }",
+        );
+        let assign = parse_document(
+            "mutation { addAssigneesToAssignable(input: { assignableId: \"PR_1\", \
+             assigneeIds: [\"L_perf\"] }) { clientMutationId } }",
+        );
+        validate_supported_document(&request, &None).unwrap();
+        validate_supported_document(&label, &None).unwrap();
+        validate_supported_document(&assign, &None).unwrap();
+
+        handle_add_nodes(&mut state, root_field(&request)).unwrap();
+        handle_add_nodes(&mut state, root_field(&request)).unwrap();
+        handle_add_nodes(&mut state, root_field(&label)).unwrap();
+        assert_eq!(state.prs[0].review_requests, ["alice", "org/team"]);
+        assert_eq!(state.prs[0].labels, ["perf"]);
+
+        let error = handle_add_nodes(&mut state, root_field(&assign)).unwrap_err();
+        assert!(error.contains("cannot be used in `addAssigneesToAssignable`"));
+    }
 }